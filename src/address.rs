@@ -0,0 +1,166 @@
+use crate::error::TokenFetchError;
+use log::warn;
+use std::io::BufRead;
+
+/// Whether `value` has the shape of an address: `0x` followed by 40 hex digits.
+pub fn looks_like_address(value: &str) -> bool {
+    value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .is_some_and(|hex| hex.len() == 40 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Whether `value` has the shape of a transaction hash: `0x` followed by 64 hex digits.
+pub fn looks_like_tx_hash(value: &str) -> bool {
+    value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .is_some_and(|hex| hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Adds the `0x` prefix to a bare 40-digit address or 64-digit transaction hash.
+fn with_prefix(value: &str) -> String {
+    let bare = matches!(value.len(), 40 | 64) && value.chars().all(|c| c.is_ascii_hexdigit());
+    if bare {
+        format!("0x{}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+/// Whether `value` is an address or transaction hash, with or without its `0x` prefix.
+pub fn looks_like_target(value: &str) -> bool {
+    let value = with_prefix(value);
+    looks_like_address(&value) || looks_like_tx_hash(&value)
+}
+
+/// Checks an address (or deployment transaction hash) before any work is done with it,
+/// returning it with a `0x` prefix.
+///
+/// A mixed-case address carries an EIP-55 checksum, and one that doesn't match
+/// usually means the address was mangled when copied. That's a warning, or an error
+/// with `strict`.
+pub fn normalize(value: &str, strict: bool) -> Result<String, TokenFetchError> {
+    let value = value.trim();
+    let prefixed = with_prefix(value);
+    if looks_like_tx_hash(&prefixed) {
+        return Ok(format!("0x{}", &prefixed[2..]));
+    }
+    if !looks_like_address(&prefixed) {
+        return Err(TokenFetchError::InvalidInput(format!("Not a contract address or transaction hash: {}", value)));
+    }
+
+    let address = format!("0x{}", &prefixed[2..]);
+    let hex = &address[2..];
+    let mixed_case = hex.chars().any(|c| c.is_ascii_lowercase()) && hex.chars().any(|c| c.is_ascii_uppercase());
+    if mixed_case {
+        let checksummed = address
+            .parse::<alloy_primitives::Address>()
+            .map_err(|e| TokenFetchError::InvalidInput(format!("Invalid address {}: {}", value, e)))?
+            .to_checksum(None);
+        if checksummed != address {
+            let message = format!("Address {} fails its EIP-55 checksum, it may have been mangled when copied", value);
+            if strict {
+                return Err(TokenFetchError::InvalidInput(message));
+            }
+            warn!("{}", message);
+        }
+    }
+    Ok(address)
+}
+
+/// Splits an explorer link such as `https://etherscan.io/address/0x...#code` into the
+/// explorer's host and the address (or transaction hash, for `/tx/` links) it points at.
+/// The scheme may be left out. Returns `None` for anything that isn't such a link.
+pub fn parse_explorer_url(input: &str) -> Option<(String, String)> {
+    let url = if input.contains("://") {
+        reqwest::Url::parse(input).ok()?
+    } else if input.contains('/') {
+        reqwest::Url::parse(&format!("https://{}", input)).ok()?
+    } else {
+        return None;
+    };
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+
+    let host = url.host_str()?.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host).to_string();
+    let mut segments = url.path_segments()?;
+    while let Some(segment) = segments.next() {
+        if matches!(segment, "address" | "token" | "tx") {
+            let value = segments.next()?;
+            if looks_like_address(value) || looks_like_tx_hash(value) {
+                return Some((host, value.to_string()));
+            }
+        }
+    }
+    None
+}
+
+/// Reads one address (or deployment transaction hash) per line, ignoring blank lines and `#` comments.
+///
+/// Every malformed line is reported together, with line numbers, so a bad list fails
+/// before any work starts.
+pub fn parse_address_list(reader: impl BufRead, source: &str) -> Result<Vec<String>, TokenFetchError> {
+    let mut addresses = Vec::new();
+    let mut malformed = Vec::new();
+
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line?;
+        let entry = match line.split_once('#') {
+            Some((before, _)) => before.trim(),
+            None => line.trim(),
+        };
+        if entry.is_empty() {
+            continue;
+        }
+
+        if looks_like_target(entry) || crate::ens::looks_like_name(entry) {
+            addresses.push(entry.to_string());
+        } else {
+            malformed.push(format!("{}:{}: not an address: {}", source, idx + 1, entry));
+        }
+    }
+
+    if !malformed.is_empty() {
+        return Err(TokenFetchError::InvalidInput(format!("Malformed address list:\n  {}", malformed.join("\n  "))));
+    }
+
+    Ok(addresses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_addresses_up_front() {
+        // The EIP-55 example address
+        let address = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        assert_eq!(normalize(address, true).unwrap(), address);
+        assert_eq!(normalize(&address[2..], true).unwrap(), address);
+        assert_eq!(normalize(&address.to_lowercase(), true).unwrap(), address.to_lowercase());
+
+        let mangled = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD";
+        assert_eq!(normalize(mangled, false).unwrap(), mangled);
+        assert!(normalize(mangled, true).is_err());
+
+        assert!(normalize("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA", false).is_err());
+    }
+
+    #[test]
+    fn reads_explorer_links() {
+        let address = "0x5b38da6a701c568545dcfcb03fcb875f56beddc4";
+        for link in [
+            format!("https://etherscan.io/address/{}#code", address),
+            format!("https://www.etherscan.io/token/{}?a=0x1", address),
+            format!("etherscan.io/address/{}", address),
+        ] {
+            assert_eq!(parse_explorer_url(&link), Some(("etherscan.io".to_string(), address.to_string())), "{}", link);
+        }
+
+        assert_eq!(parse_explorer_url("eth"), None);
+        assert_eq!(parse_explorer_url("https://etherscan.io/blocks"), None);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::error::TokenFetchError;
+use log::{info, warn};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Every entry's modification time, 1980-01-01 (the earliest a zip can record), so that
+/// packing the same files twice gives the same bytes.
+pub const MTIME: u64 = 315_532_800;
+
+/// The kind of archive `--archive` writes, chosen by its extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Zip,
+    TarGz,
+}
+
+impl Format {
+    pub fn of(path: &Path) -> Result<Self, TokenFetchError> {
+        let name = path.file_name().map(|name| name.to_string_lossy().to_lowercase()).unwrap_or_default();
+        if name.ends_with(".zip") {
+            Ok(Format::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Ok(Format::TarGz)
+        } else {
+            Err(TokenFetchError::InvalidInput(format!("Can't tell the archive format of {}, name it .zip, .tar.gz or .tgz", path.display())))
+        }
+    }
+
+    /// The archive's file name without its extension.
+    pub fn stem(self, path: &Path) -> String {
+        let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        let extension = match self {
+            Format::Zip => ".zip".len(),
+            Format::TarGz if name.to_lowercase().ends_with(".tgz") => ".tgz".len(),
+            Format::TarGz => ".tar.gz".len(),
+        };
+        name[..name.len() - extension].to_string()
+    }
+}
+
+/// A file or directory to pack.
+struct Entry {
+    /// Relative to the project, with `/` separators
+    name: String,
+    path: PathBuf,
+    is_dir: bool,
+}
+
+/// The files and directories under `dir`, sorted. Git metadata is left out: it's
+/// different on every run and isn't part of the source.
+fn entries(dir: &Path) -> Result<Vec<Entry>, TokenFetchError> {
+    let mut entries = Vec::new();
+    let walk = walkdir::WalkDir::new(dir).min_depth(1).sort_by_file_name().into_iter();
+    for entry in walk.filter_entry(|entry| entry.file_name() != ".git") {
+        let entry = entry.map_err(std::io::Error::from)?;
+        if entry.file_type().is_symlink() {
+            warn!("Not archiving the symlink {}", entry.path().display());
+            continue;
+        }
+        let relative = entry.path().strip_prefix(dir).map_err(TokenFetchError::other)?;
+        let name = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+        entries.push(Entry { name, path: entry.path().to_path_buf(), is_dir: entry.file_type().is_dir() });
+    }
+    Ok(entries)
+}
+
+/// Packs the project at `dir` into `archive`, every path under one top-level folder named
+/// like `dir`. Files are streamed in one at a time, and the archive is written next to
+/// its destination first, so a failure doesn't leave half of one behind.
+pub fn pack(dir: &Path, archive: &Path) -> Result<(), TokenFetchError> {
+    let format = Format::of(archive)?;
+    let root = dir.file_name().ok_or("The project directory has no name")?.to_string_lossy().into_owned();
+    let entries = entries(dir)?;
+
+    if let Some(parent) = archive.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut partial = archive.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let packed = File::create(&partial).map_err(TokenFetchError::from).and_then(|file| {
+        let out = BufWriter::new(file);
+        match format {
+            Format::Zip => pack_zip(out, &root, &entries),
+            Format::TarGz => pack_tar_gz(out, &root, &entries),
+        }
+    });
+    if let Err(e) = packed.and_then(|()| Ok(std::fs::rename(&partial, archive)?)) {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e);
+    }
+    crate::summary::file_written(archive);
+    info!("Packed {} files into {}", entries.iter().filter(|entry| !entry.is_dir).count(), archive.display());
+    Ok(())
+}
+
+fn pack_zip(out: impl Write + std::io::Seek, root: &str, entries: &[Entry]) -> Result<(), TokenFetchError> {
+    let mut zip = zip::ZipWriter::new(out);
+    let options = zip::write::SimpleFileOptions::DEFAULT;
+    zip.add_directory(format!("{}/", root), options.unix_permissions(0o755)).map_err(TokenFetchError::other)?;
+    for Entry { name, path, is_dir } in entries {
+        if *is_dir {
+            zip.add_directory(format!("{}/{}/", root, name), options.unix_permissions(0o755)).map_err(TokenFetchError::other)?;
+        } else {
+            zip.start_file(format!("{}/{}", root, name), options.unix_permissions(0o644)).map_err(TokenFetchError::other)?;
+            std::io::copy(&mut File::open(path)?, &mut zip)?;
+        }
+    }
+    zip.finish().map_err(TokenFetchError::other)?.flush()?;
+    Ok(())
+}
+
+fn pack_tar_gz(out: impl Write, root: &str, entries: &[Entry]) -> Result<(), TokenFetchError> {
+    // The gzip header's own timestamp and file name are left empty
+    let gz = flate2::GzBuilder::new().mtime(0).write(out, flate2::Compression::default());
+    let mut tar = tar::Builder::new(gz);
+    let header = |kind: tar::EntryType, mode: u32, size: u64| {
+        let mut header = tar::Header::new_ustar();
+        header.set_entry_type(kind);
+        header.set_mode(mode);
+        header.set_size(size);
+        header.set_mtime(MTIME);
+        header.set_uid(0);
+        header.set_gid(0);
+        header
+    };
+    tar.append_data(&mut header(tar::EntryType::Directory, 0o755, 0), format!("{}/", root), std::io::empty())?;
+    for Entry { name, path, is_dir } in entries {
+        if *is_dir {
+            tar.append_data(&mut header(tar::EntryType::Directory, 0o755, 0), format!("{}/{}/", root, name), std::io::empty())?;
+        } else {
+            let file = File::open(path)?;
+            let mut header = header(tar::EntryType::Regular, 0o644, file.metadata()?.len());
+            tar.append_data(&mut header, format!("{}/{}", root, name), file)?;
+        }
+    }
+    tar.into_inner()?.finish()?.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn packs_the_same_files_into_the_same_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("Token");
+        std::fs::create_dir_all(project.join("src/@openzeppelin")).unwrap();
+        std::fs::create_dir_all(project.join(".git")).unwrap();
+        std::fs::write(project.join("src/Token.sol"), "contract Token {}").unwrap();
+        std::fs::write(project.join("src/@openzeppelin/ERC20.sol"), "contract ERC20 {}").unwrap();
+        std::fs::write(project.join(".git/HEAD"), "ref: refs/heads/main").unwrap();
+
+        for name in ["out.zip", "out.tar.gz"] {
+            let first = dir.path().join(format!("first-{}", name));
+            pack(&project, &first).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            std::fs::write(project.join("src/Token.sol"), "contract Token {}").unwrap();
+            let second = dir.path().join(format!("second-{}", name));
+            pack(&project, &second).unwrap();
+            assert_eq!(std::fs::read(&first).unwrap(), std::fs::read(&second).unwrap(), "{}", name);
+            assert!(!dir.path().join(format!("second-{}.partial", name)).exists());
+        }
+
+        let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(File::open(dir.path().join("first-out.tar.gz")).unwrap()));
+        let mut names = Vec::new();
+        for entry in tar.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let name = entry.path().unwrap().display().to_string();
+            if name == "Token/src/Token.sol" {
+                let mut content = String::new();
+                entry.read_to_string(&mut content).unwrap();
+                assert_eq!(content, "contract Token {}");
+            }
+            names.push(name);
+        }
+        assert_eq!(names, ["Token/", "Token/src/", "Token/src/@openzeppelin/", "Token/src/@openzeppelin/ERC20.sol", "Token/src/Token.sol"]);
+
+        assert_eq!(Format::of(Path::new("a/Token.TGZ")).unwrap().stem(Path::new("a/Token.TGZ")), "Token");
+        assert_eq!(Format::TarGz.stem(Path::new("snapshot.tar.gz")), "snapshot");
+        assert!(Format::of(Path::new("out.rar")).is_err());
+    }
+}
@@ -0,0 +1,203 @@
+use crate::error::TokenFetchError;
+use crate::response::SourceCodeResult;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use log::{debug, info, warn};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Returns the cache directory, e.g. `~/.cache/tokenfetch`.
+pub fn dir() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("tokenfetch"))
+}
+
+/// Returns the cache file for a contract, e.g. `~/.cache/tokenfetch/1/0xabc....json.gz`.
+fn entry_path(chain_id: i32, address: &str) -> Option<PathBuf> {
+    let mut path = dir()?;
+    path.push(chain_id.to_string());
+    path.push(format!("{}.json.gz", address.to_lowercase()));
+    Some(path)
+}
+
+/// Where earlier versions kept the entry uncompressed, still read until it's replaced.
+fn legacy_path(path: &Path) -> PathBuf {
+    path.with_extension("")
+}
+
+/// Reads the entry at `path`, decompressing it, or the uncompressed one it replaces.
+fn read_entry(path: &Path) -> std::io::Result<String> {
+    match std::fs::File::open(path) {
+        Ok(file) => {
+            let mut contents = String::new();
+            GzDecoder::new(file).read_to_string(&mut contents)?;
+            Ok(contents)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => std::fs::read_to_string(legacy_path(path)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Writes `contents` compressed to `path`, through a temporary file so a crash never
+/// leaves a truncated entry behind, and drops the uncompressed entry it replaces.
+fn write_entry(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("gz.tmp");
+    let mut encoder = GzEncoder::new(std::fs::File::create(&tmp)?, flate2::Compression::default());
+    encoder.write_all(contents)?;
+    encoder.finish()?.sync_all()?;
+    std::fs::rename(&tmp, path)?;
+    match std::fs::remove_file(legacy_path(path)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Reads the cache entry for a contract with its age, treating entries older than
+/// `max_age` as misses.
+fn entry(chain_id: i32, address: &str, max_age: Option<Duration>) -> Option<(PathBuf, u64, serde_json::Value)> {
+    let path = entry_path(chain_id, address)?;
+    let Ok(contents) = read_entry(&path) else {
+        debug!("No cache entry at {:?}", path);
+        return None;
+    };
+
+    let entry: serde_json::Value = match serde_json::from_str(&contents) {
+        Ok(entry) => entry,
+        Err(e) => {
+            warn!("Ignoring unreadable cache entry {:?}: {}", path, e);
+            return None;
+        }
+    };
+
+    let fetched_at = entry["fetched_at"].as_u64()?;
+    let age = now().saturating_sub(fetched_at);
+    if let Some(max_age) = max_age {
+        if age > max_age.as_secs() {
+            info!("Cache entry {:?} is {}s old, treating it as missing", path, age);
+            return None;
+        }
+    }
+    Some((path, age, entry["response"].clone()))
+}
+
+/// Looks up a cached getsourcecode response, treating entries older than `max_age` as misses.
+pub fn load(chain_id: i32, address: &str, max_age: Option<Duration>) -> Option<serde_json::Value> {
+    let (path, age, response) = entry(chain_id, address, max_age)?;
+    info!("Using cached response from {:?} ({}s old)", path, age);
+    Some(response)
+}
+
+/// Whether `load` would find a response.
+pub fn contains(chain_id: i32, address: &str, max_age: Option<Duration>) -> bool {
+    entry(chain_id, address, max_age).is_some()
+}
+
+/// Stores a successful response. Responses without verified source are never cached.
+pub fn store(chain_id: i32, address: &str, response: &serde_json::Value) -> Result<(), TokenFetchError> {
+    if !is_cacheable(response) {
+        debug!("Not caching the response for {}, it has no verified source", address);
+        return Ok(());
+    }
+    let Some(path) = entry_path(chain_id, address) else {
+        warn!("No cache directory available on this platform, skipping cache");
+        return Ok(());
+    };
+
+    let entry = serde_json::json!({
+        "fetched_at": now(),
+        "response": response,
+    });
+    write_entry(&path, &serde_json::to_vec(&entry)?)?;
+    info!("Cached response at {:?}", path);
+
+    Ok(())
+}
+
+fn is_cacheable(response: &serde_json::Value) -> bool {
+    response["status"].as_str() == Some("1")
+        && SourceCodeResult::from_response(response).is_ok_and(|result| !result.source_code.is_empty())
+}
+
+/// How much the cache holds.
+#[derive(Debug, Default, PartialEq)]
+pub struct Usage {
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+fn usage_of(dir: &Path) -> Usage {
+    let mut usage = Usage::default();
+    for entry in walkdir::WalkDir::new(dir).into_iter().flatten().filter(|entry| entry.file_type().is_file()) {
+        usage.entries += 1;
+        usage.bytes += entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+    }
+    usage
+}
+
+/// The entries in the cache and their size on disk.
+pub fn usage() -> Usage {
+    dir().map(|dir| usage_of(&dir)).unwrap_or_default()
+}
+
+/// Removes every cache entry, returning what they took up.
+pub fn clear() -> Result<Usage, TokenFetchError> {
+    let Some(dir) = dir() else { return Ok(Usage::default()) };
+    let usage = usage_of(&dir);
+    match std::fs::remove_dir_all(&dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(usage),
+    }
+}
+
+/// `bytes` in the largest unit that keeps it at or above 1, e.g. `1.5 MiB`.
+pub fn format_size(bytes: u64) -> String {
+    let units = ["KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = "B";
+    for next in units {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+    match unit {
+        "B" => format!("{} B", bytes),
+        unit => format!("{:.1} {}", size, unit),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_are_compressed_and_legacy_ones_still_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("1/0xabc.json.gz");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(legacy_path(&path), "{\"legacy\":true}").unwrap();
+        assert_eq!(read_entry(&path).unwrap(), "{\"legacy\":true}");
+
+        let contents = format!("{{\"source\":\"{}\"}}", "contract Token {}".repeat(100));
+        write_entry(&path, contents.as_bytes()).unwrap();
+        assert_eq!(read_entry(&path).unwrap(), contents);
+        assert!(!legacy_path(&path).exists());
+        let usage = usage_of(dir.path());
+        assert_eq!(usage.entries, 1);
+        assert!(usage.bytes < contents.len() as u64 / 10, "{:?}", usage);
+
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536 * 1024), "1.5 MiB");
+    }
+}
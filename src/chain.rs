@@ -0,0 +1,312 @@
+use crate::config;
+use crate::error::TokenFetchError;
+use log::info;
+use std::borrow::Cow;
+
+/// A chain and the Etherscan-compatible explorer serving it. The built-in chains borrow
+/// their names, custom ones own them.
+#[derive(Debug)]
+pub struct ChainConfig {
+    /// Name the chain is selected by on the command line
+    pub name: Cow<'static, str>,
+    /// Other names accepted for the chain
+    pub aliases: &'static [&'static str],
+    /// Host of the chain's block explorer website, for reading explorer links
+    pub explorer_host: Cow<'static, str>,
+    pub api_key_env: Cow<'static, str>,
+    pub api_url: Cow<'static, str>,
+    pub chain_id: i32,
+    /// Requests per second allowed on the explorer's free tier
+    pub rate_limit: u32,
+    /// Public JSON-RPC endpoint for the features that can't work without one, when neither
+    /// --rpc-url nor the config file gives one
+    pub rpc_url: Option<&'static str>,
+}
+
+/// Every supported chain.
+pub const CHAINS: &[ChainConfig] = &[
+    ChainConfig {
+        name: Cow::Borrowed("eth"),
+        aliases: &["ethereum", "mainnet"],
+        explorer_host: Cow::Borrowed("etherscan.io"),
+        api_key_env: Cow::Borrowed("ETHERSCAN_API_KEY"),
+        api_url: Cow::Borrowed("https://api.etherscan.io/api"),
+        chain_id: 1,
+        rate_limit: 5,
+        rpc_url: Some("https://ethereum-rpc.publicnode.com"),
+    },
+    ChainConfig {
+        name: Cow::Borrowed("base"),
+        aliases: &[],
+        explorer_host: Cow::Borrowed("basescan.org"),
+        api_key_env: Cow::Borrowed("BASESCAN_API_KEY"),
+        api_url: Cow::Borrowed("https://api.basescan.org/api"),
+        chain_id: 8453,
+        rate_limit: 5,
+        rpc_url: Some("https://mainnet.base.org"),
+    },
+];
+
+/// The chains names are looked up in: the built-in ones and the custom chains added to
+/// it. [`register_custom_chains`] sets up the process-wide one the CLI and the free
+/// functions below use; an embedder serving several setups can keep one of its own each.
+#[derive(Debug, Default)]
+pub struct ChainRegistry {
+    custom: Vec<ChainConfig>,
+}
+
+/// The registry with the chains from `--custom-chain` and the config file, set once
+/// before any command runs.
+static REGISTRY: std::sync::OnceLock<ChainRegistry> = std::sync::OnceLock::new();
+
+/// The process-wide registry, with only the built-in chains until custom ones are registered.
+fn registry() -> &'static ChainRegistry {
+    static BUILT_IN: ChainRegistry = ChainRegistry { custom: Vec::new() };
+    REGISTRY.get().unwrap_or(&BUILT_IN)
+}
+
+/// The built-in chains followed by the custom ones.
+pub fn chains() -> impl Iterator<Item = &'static ChainConfig> {
+    registry().chains()
+}
+
+impl ChainConfig {
+    /// A chain served by an Etherscan-compatible explorer at `api_url`.
+    pub fn custom(
+        name: &str,
+        api_url: &str,
+        chain_id: i32,
+        api_key_env: Option<&str>,
+        explorer_host: Option<&str>,
+        rate_limit: Option<u32>,
+    ) -> Result<ChainConfig, TokenFetchError> {
+        let invalid = |message: String| TokenFetchError::InvalidInput(format!("Custom chain {}: {}", name, message));
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(invalid("the name may only contain letters, digits, - and _".into()));
+        }
+        if ChainRegistry::new().get(name).is_some() {
+            return Err(invalid("a chain with that name already exists".into()));
+        }
+        let url = reqwest::Url::parse(api_url).map_err(|e| invalid(format!("invalid url {}: {}", api_url, e)))?;
+        let host = url.host_str().ok_or_else(|| invalid(format!("url {} has no host", api_url)))?;
+
+        let api_key_env = match api_key_env {
+            Some(env) => env.to_string(),
+            None => format!("{}_API_KEY", name.to_uppercase().replace('-', "_")),
+        };
+        // The website usually lives on the API host without its `api.` prefix
+        let explorer_host = explorer_host.unwrap_or_else(|| host.strip_prefix("api.").unwrap_or(host));
+        Ok(ChainConfig {
+            name: Cow::Owned(name.to_lowercase()),
+            aliases: &[],
+            explorer_host: Cow::Owned(explorer_host.to_lowercase()),
+            api_key_env: Cow::Owned(api_key_env),
+            api_url: Cow::Owned(api_url.to_string()),
+            chain_id,
+            rate_limit: rate_limit.unwrap_or(5),
+            rpc_url: None,
+        })
+    }
+}
+
+/// Parses a `--custom-chain` spec such as
+/// `name=foo,url=https://api.fooscan.io/api,id=9999,key_env=FOO_API_KEY`.
+pub fn parse_custom_chain(spec: &str) -> Result<ChainConfig, TokenFetchError> {
+    let invalid = |message: String| TokenFetchError::InvalidInput(format!("Invalid --custom-chain {}: {}", spec, message));
+    let mut fields = std::collections::HashMap::new();
+    for field in spec.split(',').filter(|field| !field.trim().is_empty()) {
+        let (key, value) = field.split_once('=').ok_or_else(|| invalid(format!("expected key=value, got {}", field)))?;
+        match key.trim() {
+            key @ ("name" | "url" | "id" | "key_env" | "explorer" | "rps") => fields.insert(key, value.trim()),
+            key => return Err(invalid(format!("unknown key {}, expected name, url, id, key_env, explorer or rps", key))),
+        };
+    }
+
+    let required = |key: &str| fields.get(key).copied().ok_or_else(|| invalid(format!("{} is required", key)));
+    let chain_id = required("id")?.parse().map_err(|_| invalid("id must be a number".into()))?;
+    let rate_limit = match fields.get("rps") {
+        Some(rps) => Some(rps.parse().ok().filter(|rps| *rps > 0).ok_or_else(|| invalid("rps must be a positive number".into()))?),
+        None => None,
+    };
+    ChainConfig::custom(
+        required("name")?,
+        required("url")?,
+        chain_id,
+        fields.get("key_env").copied(),
+        fields.get("explorer").copied(),
+        rate_limit,
+    )
+}
+
+impl ChainRegistry {
+    /// A registry of the built-in chains only.
+    pub fn new() -> Self {
+        ChainRegistry::default()
+    }
+
+    /// The built-in chains along with those from `--custom-chain` specs and the config
+    /// file's `[chains.<name>]` sections that have an `api_url`, with the specs winning on a
+    /// name both define.
+    pub fn with_custom_chains(specs: &[String], user_config: &config::Config) -> Result<Self, TokenFetchError> {
+        let mut registry = ChainRegistry::new();
+        for spec in specs {
+            registry.add(parse_custom_chain(spec)?)?;
+        }
+
+        let mut names: Vec<&String> = user_config.chains.keys().collect();
+        names.sort();
+        for name in names {
+            let settings = &user_config.chains[name];
+            let Some(api_url) = &settings.api_url else { continue };
+            if registry.custom.iter().any(|chain| chain.name == name.as_str()) {
+                continue;
+            }
+            let chain_id = settings.chain_id.ok_or_else(|| {
+                TokenFetchError::InvalidInput(format!("Custom chain {} in the config file needs a chain_id", name))
+            })?;
+            registry.add(ChainConfig::custom(
+                name,
+                api_url,
+                chain_id,
+                settings.api_key_env.as_deref(),
+                settings.explorer_host.as_deref(),
+                settings.rate_limit,
+            )?)?;
+        }
+        Ok(registry)
+    }
+
+    /// Adds a custom chain, whose name may not be taken already.
+    pub fn add(&mut self, chain: ChainConfig) -> Result<(), TokenFetchError> {
+        if self.get(&chain.name).is_some() {
+            return Err(TokenFetchError::InvalidInput(format!("Custom chain {} is defined twice", chain.name)));
+        }
+        info!("Using custom chain {} (chain id {}) at {}", chain.name, chain.chain_id, chain.api_url);
+        self.custom.push(chain);
+        Ok(())
+    }
+
+    /// The built-in chains followed by the custom ones.
+    pub fn chains(&self) -> impl Iterator<Item = &ChainConfig> {
+        CHAINS.iter().chain(&self.custom)
+    }
+
+    /// The chain named `chain` or one of its aliases, ignoring case.
+    pub fn get(&self, chain: &str) -> Option<&ChainConfig> {
+        self.chains().find(|config| {
+            config.name.eq_ignore_ascii_case(chain) || config.aliases.iter().any(|alias| alias.eq_ignore_ascii_case(chain))
+        })
+    }
+
+    /// Like [`ChainRegistry::get`], but an unknown chain is an error naming the supported
+    /// chains and, for a likely typo, the one that was probably meant.
+    pub fn chain_config(&self, chain: &str) -> Result<&ChainConfig, TokenFetchError> {
+        self.get(chain).ok_or_else(|| {
+            let input = chain.to_lowercase();
+            let mut suggestion = None;
+            let mut closest = 3;
+            for config in self.chains() {
+                for name in std::iter::once(config.name.as_ref()).chain(config.aliases.iter().copied()) {
+                    let distance = edit_distance(&input, name);
+                    if distance < closest {
+                        closest = distance;
+                        suggestion = Some(config.name.to_string());
+                    }
+                }
+            }
+
+            TokenFetchError::UnsupportedChain {
+                chain: chain.to_string(),
+                suggestion,
+                supported: self.chains().map(|config| config.name.to_string()).collect(),
+            }
+        })
+    }
+}
+
+/// Registers the chains from `--custom-chain` flags and the config file's `[chains.<name>]`
+/// sections that have an `api_url` in the process-wide registry, with the flags winning on
+/// a name both define.
+pub fn register_custom_chains(specs: &[String], user_config: &config::Config) -> Result<(), TokenFetchError> {
+    let registry = ChainRegistry::with_custom_chains(specs, user_config)?;
+    REGISTRY.set(registry).map_err(|_| TokenFetchError::Other("Custom chains are already registered".into()))
+}
+
+/// The chain named `chain` or one of its aliases, ignoring case.
+pub fn get_chain_config(chain: &str) -> Option<&'static ChainConfig> {
+    registry().get(chain)
+}
+
+/// Like [`get_chain_config`], but an unknown chain is an error naming the supported
+/// chains and, for a likely typo, the one that was probably meant.
+pub fn chain_config(chain: &str) -> Result<&'static ChainConfig, TokenFetchError> {
+    registry().chain_config(chain)
+}
+
+/// Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_chains_are_parsed_from_a_spec() {
+        let chain = parse_custom_chain("name=Foo,url=https://api.fooscan.io/api,id=9999,rps=2").unwrap();
+        assert_eq!((chain.name.as_ref(), chain.chain_id, chain.rate_limit), ("foo", 9999, 2));
+        assert_eq!(chain.api_key_env, "FOO_API_KEY");
+        assert_eq!(chain.explorer_host, "fooscan.io");
+
+        let chain = parse_custom_chain("name=bar,url=https://bar.example/api,id=1,key_env=BAR_KEY,explorer=scan.bar.example").unwrap();
+        assert_eq!((chain.api_key_env.as_ref(), chain.explorer_host.as_ref()), ("BAR_KEY", "scan.bar.example"));
+
+        let err = parse_custom_chain("name=foo,url=https://api.fooscan.io/api").unwrap_err();
+        assert_eq!(err.to_string(), "Invalid --custom-chain name=foo,url=https://api.fooscan.io/api: id is required");
+        assert!(parse_custom_chain("name=eth,url=https://example.com,id=1").is_err());
+        assert!(parse_custom_chain("name=foo,url=https://example.com,id=1,colour=red").is_err());
+    }
+
+    #[test]
+    fn registries_keep_their_custom_chains_to_themselves() {
+        let mut registry = ChainRegistry::new();
+        registry.add(parse_custom_chain("name=local,url=http://127.0.0.1:8545/api,id=31337").unwrap()).unwrap();
+        assert_eq!(registry.chain_config("local").unwrap().chain_id, 31337);
+        assert_eq!(registry.get("mainnet").unwrap().name, "eth");
+        assert!(get_chain_config("local").is_none());
+
+        let err = registry.add(parse_custom_chain("name=LOCAL,url=http://127.0.0.1:8545/api,id=1").unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), "Custom chain local is defined twice");
+        let err = registry.chain_config("locl").unwrap_err();
+        assert!(err.to_string().contains("(did you mean `local`?)"), "{}", err);
+    }
+
+    #[test]
+    fn unknown_chains_get_suggestions() {
+        assert_eq!(get_chain_config("Mainnet").unwrap().name, "eth");
+
+        assert_eq!(
+            chain_config("bsae").unwrap_err().to_string(),
+            "Unsupported chain `bsae` (did you mean `base`?), supported chains are: eth, base"
+        );
+        assert_eq!(
+            chain_config("etherium").unwrap_err().to_string(),
+            "Unsupported chain `etherium` (did you mean `eth`?), supported chains are: eth, base"
+        );
+        assert_eq!(
+            chain_config("polygon").unwrap_err().to_string(),
+            "Unsupported chain `polygon`, supported chains are: eth, base"
+        );
+    }
+}
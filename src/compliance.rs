@@ -0,0 +1,420 @@
+use crate::error::TokenFetchError;
+use crate::standard::Standard;
+use alloy_primitives::{Address, U256};
+use semver::Version;
+
+/// The oldest solc forge-std compiles with, and so the oldest the suite can be built with.
+const OLDEST_SOLC: Version = Version::new(0, 6, 2);
+
+/// The solc a suite is built with in its own foundry profile, when the pinned one is too
+/// old for it.
+pub const PROFILE_SOLC: &str = "0.8.24";
+
+/// What a compliance suite is generated for.
+pub struct Suite<'a> {
+    /// Contract name, used to name the test contracts
+    pub name: &'a str,
+    pub address: &'a str,
+    pub standard: Standard,
+    pub extensions: &'a [&'static str],
+    /// Addresses that may hold the token, tried in order
+    pub holders: &'a [String],
+    /// For ERC-721, the token to transfer around
+    pub token_id: Option<U256>,
+    /// Environment variable with the RPC URL the test forks from
+    pub rpc_env: &'a str,
+    pub fork_block: Option<u64>,
+}
+
+/// Whether the pinned solc `version` can build a suite, which only needs forge-std to be
+/// built with it: the suite calls the token with encoded calldata instead of importing
+/// its sources, and sticks to what every solc from 0.6.2 on accepts.
+pub fn builds_with(version: Option<&str>) -> bool {
+    version.and_then(|version| Version::parse(version).ok()).is_none_or(|version| version >= OLDEST_SOLC)
+}
+
+/// Addresses among a contract's ABI-encoded constructor arguments, such as the account
+/// an initial supply was minted to. Words too small to be an address, like a decimals
+/// count, are left out.
+pub fn constructor_addresses(arguments: &str) -> Vec<String> {
+    let Ok(bytes) = alloy_primitives::hex::decode(arguments.trim_start_matches("0x")) else {
+        return Vec::new();
+    };
+    let mut addresses: Vec<String> = Vec::new();
+    for word in bytes.chunks_exact(32) {
+        let (padding, address) = word.split_at(12);
+        if padding.iter().all(|byte| *byte == 0) && address[..4].iter().any(|byte| *byte != 0) {
+            let address = Address::from_slice(address).to_checksum(None);
+            if !addresses.contains(&address) {
+                addresses.push(address);
+            }
+        }
+    }
+    addresses
+}
+
+/// Renders the forge test exercising `suite.standard` against the deployment. Only
+/// ERC-20 and ERC-721 have a suite.
+pub fn render(suite: &Suite) -> Result<String, TokenFetchError> {
+    // Solidity only accepts checksummed address literals
+    let address = suite.address.parse::<Address>().map_err(TokenFetchError::other)?.to_checksum(None);
+    let holders = suite
+        .holders
+        .iter()
+        .map(|holder| Ok(holder.parse::<Address>().map_err(TokenFetchError::other)?.to_checksum(None)))
+        .collect::<Result<Vec<_>, TokenFetchError>>()?;
+    let fork = match suite.fork_block {
+        Some(block) => format!("vm.createSelectFork(vm.envString(\"{}\"), {});", suite.rpc_env, block),
+        None => format!("vm.createSelectFork(vm.envString(\"{}\"));", suite.rpc_env),
+    };
+    let body = match suite.standard {
+        Standard::Erc20 => erc20(suite.name, &address, &holders, &fork),
+        Standard::Erc721 => erc721(suite, &address, &holders, &fork),
+        Standard::Erc1155 => return Err("There is no compliance suite for ERC-1155 tokens".into()),
+    };
+
+    Ok(format!(
+        r#"// SPDX-License-Identifier: UNLICENSED
+// Generated by TokenFetch
+pragma solidity >=0.6.2 <0.9.0;
+
+import {{Test}} from "forge-std/Test.sol";
+
+{body}"#
+    ))
+}
+
+/// A fixed-size array literal of `values`, as `[a, b]`, with the first one converted to
+/// `ty` so solc gives the array that element type.
+fn array_literal(ty: &str, values: &[String]) -> String {
+    let mut values = values.to_vec();
+    values[0] = format!("{}({})", ty, values[0]);
+    format!("[{}]", values.join(", "))
+}
+
+fn erc20(name: &str, address: &str, holders: &[String], fork: &str) -> String {
+    let find_holder = match holders.is_empty() {
+        true => String::new(),
+        false => format!(
+            r#"        address[{len}] memory candidates = {candidates};
+        for (uint256 i = 0; i < candidates.length; i++) {{
+            if (_balanceOf(candidates[i]) > 0) {{
+                holder = candidates[i];
+                break;
+            }}
+        }}
+"#,
+            len = holders.len(),
+            candidates = array_literal("address", holders),
+        ),
+    };
+
+    format!(
+        r#"/// ERC-20 compliance checks for {name}, run against a fork of the chain it is deployed on.
+/// The token is called with encoded calldata, so one whose transfer returns nothing (like
+/// USDT's) is tested too.
+contract {name}ComplianceTest is Test {{
+    event Transfer(address indexed from, address indexed to, uint256 value);
+    event Approval(address indexed owner, address indexed spender, uint256 value);
+
+    address internal token = {address};
+    address internal holder;
+    address internal recipient;
+    address internal spender;
+    uint256 internal amount;
+
+    function setUp() public {{
+        {fork}
+        recipient = makeAddr("recipient");
+        spender = makeAddr("spender");
+{find_holder}        if (holder == address(0)) {{
+            // No known holder has a balance, so give one, which works for tokens that
+            // keep balances in a mapping
+            holder = makeAddr("holder");
+            deal(token, holder, 1000000);
+        }}
+        amount = _balanceOf(holder) / 2;
+        if (amount == 0) {{
+            amount = 1;
+        }}
+    }}
+
+    function _balanceOf(address account) internal view returns (uint256) {{
+        (bool ok, bytes memory data) = token.staticcall(abi.encodeWithSignature("balanceOf(address)", account));
+        require(ok && data.length >= 32, "balanceOf failed");
+        return abi.decode(data, (uint256));
+    }}
+
+    function _allowance(address owner, address approved) internal view returns (uint256) {{
+        (bool ok, bytes memory data) = token.staticcall(abi.encodeWithSignature("allowance(address,address)", owner, approved));
+        require(ok && data.length >= 32, "allowance failed");
+        return abi.decode(data, (uint256));
+    }}
+
+    /// Whether `from` calling the token with `data` succeeds: it doesn't revert, and
+    /// returns true or nothing.
+    function _succeeds(address from, bytes memory data) internal returns (bool) {{
+        vm.prank(from);
+        (bool ok, bytes memory result) = token.call(data);
+        return ok && (result.length == 0 || abi.decode(result, (bool)));
+    }}
+
+    function test_TotalSupplyCoversBalance() public {{
+        (bool ok, bytes memory data) = token.staticcall(abi.encodeWithSignature("totalSupply()"));
+        assertTrue(ok);
+        assertGe(abi.decode(data, (uint256)), _balanceOf(holder));
+    }}
+
+    function test_Transfer() public {{
+        uint256 holderBalance = _balanceOf(holder);
+        uint256 recipientBalance = _balanceOf(recipient);
+        vm.expectEmit(true, true, false, true, token);
+        emit Transfer(holder, recipient, amount);
+        assertTrue(_succeeds(holder, abi.encodeWithSignature("transfer(address,uint256)", recipient, amount)));
+        assertEq(_balanceOf(holder), holderBalance - amount);
+        assertEq(_balanceOf(recipient), recipientBalance + amount);
+    }}
+
+    function test_TransferMoreThanBalanceFails() public {{
+        assertEq(_balanceOf(recipient), 0);
+        assertTrue(!_succeeds(recipient, abi.encodeWithSignature("transfer(address,uint256)", holder, 1)));
+    }}
+
+    function test_Approve() public {{
+        vm.expectEmit(true, true, false, true, token);
+        emit Approval(holder, spender, amount);
+        assertTrue(_succeeds(holder, abi.encodeWithSignature("approve(address,uint256)", spender, amount)));
+        assertEq(_allowance(holder, spender), amount);
+    }}
+
+    function test_TransferFrom() public {{
+        assertTrue(_succeeds(holder, abi.encodeWithSignature("approve(address,uint256)", spender, amount)));
+        uint256 holderBalance = _balanceOf(holder);
+        vm.expectEmit(true, true, false, true, token);
+        emit Transfer(holder, recipient, amount);
+        assertTrue(_succeeds(spender, abi.encodeWithSignature("transferFrom(address,address,uint256)", holder, recipient, amount)));
+        assertEq(_balanceOf(holder), holderBalance - amount);
+        assertEq(_balanceOf(recipient), amount);
+        assertEq(_allowance(holder, spender), 0);
+    }}
+
+    function test_TransferFromWithoutAllowanceFails() public {{
+        assertEq(_allowance(holder, spender), 0);
+        assertTrue(!_succeeds(spender, abi.encodeWithSignature("transferFrom(address,address,uint256)", holder, recipient, amount)));
+    }}
+}}
+"#
+    )
+}
+
+fn erc721(suite: &Suite, address: &str, holders: &[String], fork: &str) -> String {
+    let name = suite.name;
+    let enumerable = suite.extensions.contains(&"enumerable");
+    let candidate_ids = |ids: &[String]| {
+        format!(
+            r#"        uint256[{len}] memory candidates = {candidates};
+        for (uint256 i = 0; i < candidates.length && holder == address(0); i++) {{
+            _pick(candidates[i]);
+        }}
+"#,
+            len = ids.len(),
+            candidates = array_literal("uint256", ids),
+        )
+    };
+    let mut find_token = String::new();
+    match suite.token_id {
+        Some(id) => find_token.push_str(&candidate_ids(&[id.to_string()])),
+        None => {
+            if enumerable && !holders.is_empty() {
+                find_token.push_str(&format!(
+                    r#"        address[{len}] memory holders = {holders};
+        for (uint256 i = 0; i < holders.length && holder == address(0); i++) {{
+            (bool ok, bytes memory data) = token.staticcall(abi.encodeWithSignature("tokenOfOwnerByIndex(address,uint256)", holders[i], 0));
+            if (ok && data.length >= 32) {{
+                _pick(abi.decode(data, (uint256)));
+            }}
+        }}
+"#,
+                    len = holders.len(),
+                    holders = array_literal("address", holders),
+                ));
+            }
+            if enumerable {
+                find_token.push_str(
+                    r#"        if (holder == address(0)) {
+            (bool ok, bytes memory data) = token.staticcall(abi.encodeWithSignature("tokenByIndex(uint256)", 0));
+            if (ok && data.length >= 32) {
+                _pick(abi.decode(data, (uint256)));
+            }
+        }
+"#,
+                );
+            }
+            // Without a token id to go by, the lowest ids are the likeliest to be minted
+            find_token.push_str(&candidate_ids(&["0".to_string(), "1".to_string()]));
+        }
+    }
+    find_token.push_str("        require(holder != address(0), \"No minted token found, pass --token-id\");\n");
+
+    format!(
+        r#"/// ERC-721 compliance checks for {name}, run against a fork of the chain it is deployed on,
+/// with a token that's already minted.
+contract {name}ComplianceTest is Test {{
+    event Transfer(address indexed from, address indexed to, uint256 indexed tokenId);
+    event Approval(address indexed owner, address indexed approved, uint256 indexed tokenId);
+
+    address internal token = {address};
+    address internal holder;
+    address internal recipient;
+    address internal spender;
+    uint256 internal tokenId;
+
+    function setUp() public {{
+        {fork}
+        recipient = makeAddr("recipient");
+        spender = makeAddr("spender");
+{find_token}    }}
+
+    /// Tests with `id` if it has an owner.
+    function _pick(uint256 id) internal {{
+        address owner = _ownerOf(id);
+        if (owner != address(0)) {{
+            tokenId = id;
+            holder = owner;
+        }}
+    }}
+
+    /// The owner of `id`, or zero if it has none.
+    function _ownerOf(uint256 id) internal view returns (address) {{
+        (bool ok, bytes memory data) = token.staticcall(abi.encodeWithSignature("ownerOf(uint256)", id));
+        if (!ok || data.length < 32) {{
+            return address(0);
+        }}
+        return abi.decode(data, (address));
+    }}
+
+    function _balanceOf(address account) internal view returns (uint256) {{
+        (bool ok, bytes memory data) = token.staticcall(abi.encodeWithSignature("balanceOf(address)", account));
+        require(ok && data.length >= 32, "balanceOf failed");
+        return abi.decode(data, (uint256));
+    }}
+
+    function _succeeds(address from, bytes memory data) internal returns (bool) {{
+        vm.prank(from);
+        (bool ok, ) = token.call(data);
+        return ok;
+    }}
+
+    function test_OwnerOf() public {{
+        assertEq(_ownerOf(tokenId), holder);
+        assertGe(_balanceOf(holder), 1);
+    }}
+
+    function test_TransferFrom() public {{
+        uint256 holderBalance = _balanceOf(holder);
+        vm.expectEmit(true, true, true, true, token);
+        emit Transfer(holder, recipient, tokenId);
+        assertTrue(_succeeds(holder, abi.encodeWithSignature("transferFrom(address,address,uint256)", holder, recipient, tokenId)));
+        assertEq(_ownerOf(tokenId), recipient);
+        assertEq(_balanceOf(holder), holderBalance - 1);
+        assertEq(_balanceOf(recipient), 1);
+    }}
+
+    function test_ApprovedTransferFrom() public {{
+        vm.expectEmit(true, true, true, true, token);
+        emit Approval(holder, spender, tokenId);
+        assertTrue(_succeeds(holder, abi.encodeWithSignature("approve(address,uint256)", spender, tokenId)));
+        (bool ok, bytes memory data) = token.staticcall(abi.encodeWithSignature("getApproved(uint256)", tokenId));
+        assertTrue(ok);
+        assertEq(abi.decode(data, (address)), spender);
+
+        assertTrue(_succeeds(spender, abi.encodeWithSignature("transferFrom(address,address,uint256)", holder, recipient, tokenId)));
+        assertEq(_ownerOf(tokenId), recipient);
+    }}
+
+    function test_TransferByStrangerFails() public {{
+        assertTrue(!_succeeds(spender, abi.encodeWithSignature("transferFrom(address,address,uint256)", holder, recipient, tokenId)));
+        assertEq(_ownerOf(tokenId), holder);
+    }}
+
+    function test_SafeTransferToReceiver() public {{
+        address receiver = address(new {name}ComplianceReceiver());
+        assertTrue(_succeeds(holder, abi.encodeWithSignature("safeTransferFrom(address,address,uint256)", holder, receiver, tokenId)));
+        assertEq(_ownerOf(tokenId), receiver);
+    }}
+
+    function test_SafeTransferToNonReceiverFails() public {{
+        address nonReceiver = address(new {name}ComplianceNonReceiver());
+        assertTrue(!_succeeds(holder, abi.encodeWithSignature("safeTransferFrom(address,address,uint256)", holder, nonReceiver, tokenId)));
+        assertEq(_ownerOf(tokenId), holder);
+    }}
+}}
+
+contract {name}ComplianceReceiver {{
+    function onERC721Received(address, address, uint256, bytes calldata) external pure returns (bytes4) {{
+        return bytes4(0x150b7a02);
+    }}
+}}
+
+/// A contract that doesn't implement onERC721Received, which safeTransferFrom must refuse.
+contract {name}ComplianceNonReceiver {{}}
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_holders_in_constructor_arguments() {
+        let arguments = concat!(
+            "0000000000000000000000000000000000000000000000000000000000000012",
+            "0000000000000000000000005aaeb6053f3e94c9b9a09f33669435e7ef1beaed",
+            "00000000000000000000000000000000000000000000d3c21bcecceda1000000"
+        );
+        assert_eq!(constructor_addresses(arguments), ["0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"]);
+        assert!(constructor_addresses("not hex").is_empty());
+
+        assert!(builds_with(Some("0.8.19")));
+        assert!(builds_with(Some("0.6.2")));
+        assert!(!builds_with(Some("0.5.16")));
+        assert!(builds_with(None));
+    }
+
+    #[test]
+    fn renders_suites_for_the_standard() {
+        let holders = ["0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed".to_string()];
+        let mut suite = Suite {
+            name: "Token",
+            address: "0xdac17f958d2ee523a2206206994597c13d831ec7",
+            standard: Standard::Erc20,
+            extensions: &[],
+            holders: &holders,
+            token_id: None,
+            rpc_env: "ETH_RPC_URL",
+            fork_block: Some(19_000_000),
+        };
+        let test = render(&suite).unwrap();
+        assert!(test.contains("pragma solidity >=0.6.2 <0.9.0;"));
+        assert!(test.contains("address internal token = 0xdAC17F958D2ee523a2206206994597C13D831ec7;"));
+        assert!(test.contains("address[1] memory candidates = [address(0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed)];"));
+        assert!(test.contains("vm.createSelectFork(vm.envString(\"ETH_RPC_URL\"), 19000000);"));
+        assert!(test.contains("function test_TransferFromWithoutAllowanceFails()"));
+
+        suite.standard = Standard::Erc721;
+        suite.extensions = &["enumerable"];
+        suite.token_id = Some(U256::from(42));
+        let test = render(&suite).unwrap();
+        assert!(test.contains("uint256[1] memory candidates = [uint256(42)];"));
+        assert!(test.contains("contract TokenComplianceReceiver {"));
+        // The given token id is the one tested with
+        assert!(!test.contains("tokenByIndex"));
+        suite.token_id = None;
+        let test = render(&suite).unwrap();
+        assert!(test.find("tokenOfOwnerByIndex").unwrap() < test.find("[uint256(0), 1]").unwrap());
+
+        suite.standard = Standard::Erc1155;
+        assert!(render(&suite).is_err());
+    }
+}
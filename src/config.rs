@@ -0,0 +1,276 @@
+use crate::error::TokenFetchError;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// User defaults from `~/.config/tokenfetch/config.toml`. Command-line flags always win.
+///
+/// ```toml
+/// default_chain = "eth"
+/// default_output_dir = "~/vendored"
+/// always_fetch_abi = true
+/// concurrency = 5
+/// tier = "standard"
+///
+/// [chains.eth]
+/// api_key = "..."
+/// rpc_url = "https://..."
+///
+/// # A chain of its own, like --custom-chain
+/// [chains.foo]
+/// api_url = "https://api.fooscan.io/api"
+/// chain_id = 9999
+/// ```
+#[derive(Debug, Default)]
+pub struct Config {
+    /// Chain used when the chain argument is left out
+    pub default_chain: Option<String>,
+    /// Parent directory for clones when no path is given
+    pub default_output_dir: Option<PathBuf>,
+    /// Write the ABI alongside the sources on every clone, like `--abi`
+    pub always_fetch_abi: bool,
+    /// Default for `--concurrency`
+    pub concurrency: Option<u32>,
+    /// Default for `--tier`, checked when the tool starts
+    pub tier: Option<String>,
+    /// Default for `--rps`
+    pub rps: Option<u32>,
+    /// Per-chain settings, keyed by chain name
+    pub chains: HashMap<String, ChainSettings>,
+}
+
+#[derive(Debug, Default)]
+pub struct ChainSettings {
+    pub api_key: Option<String>,
+    pub rpc_url: Option<String>,
+    /// Explorer API of a chain that isn't built in, which defines it
+    pub api_url: Option<String>,
+    pub chain_id: Option<i32>,
+    pub api_key_env: Option<String>,
+    pub explorer_host: Option<String>,
+    pub rate_limit: Option<u32>,
+}
+
+impl Config {
+    pub fn chain(&self, name: &str) -> Option<&ChainSettings> {
+        self.chains.get(&name.to_lowercase())
+    }
+}
+
+/// The config file used when `--config` isn't given.
+fn default_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("tokenfetch").join("config.toml"))
+}
+
+/// Loads the config file selected by `--config`/`--no-config` in `args`.
+///
+/// It has to be read before clap parses the arguments, since it supplies their defaults,
+/// so those two flags are picked out of the raw arguments here.
+pub fn load_from_args(args: &[OsString]) -> Result<Config, TokenFetchError> {
+    if raw_flag(args, "--no-config") {
+        return Ok(Config::default());
+    }
+
+    match raw_flag_value(args, "--config") {
+        Some(path) => load(&path),
+        None => match default_path() {
+            Some(path) if path.is_file() => load(&path),
+            _ => Ok(Config::default()),
+        },
+    }
+}
+
+/// The arguments after the program name, up to a `--`.
+fn raw_args(args: &[OsString]) -> impl Iterator<Item = &str> {
+    args.iter()
+        .skip(1)
+        .filter_map(|arg| arg.to_str())
+        .take_while(|arg| *arg != "--")
+}
+
+/// Whether the switch `flag` was given, for the few flags that are needed before clap
+/// parses the arguments.
+pub fn raw_flag(args: &[OsString], flag: &str) -> bool {
+    raw_args(args).any(|arg| arg == flag)
+}
+
+/// How many times the switch `short`/`long` was given, counting clusters like `-vv`.
+pub fn raw_count(args: &[OsString], short: char, long: &str) -> usize {
+    raw_args(args)
+        .map(|arg| match arg.strip_prefix('-') {
+            _ if arg == long => 1,
+            Some(cluster) if !cluster.starts_with('-') && !cluster.is_empty() && cluster.chars().all(|c| c == short) => cluster.len(),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// The last value given for `flag` (as `flag <value>` or `flag=<value>`), for the few
+/// flags that are needed before clap parses the arguments.
+pub fn raw_flag_value(args: &[OsString], flag: &str) -> Option<PathBuf> {
+    let mut value = None;
+    let mut iter = raw_args(args);
+    while let Some(arg) = iter.next() {
+        if arg == flag {
+            value = iter.next().map(PathBuf::from);
+        } else if let Some(path) = arg.strip_prefix(flag).and_then(|rest| rest.strip_prefix('=')) {
+            value = Some(PathBuf::from(path));
+        }
+    }
+    value
+}
+
+pub fn load(path: &Path) -> Result<Config, TokenFetchError> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read config file {:?}: {}", path, e))?;
+    let config = parse(&contents).map_err(|e| format!("Invalid config file {:?}: {}", path, e))?;
+    info!("Loaded config from {:?}", path);
+    Ok(config)
+}
+
+/// Parses the config, warning about keys that don't correspond to any option.
+pub fn parse(contents: &str) -> Result<Config, TokenFetchError> {
+    let table: toml::Table = contents.parse().map_err(TokenFetchError::other)?;
+    let mut config = Config::default();
+
+    for (key, value) in &table {
+        match key.as_str() {
+            "default_chain" => config.default_chain = Some(string(key, value)?),
+            "default_output_dir" => config.default_output_dir = Some(expand_home(&string(key, value)?)),
+            "always_fetch_abi" => {
+                config.always_fetch_abi = value.as_bool().ok_or_else(|| format!("{} must be true or false", key))?
+            }
+            "concurrency" => {
+                let concurrency = value
+                    .as_integer()
+                    .filter(|n| (1..=u32::MAX as i64).contains(n))
+                    .ok_or_else(|| format!("{} must be a positive number", key))?;
+                config.concurrency = Some(concurrency as u32);
+            }
+            "tier" => config.tier = Some(string(key, value)?),
+            "rps" => {
+                let rps = value
+                    .as_integer()
+                    .filter(|n| (1..=u32::MAX as i64).contains(n))
+                    .ok_or_else(|| format!("{} must be a positive number", key))?;
+                config.rps = Some(rps as u32);
+            }
+            "chains" => {
+                let chains = value.as_table().ok_or("chains must be a table of [chains.<name>] sections")?;
+                for (name, settings) in chains {
+                    let settings = settings
+                        .as_table()
+                        .ok_or_else(|| format!("chains.{} must be a table", name))?;
+                    config.chains.insert(name.to_lowercase(), chain_settings(name, settings)?);
+                }
+            }
+            _ => warn!("Ignoring unknown config key `{}`", key),
+        }
+    }
+
+    Ok(config)
+}
+
+fn chain_settings(name: &str, table: &toml::Table) -> Result<ChainSettings, TokenFetchError> {
+    let mut settings = ChainSettings::default();
+    for (key, value) in table {
+        match key.as_str() {
+            "api_key" => settings.api_key = Some(string(key, value)?),
+            "rpc_url" => settings.rpc_url = Some(string(key, value)?),
+            "api_url" => settings.api_url = Some(string(key, value)?),
+            "chain_id" => {
+                let id = value.as_integer().and_then(|id| i32::try_from(id).ok());
+                settings.chain_id = Some(id.ok_or_else(|| format!("chains.{}.chain_id must be a number", name))?);
+            }
+            "api_key_env" => settings.api_key_env = Some(string(key, value)?),
+            "explorer_host" => settings.explorer_host = Some(string(key, value)?),
+            "rate_limit" => {
+                let rps = value.as_integer().filter(|n| (1..=u32::MAX as i64).contains(n));
+                settings.rate_limit = Some(rps.ok_or_else(|| format!("chains.{}.rate_limit must be a positive number", name))? as u32);
+            }
+            _ => warn!("Ignoring unknown config key `chains.{}.{}`", name, key),
+        }
+    }
+    Ok(settings)
+}
+
+fn string(key: &str, value: &toml::Value) -> Result<String, TokenFetchError> {
+    value
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| format!("{} must be a string", key).into())
+}
+
+/// Expands a leading `~/` to the home directory.
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_defaults_and_chain_sections() {
+        let config = parse(
+            r#"
+            default_chain = "base"
+            always_fetch_abi = true
+            concurrency = 8
+            tier = "advanced"
+            rps = 12
+            something_else = 1
+
+            [chains.ETH]
+            api_key = "k1,k2"
+            rpc_url = "http://localhost:8545"
+
+            [chains.foo]
+            api_url = "https://api.fooscan.io/api"
+            chain_id = 9999
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.default_chain.as_deref(), Some("base"));
+        assert!(config.always_fetch_abi);
+        assert_eq!(config.concurrency, Some(8));
+        assert_eq!(config.tier.as_deref(), Some("advanced"));
+        assert_eq!(config.rps, Some(12));
+        let eth = config.chain("eth").unwrap();
+        assert_eq!(eth.api_key.as_deref(), Some("k1,k2"));
+        assert_eq!(eth.rpc_url.as_deref(), Some("http://localhost:8545"));
+        let foo = config.chain("foo").unwrap();
+        assert_eq!(foo.api_url.as_deref(), Some("https://api.fooscan.io/api"));
+        assert_eq!(foo.chain_id, Some(9999));
+    }
+
+    #[test]
+    fn rejects_mistyped_values() {
+        let err = parse("concurrency = \"fast\"").unwrap_err();
+        assert_eq!(err.to_string(), "concurrency must be a positive number");
+    }
+
+    #[test]
+    fn counts_repeated_switches() {
+        let args: Vec<OsString> = ["tokenfetch", "-vv", "eth", "--verbose", "-o", "-v.json"].iter().map(OsString::from).collect();
+        assert_eq!(raw_count(&args, 'v', "--verbose"), 3);
+        assert_eq!(raw_count(&args, 'q', "--quiet"), 0);
+    }
+
+    #[test]
+    fn no_config_skips_the_file() {
+        let args: Vec<OsString> = ["tokenfetch", "--config", "/nonexistent.toml", "--no-config"]
+            .iter()
+            .map(OsString::from)
+            .collect();
+        assert!(load_from_args(&args).unwrap().default_chain.is_none());
+
+        let args: Vec<OsString> = ["tokenfetch", "--config=/nonexistent.toml"].iter().map(OsString::from).collect();
+        assert!(load_from_args(&args).is_err());
+    }
+}
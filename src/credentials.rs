@@ -0,0 +1,46 @@
+use crate::error::TokenFetchError;
+use keyring::{Entry, Error};
+use log::warn;
+
+/// Service name API keys are stored under in the OS credential store.
+const SERVICE: &str = "tokenfetch";
+
+/// Looks up the stored API key for `chain`.
+///
+/// A missing or unusable credential store, as on headless Linux without a secret
+/// service, is logged and treated like a missing key, so callers fall back gracefully.
+pub fn get(chain: &str) -> Option<String> {
+    match Entry::new(SERVICE, chain).and_then(|entry| entry.get_password()) {
+        Ok(key) => Some(key),
+        Err(Error::NoEntry) => None,
+        Err(e) => {
+            warn!("OS keyring is unavailable, skipping it: {}", e);
+            None
+        }
+    }
+}
+
+/// Stores `key` as the API key for `chain`, replacing any previous one.
+pub fn set(chain: &str, key: &str) -> Result<(), TokenFetchError> {
+    Entry::new(SERVICE, chain).map_err(TokenFetchError::other)?
+        .set_password(key)
+        .map_err(|e| format!("Failed to store the API key in the OS keyring: {}", e).into())
+}
+
+/// Removes the stored API key for `chain`, returning whether there was one.
+pub fn delete(chain: &str) -> Result<bool, TokenFetchError> {
+    match Entry::new(SERVICE, chain).map_err(TokenFetchError::other)?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(Error::NoEntry) => Ok(false),
+        Err(e) => Err(format!("Failed to delete the API key from the OS keyring: {}", e).into()),
+    }
+}
+
+/// Whether a key is stored for `chain`. Unlike [`get`], keyring failures are errors.
+pub fn exists(chain: &str) -> Result<bool, TokenFetchError> {
+    match Entry::new(SERVICE, chain).map_err(TokenFetchError::other)?.get_password() {
+        Ok(_) => Ok(true),
+        Err(Error::NoEntry) => Ok(false),
+        Err(e) => Err(format!("Failed to read the OS keyring: {}", e).into()),
+    }
+}
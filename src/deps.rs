@@ -0,0 +1,140 @@
+/// Top-level directories that hold installed packages rather than a project's own code.
+const PACKAGE_DIRS: &[&str] = &["lib", "node_modules", "dependencies", ".deps"];
+
+/// Well-known packages that are commonly imported without an `@scope/` prefix.
+const UNSCOPED_PACKAGES: &[&str] = &[
+    "forge-std",
+    "hardhat",
+    "openzeppelin-contracts",
+    "openzeppelin-contracts-upgradeable",
+    "solady",
+    "solmate",
+];
+
+/// Whether a source key such as `@openzeppelin/contracts/token/ERC20/ERC20.sol` belongs
+/// to an imported package rather than the verified contract's own sources.
+pub fn is_dependency(key: &str) -> bool {
+    let key = key.trim_start_matches("./");
+    let Some((first, _)) = key.split_once('/') else {
+        return false;
+    };
+
+    // Scoped (`@openzeppelin/`) or version-pinned (`solmate@6.2.0/`) package names
+    first.contains('@')
+        || PACKAGE_DIRS.contains(&first)
+        || UNSCOPED_PACKAGES.contains(&first)
+        || key.contains("/node_modules/")
+}
+
+/// Where `--link-deps` expects the packages to be installed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum LinkDir {
+    /// forge install's `lib/<repository>/`
+    Lib,
+    /// npm's `node_modules/<package>/`
+    NodeModules,
+}
+
+/// Packages whose forge repository lays the sources out differently from the npm package.
+const FORGE_LAYOUTS: &[(&str, &str)] = &[
+    ("@openzeppelin/contracts", "openzeppelin-contracts/contracts"),
+    ("@openzeppelin/contracts-upgradeable", "openzeppelin-contracts-upgradeable/contracts"),
+    ("forge-std", "forge-std/src"),
+];
+
+/// The package a dependency source belongs to, as found from its key.
+#[derive(Debug, PartialEq)]
+pub struct Package {
+    /// What the source keys of the package start with, e.g. `@openzeppelin/contracts@4.9.0/`
+    pub prefix: String,
+    /// The package name, e.g. `@openzeppelin/contracts`
+    pub name: String,
+    /// The version the key pins, if any
+    pub version: Option<String>,
+}
+
+/// Reads a package name, scoped or not and maybe version-pinned, off the front of `path`.
+fn read_package(path: &str) -> Option<(String, String, Option<String>)> {
+    let mut parts = path.split('/');
+    let first = parts.next()?;
+    let named = match first.starts_with('@') {
+        true => format!("{}/{}", first, parts.next()?),
+        false => first.to_string(),
+    };
+    parts.next()?;
+    // The version follows the last `@`, which isn't the scope's
+    let (name, version) = match named.rfind('@').filter(|at| *at > 0) {
+        Some(at) => (named[..at].to_string(), Some(named[at + 1..].to_string())),
+        None => (named.clone(), None),
+    };
+    Some((format!("{}/", named), name, version))
+}
+
+/// The package a dependency source such as `@openzeppelin/contracts@4.9.0/token/ERC20.sol`
+/// or `lib/solmate/src/tokens/ERC20.sol` belongs to, going by the shape of its key.
+pub fn package(key: &str) -> Option<Package> {
+    let key = key.trim_start_matches("./");
+    if !is_dependency(key) {
+        return None;
+    }
+    let (outer, rest) = match key.rfind("/node_modules/").map(|at| at + "/node_modules/".len()) {
+        Some(at) => key.split_at(at),
+        None => match key.split_once('/') {
+            Some((first, rest)) if PACKAGE_DIRS.contains(&first) => (&key[..first.len() + 1], rest),
+            _ => ("", key),
+        },
+    };
+    let (prefix, name, version) = read_package(rest)?;
+    Some(Package { prefix: format!("{}{}", outer, prefix), name, version })
+}
+
+/// The `prefix=target` remapping that points `package` at where `dir` installs it, or
+/// `None` when its sources are already imported from there.
+pub fn remapping(package: &Package, dir: LinkDir) -> Option<String> {
+    let target = match dir {
+        LinkDir::NodeModules => format!("node_modules/{}/", package.name),
+        LinkDir::Lib => match FORGE_LAYOUTS.iter().find(|(name, _)| *name == package.name) {
+            Some((_, layout)) => format!("lib/{}/", layout),
+            None => format!("lib/{}/", package.name.rsplit('/').next().unwrap_or(&package.name)),
+        },
+    };
+    (package.prefix != target).then(|| format!("{}={}", package.prefix, target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_package_paths() {
+        assert!(is_dependency("@openzeppelin/contracts/token/ERC20/ERC20.sol"));
+        assert!(is_dependency("lib/forge-std/src/Test.sol"));
+        assert!(is_dependency("solmate@6.2.0/src/tokens/ERC20.sol"));
+        assert!(is_dependency("solady/src/utils/LibString.sol"));
+        assert!(is_dependency("contracts/node_modules/@uniswap/v3-core/contracts/UniswapV3Pool.sol"));
+
+        assert!(!is_dependency("contracts/Token.sol"));
+        assert!(!is_dependency("src/Vault.sol"));
+        assert!(!is_dependency("Single.sol"));
+    }
+
+    #[test]
+    fn finds_packages_and_their_remappings() {
+        let linked = |key: &str, dir| package(key).and_then(|package| remapping(&package, dir));
+        let pinned = package("@openzeppelin/contracts@4.9.0/token/ERC20/ERC20.sol").unwrap();
+        assert_eq!((pinned.name.as_str(), pinned.version.as_deref()), ("@openzeppelin/contracts", Some("4.9.0")));
+        assert_eq!(remapping(&pinned, LinkDir::Lib).as_deref(), Some("@openzeppelin/contracts@4.9.0/=lib/openzeppelin-contracts/contracts/"));
+        assert_eq!(remapping(&pinned, LinkDir::NodeModules).as_deref(), Some("@openzeppelin/contracts@4.9.0/=node_modules/@openzeppelin/contracts/"));
+
+        assert_eq!(linked("solmate@6.2.0/src/tokens/ERC20.sol", LinkDir::Lib).as_deref(), Some("solmate@6.2.0/=lib/solmate/"));
+        assert_eq!(linked("@uniswap/v3-core/contracts/UniswapV3Pool.sol", LinkDir::Lib).as_deref(), Some("@uniswap/v3-core/=lib/v3-core/"));
+        assert_eq!(
+            linked("contracts/node_modules/@uniswap/v3-core/contracts/UniswapV3Pool.sol", LinkDir::NodeModules).as_deref(),
+            Some("contracts/node_modules/@uniswap/v3-core/=node_modules/@uniswap/v3-core/")
+        );
+        // Already where forge installs it
+        assert_eq!(package("lib/solmate/src/tokens/ERC20.sol").unwrap().prefix, "lib/solmate/");
+        assert_eq!(linked("lib/solmate/src/tokens/ERC20.sol", LinkDir::Lib), None);
+        assert_eq!(package("contracts/Token.sol"), None);
+    }
+}
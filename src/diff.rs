@@ -0,0 +1,165 @@
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// How the files of two source trees line up.
+#[derive(Debug, Default, PartialEq)]
+pub struct Pairing {
+    /// Files present in both trees, as (left path, right path)
+    pub pairs: Vec<(String, String)>,
+    pub only_left: Vec<String>,
+    pub only_right: Vec<String>,
+}
+
+/// A source path with the parts that say nothing about the file dropped: a leading `./`
+/// or `/`, and Windows separators.
+fn normalize_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    path.trim_start_matches("./").trim_start_matches('/').to_string()
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// The contents of a source file with line endings normalized, so a tree verified from
+/// Windows doesn't differ on every line.
+pub fn content(source: &Value) -> String {
+    source["content"].as_str().unwrap_or_default().replace("\r\n", "\n")
+}
+
+/// Matches the files of two trees: by path first, then files whose name appears once in
+/// each tree, as a fork often moves files around; and when a single file is left on each
+/// side, such as a renamed single-file contract, those two.
+pub fn pair_sources(left: &Map<String, Value>, right: &Map<String, Value>) -> Pairing {
+    let mut left: BTreeMap<String, &str> = left.keys().map(|key| (normalize_path(key), key.as_str())).collect();
+    let mut right: BTreeMap<String, &str> = right.keys().map(|key| (normalize_path(key), key.as_str())).collect();
+    let mut pairing = Pairing::default();
+
+    let same_path: Vec<String> = left.keys().filter(|path| right.contains_key(*path)).cloned().collect();
+    for path in same_path {
+        let (l, r) = (left.remove(&path).unwrap(), right.remove(&path).unwrap());
+        pairing.pairs.push((l.to_string(), r.to_string()));
+    }
+
+    let unique_by_name = |tree: &BTreeMap<String, &str>, name: &str| {
+        let mut matches = tree.keys().filter(|path| file_name(path) == name);
+        match (matches.next(), matches.next()) {
+            (Some(path), None) => Some(path.clone()),
+            _ => None,
+        }
+    };
+    let names: Vec<String> = left.keys().map(|path| file_name(path).to_string()).collect();
+    for name in names {
+        if let (Some(l), Some(r)) = (unique_by_name(&left, &name), unique_by_name(&right, &name)) {
+            let (l, r) = (left.remove(&l).unwrap(), right.remove(&r).unwrap());
+            pairing.pairs.push((l.to_string(), r.to_string()));
+        }
+    }
+
+    if left.len() == 1 && right.len() == 1 {
+        let (l, r) = (left.pop_first().unwrap().1, right.pop_first().unwrap().1);
+        pairing.pairs.push((l.to_string(), r.to_string()));
+    }
+    pairing.pairs.sort();
+    pairing.only_left = left.into_values().map(str::to_string).collect();
+    pairing.only_right = right.into_values().map(str::to_string).collect();
+    pairing
+}
+
+/// A unified diff from `old` to `new`, or `None` when they're the same.
+pub fn unified(old_label: &str, new_label: &str, old: &str, new: &str) -> Option<String> {
+    if old == new {
+        return None;
+    }
+    let diff = similar::TextDiff::from_lines(old, new);
+    Some(diff.unified_diff().context_radius(3).header(old_label, new_label).to_string())
+}
+
+/// The settings two compilations differ in, as dotted paths such as `optimizer.runs`.
+/// Objects are compared key by key, anything else as a whole.
+pub fn settings_differences(left: &Value, right: &Value) -> Vec<String> {
+    fn walk(path: &str, left: &Value, right: &Value, out: &mut Vec<String>) {
+        match (left, right) {
+            (Value::Object(l), Value::Object(r)) => {
+                let keys: std::collections::BTreeSet<&String> = l.keys().chain(r.keys()).collect();
+                for key in keys {
+                    let nested = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                    walk(&nested, l.get(key).unwrap_or(&Value::Null), r.get(key).unwrap_or(&Value::Null), out);
+                }
+            }
+            _ if left != right => out.push(path.to_string()),
+            _ => {}
+        }
+    }
+    let mut out = Vec::new();
+    walk("", left, right, &mut out);
+    out
+}
+
+/// Colors a unified diff for a terminal: headers bold, hunk markers cyan, removed lines
+/// red and added lines green.
+pub fn colorize(diff: &str) -> String {
+    diff.lines()
+        .map(|line| {
+            let color = match line.as_bytes().first() {
+                _ if line.starts_with("---") || line.starts_with("+++") => "1",
+                Some(b'@') => "36",
+                Some(b'-') => "31",
+                Some(b'+') => "32",
+                _ => return format!("{}\n", line),
+            };
+            format!("\x1b[{}m{}\x1b[0m\n", color, line)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(paths: &[&str]) -> Map<String, Value> {
+        paths.iter().map(|path| (path.to_string(), serde_json::json!({ "content": "" }))).collect()
+    }
+
+    #[test]
+    fn pairs_files_by_path_then_name() {
+        let left = tree(&["contracts/Token.sol", "./@openzeppelin/ERC20.sol", "contracts/utils/Math.sol", "contracts/Old.sol"]);
+        let right = tree(&["@openzeppelin/ERC20.sol", "src/Token.sol", "src/lib/Math.sol", "lib/Math.sol", "src/New.sol", "src/Extra.sol"]);
+
+        let pairing = pair_sources(&left, &right);
+        assert_eq!(
+            pairing.pairs,
+            [
+                ("./@openzeppelin/ERC20.sol".to_string(), "@openzeppelin/ERC20.sol".to_string()),
+                ("contracts/Token.sol".to_string(), "src/Token.sol".to_string()),
+            ]
+        );
+        assert_eq!(pairing.only_left, ["contracts/Old.sol", "contracts/utils/Math.sol"]);
+        assert_eq!(pairing.only_right, ["lib/Math.sol", "src/Extra.sol", "src/New.sol", "src/lib/Math.sol"]);
+
+        // A single file left on each side is the same file renamed
+        let pairing = pair_sources(&tree(&["Token.sol"]), &tree(&["Fork.sol"]));
+        assert_eq!(pairing.pairs, [("Token.sol".to_string(), "Fork.sol".to_string())]);
+    }
+
+    #[test]
+    fn names_differing_settings() {
+        let left = serde_json::json!({ "optimizer": { "enabled": true, "runs": 200 }, "evmVersion": "paris", "remappings": ["a=b"] });
+        let right = serde_json::json!({ "optimizer": { "enabled": true, "runs": 999 }, "remappings": ["a=b"], "viaIR": true });
+        assert_eq!(settings_differences(&left, &right), ["evmVersion", "optimizer.runs", "viaIR"]);
+        assert!(settings_differences(&left, &left).is_empty());
+    }
+
+    #[test]
+    fn diffs_changed_files_only() {
+        assert_eq!(unified("a", "b", "same\n", "same\n"), None);
+        assert_eq!(content(&serde_json::json!({ "content": "a\r\nb\r\n" })), "a\nb\n");
+
+        let diff = unified("a/Token.sol", "b/Token.sol", "uint fee = 1;\nreturn;\n", "uint fee = 99;\nreturn;\n").unwrap();
+        assert_eq!(diff, "--- a/Token.sol\n+++ b/Token.sol\n@@ -1,2 +1,2 @@\n-uint fee = 1;\n+uint fee = 99;\n return;\n");
+        assert_eq!(
+            colorize("@@ -1 +1 @@\n-a\n+b\n c\n"),
+            "\x1b[36m@@ -1 +1 @@\x1b[0m\n\x1b[31m-a\x1b[0m\n\x1b[32m+b\x1b[0m\n c\n"
+        );
+    }
+}
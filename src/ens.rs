@@ -0,0 +1,129 @@
+use crate::error::TokenFetchError;
+use crate::rpc;
+use alloy_primitives::{hex, keccak256, Address};
+use log::info;
+
+/// The ENS registry on Ethereum mainnet.
+const REGISTRY: &str = "0x00000000000C2E074eC69A0dFb2997BA6C7d2e1e";
+
+/// `resolver(bytes32)` on the registry.
+const RESOLVER_SELECTOR: &str = "0178b8bf";
+
+/// `addr(bytes32)` on a resolver.
+const ADDR_SELECTOR: &str = "3b3b57de";
+
+/// Whether `value` has the shape of an ENS name such as `uniswap.eth`, rather than an
+/// address or something else entirely.
+pub fn looks_like_name(value: &str) -> bool {
+    value.contains('.')
+        && !value.starts_with("0x")
+        && !value.contains(['/', ':'])
+        && value.split('.').all(|label| !label.is_empty())
+}
+
+/// The EIP-137 namehash of `name`.
+pub fn namehash(name: &str) -> [u8; 32] {
+    let mut node = [0u8; 32];
+    for label in name.rsplit('.').filter(|label| !label.is_empty()) {
+        let mut buf = [0u8; 64];
+        buf[..32].copy_from_slice(&node);
+        buf[32..].copy_from_slice(keccak256(label.as_bytes()).as_slice());
+        node = keccak256(buf).0;
+    }
+    node
+}
+
+/// Resolves `name` to the address its resolver records for it, through the registry
+/// and resolver contracts at `rpc_url`.
+pub async fn resolve(client: &reqwest::Client, rpc_url: &str, name: &str) -> Result<String, TokenFetchError> {
+    // Full ENS normalization is UTS-46; lowercasing covers plain ASCII names
+    let name = name.to_lowercase();
+    let node = namehash(&name);
+    let not_resolved = || TokenFetchError::InvalidInput(format!("ENS name {} doesn't resolve to an address", name));
+
+    let resolver = call_returning_address(client, rpc_url, REGISTRY, RESOLVER_SELECTOR, &node)
+        .await?
+        .ok_or_else(not_resolved)?;
+    let address = call_returning_address(client, rpc_url, &resolver, ADDR_SELECTOR, &node)
+        .await?
+        .ok_or_else(not_resolved)?;
+
+    info!("Resolved ENS name {} to {}", name, address);
+    Ok(address)
+}
+
+/// Calls a `(bytes32) returns (address)` function, treating the zero address as none.
+async fn call_returning_address(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    to: &str,
+    selector: &str,
+    node: &[u8; 32],
+) -> Result<Option<String>, TokenFetchError> {
+    let data = format!("0x{}{}", selector, hex::encode(node));
+    let result = rpc::call(client, rpc_url, "eth_call", serde_json::json!([{ "to": to, "data": data }, "latest"])).await?;
+    let result = result.as_str().ok_or_else(|| format!("Unexpected eth_call result: {}", result))?;
+
+    // An address is returned right-aligned in a 32-byte word; a call to a contract
+    // that isn't there returns no data at all
+    let word = result.trim_start_matches("0x");
+    if word.len() < 64 {
+        return Ok(None);
+    }
+    let address = Address::from_slice(&hex::decode(&word[24..64]).map_err(TokenFetchError::other)?);
+    if address.is_zero() {
+        return Ok(None);
+    }
+    Ok(Some(address.to_checksum(None)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn hashes_names() {
+        assert_eq!(namehash(""), [0u8; 32]);
+        assert_eq!(
+            hex::encode(namehash("foo.eth")),
+            "de9b09fd7c5f901e23a3f19fecc54828e9c848539801e86591bd9801b019f84f"
+        );
+    }
+
+    async fn mount_call(server: &MockServer, to: &str, data: &str, result: &str) {
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({ "params": [{ "to": to, "data": data }] })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": result,
+            })))
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn resolves_through_the_registry() {
+        let server = MockServer::start().await;
+        let node = hex::encode(namehash("foo.eth"));
+        let word = |address: &str| format!("0x{:0>64}", address);
+        mount_call(&server, REGISTRY, &format!("0x{}{}", RESOLVER_SELECTOR, node), &word("4976fb03c32e5b8cfe2b6ccb31c09ba78ebaba41")).await;
+        mount_call(
+            &server,
+            "0x4976fb03C32e5B8cfe2b6cCB31c09Ba78EBaBa41",
+            &format!("0x{}{}", ADDR_SELECTOR, node),
+            &word("5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"),
+        )
+        .await;
+
+        let address = resolve(&reqwest::Client::new(), &server.uri(), "Foo.eth").await.unwrap();
+        assert_eq!(address, "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed");
+
+        let unregistered = format!("0x{}{}", RESOLVER_SELECTOR, hex::encode(namehash("bar.eth")));
+        mount_call(&server, REGISTRY, &unregistered, &word("")).await;
+        let err = resolve(&reqwest::Client::new(), &server.uri(), "bar.eth").await.unwrap_err();
+        assert_eq!(err.to_string(), "ENS name bar.eth doesn't resolve to an address");
+    }
+}
@@ -0,0 +1,136 @@
+use crate::error::TokenFetchError;
+use log::{debug, info, warn};
+use std::path::{Path, PathBuf};
+
+/// Env files looked for in each directory, highest precedence first.
+const FILE_NAMES: &[&str] = &[".env.local", ".env"];
+
+/// Loads environment variables from `explicit` or, without it, from the env files
+/// [`discover`] finds above `start`.
+///
+/// Variables already set in the process environment are never overridden, and since
+/// the files are loaded in precedence order `.env.local` wins over `.env`.
+pub fn load(explicit: Option<&Path>, start: &Path) -> Result<(), TokenFetchError> {
+    if let Some(path) = explicit {
+        dotenv::from_path(path).map_err(|e| format!("Failed to load env file {:?}: {}", path, e))?;
+        debug!("Loaded environment from {:?}", path);
+        return Ok(());
+    }
+
+    let files = discover(start);
+    if files.is_empty() {
+        debug!("No .env file found in {:?} or its parents", start);
+    }
+    for path in files {
+        match dotenv::from_path(&path) {
+            Ok(()) => debug!("Loaded environment from {:?}", path),
+            Err(e) => warn!("Skipping env file {:?}: {}", path, e),
+        }
+    }
+    Ok(())
+}
+
+/// Walks up from `start`, like git does looking for `.git`, and returns the env files
+/// in the first directory that has any, highest precedence first.
+pub fn discover(start: &Path) -> Vec<PathBuf> {
+    for dir in start.ancestors() {
+        let found: Vec<PathBuf> = FILE_NAMES
+            .iter()
+            .map(|name| dir.join(name))
+            .filter(|path| path.is_file())
+            .collect();
+        if !found.is_empty() {
+            return found;
+        }
+    }
+    Vec::new()
+}
+
+/// Documents the environment a cloned project needs, written as `.env.example`.
+pub const EXAMPLE_FILE: &str = ".env.example";
+
+/// A variable for `.env.example`, with a comment saying what it's for.
+pub struct Variable {
+    pub name: String,
+    pub comment: String,
+    pub placeholder: String,
+}
+
+/// Writes `vars` to `.env.example` at `root`, each with a placeholder value and its
+/// comment. An existing file only gets the variables it doesn't set yet, after the rest.
+pub fn write_example(root: &Path, header: &str, vars: &[Variable]) -> std::io::Result<()> {
+    let path = root.join(EXAMPLE_FILE);
+    let existing = match std::fs::read_to_string(&path) {
+        Ok(contents) => Some(contents),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    let declared = |name: &str| {
+        existing.iter().flat_map(|contents| contents.lines()).any(|line| {
+            let line = line.trim_start().trim_start_matches("export ");
+            line.split_once('=').is_some_and(|(key, _)| key.trim() == name)
+        })
+    };
+    let missing: Vec<&Variable> = vars.iter().filter(|var| !declared(&var.name)).collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let mut contents = match existing {
+        Some(mut contents) => {
+            if !contents.is_empty() && !contents.ends_with('\n') {
+                contents.push('\n');
+            }
+            contents
+        }
+        None => format!("{}\n", header.lines().map(|line| format!("# {}", line)).collect::<Vec<_>>().join("\n")),
+    };
+    for var in &missing {
+        contents.push_str(&format!("\n# {}\n{}={}\n", var.comment, var.name, var.placeholder));
+    }
+    std::fs::write(&path, contents)?;
+    crate::summary::file_written(&path);
+    let names: Vec<&str> = missing.iter().map(|var| var.name.as_str()).collect();
+    info!("Listed {} in {:?}", names.join(", "), path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_nearest_env_files_upwards() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let nested = root.join("project/contracts/src");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(root.join(".env"), "A=outer").unwrap();
+        std::fs::write(root.join("project/.env"), "A=inner").unwrap();
+        std::fs::write(root.join("project/.env.local"), "A=local").unwrap();
+
+        assert_eq!(
+            discover(&nested),
+            [root.join("project/.env.local"), root.join("project/.env")]
+        );
+        assert_eq!(discover(root), [root.join(".env")]);
+    }
+
+    #[test]
+    fn example_lists_each_variable_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let var = |name: &str| Variable { name: name.to_string(), comment: format!("For {}", name), placeholder: "changeme".to_string() };
+        write_example(dir.path(), "Needed by this project.\nCopy it to .env.", &[var("ETHERSCAN_API_KEY")]).unwrap();
+        let path = dir.path().join(EXAMPLE_FILE);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "# Needed by this project.\n# Copy it to .env.\n\n# For ETHERSCAN_API_KEY\nETHERSCAN_API_KEY=changeme\n"
+        );
+
+        write_example(dir.path(), "ignored", &[var("ETHERSCAN_API_KEY"), var("ETH_RPC_URL")]).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.matches("ETHERSCAN_API_KEY=").count(), 1);
+        assert!(contents.ends_with("\n# For ETH_RPC_URL\nETH_RPC_URL=changeme\n"), "{}", contents);
+        assert!(!contents.contains("ignored"));
+    }
+}
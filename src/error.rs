@@ -0,0 +1,215 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// The ways a run can fail. Each is reported on one line and maps onto an exit code,
+/// so scripts wrapping the tool can tell ordinary mistakes apart.
+///
+/// The library returns these from every fallible call. The binary's helpers return
+/// `Box<dyn Error>` and raise these for the failures worth telling apart; converting the
+/// box back with `From` recovers the variant.
+#[derive(Debug, Error)]
+pub enum TokenFetchError {
+    #[error("Unsupported chain `{chain}`{}, supported chains are: {}", did_you_mean(.suggestion), .supported.join(", "))]
+    UnsupportedChain {
+        chain: String,
+        /// A supported chain close to what was typed
+        suggestion: Option<String>,
+        supported: Vec<String>,
+    },
+    /// Arguments or inputs that can't work, such as a malformed address
+    #[error("{0}")]
+    InvalidInput(String),
+    #[error("{env} environment variable not set, pass --api-key or store a key with `key set {chain}`")]
+    MissingApiKey { env: String, chain: String },
+    #[error("Path {} already exists, pass --force to clone into it", .0.display())]
+    PathExists(PathBuf),
+    #[error("API error: {message}")]
+    ExplorerApi { message: String },
+    #[error("Contract source code is empty, the contract might not be verified")]
+    NotVerified,
+    /// The explorer has no record of the address, which it reports like an API error
+    #[error("Contract not verified or does not exist")]
+    NotFound,
+    /// Nothing is deployed at the address
+    #[error("No code at address {0} (EOA or selfdestructed)")]
+    NoCode(String),
+    #[error("forge failed: {stderr}")]
+    ForgeFailed { stderr: String },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    /// A request that never got a usable answer: timeouts, proxy failures, 5xx responses
+    #[error("{0}")]
+    Network(String),
+    #[error("Failed to parse JSON: {0}")]
+    Parse(#[from] serde_json::Error),
+    /// `--offline` was given and the run needed something it would have to fetch
+    #[error("{0} isn't cached, and --offline doesn't fetch it")]
+    NotCached(String),
+    /// `diff` found the two contracts to differ, or `verify-match` the local build and the
+    /// deployed code, which isn't a failure of the run
+    #[error("{0}")]
+    Differs(String),
+    /// Ctrl-C stopped the run
+    #[error("Interrupted")]
+    Interrupted,
+    /// `--deadline` ran out before the command finished
+    #[error("Deadline of {}s exceeded", .0.as_secs_f64())]
+    DeadlineExceeded(std::time::Duration),
+    #[error("{0}")]
+    Other(String),
+}
+
+impl TokenFetchError {
+    /// Wraps an error from a dependency that has no variant of its own.
+    pub fn other(e: impl std::fmt::Display) -> Self {
+        TokenFetchError::Other(e.to_string())
+    }
+
+    /// The process exit code for this error. These are stable, see the README.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            // 2 is also what clap exits with on usage errors
+            TokenFetchError::UnsupportedChain { .. } | TokenFetchError::InvalidInput(_) | TokenFetchError::PathExists(_) => 2,
+            TokenFetchError::MissingApiKey { .. } => 3,
+            TokenFetchError::NotVerified | TokenFetchError::NotFound => 4,
+            TokenFetchError::ExplorerApi { .. } | TokenFetchError::Parse(_) => 5,
+            TokenFetchError::Http(_) | TokenFetchError::Network(_) => 6,
+            TokenFetchError::ForgeFailed { .. } => 7,
+            TokenFetchError::NoCode(_) => 8,
+            TokenFetchError::NotCached(_) => 9,
+            TokenFetchError::Differs(_) => 10,
+            // What shells report for a process killed by SIGINT
+            TokenFetchError::Interrupted => 130,
+            // What timeout(1) exits with
+            TokenFetchError::DeadlineExceeded(_) => 124,
+            TokenFetchError::Io(_) | TokenFetchError::Other(_) => 1,
+        }
+    }
+
+    /// Every exit code a failed run can end with and what it means, in order, as the
+    /// README and the man page list them. The codes come from `exit_code`, one error
+    /// standing in for all that share its code, so the two can't drift apart.
+    pub fn exit_codes() -> Vec<(u8, &'static str)> {
+        let mut codes: Vec<_> = [
+            (TokenFetchError::Other(String::new()), "Any other error, such as a file system error or a batch with failed contracts."),
+            (TokenFetchError::InvalidInput(String::new()), "Usage or validation error: bad arguments, an unsupported chain, a malformed address, an existing target path."),
+            (TokenFetchError::MissingApiKey { env: String::new(), chain: String::new() }, "No API key for the chain."),
+            (TokenFetchError::NotFound, "The contract isn't verified, or the explorer has no record of the address."),
+            (TokenFetchError::ExplorerApi { message: String::new() }, "The explorer API returned an error or an unreadable response."),
+            (TokenFetchError::Network(String::new()), "Network error: connection failures, timeouts, proxy errors and 5xx responses."),
+            (TokenFetchError::ForgeFailed { stderr: String::new() }, "A forge command failed."),
+            (TokenFetchError::NoCode(String::new()), "No code is deployed at the address, as for an EOA or a selfdestructed contract."),
+            (TokenFetchError::NotCached(String::new()), "With --offline, a response the run needed isn't cached."),
+            (TokenFetchError::Differs(String::new()), "diff found the contracts to differ, or verify-match the local build to differ from the deployed code."),
+            (TokenFetchError::DeadlineExceeded(std::time::Duration::ZERO), "The --deadline ran out."),
+            (TokenFetchError::Interrupted, "Interrupted with Ctrl-C."),
+        ]
+        .into_iter()
+        .map(|(err, meaning)| (err.exit_code(), meaning))
+        .collect();
+        codes.sort_by_key(|(code, _)| *code);
+        codes
+    }
+
+    /// A stable name for the kind of error, for machine-readable output.
+    pub fn class(&self) -> &'static str {
+        match self {
+            TokenFetchError::UnsupportedChain { .. } => "unsupported_chain",
+            TokenFetchError::InvalidInput(_) => "invalid_input",
+            TokenFetchError::MissingApiKey { .. } => "missing_api_key",
+            TokenFetchError::PathExists(_) => "path_exists",
+            TokenFetchError::ExplorerApi { .. } => "explorer_api",
+            TokenFetchError::NotVerified => "not_verified",
+            TokenFetchError::NotFound => "not_found",
+            TokenFetchError::NoCode(_) => "no_code",
+            TokenFetchError::ForgeFailed { .. } => "forge_failed",
+            TokenFetchError::Io(_) => "io",
+            TokenFetchError::Http(_) => "http",
+            TokenFetchError::Network(_) => "network",
+            TokenFetchError::Parse(_) => "parse",
+            TokenFetchError::NotCached(_) => "not_cached",
+            TokenFetchError::Differs(_) => "differs",
+            TokenFetchError::Interrupted => "interrupted",
+            TokenFetchError::DeadlineExceeded(_) => "deadline_exceeded",
+            TokenFetchError::Other(_) => "other",
+        }
+    }
+}
+
+fn did_you_mean(suggestion: &Option<String>) -> String {
+    match suggestion {
+        Some(chain) => format!(" (did you mean `{}`?)", chain),
+        None => String::new(),
+    }
+}
+
+/// A failure without a variant of its own, described by its message alone.
+impl From<String> for TokenFetchError {
+    fn from(message: String) -> Self {
+        TokenFetchError::Other(message)
+    }
+}
+
+impl From<&str> for TokenFetchError {
+    fn from(message: &str) -> Self {
+        TokenFetchError::Other(message.to_string())
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for TokenFetchError {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        let e = match e.downcast::<TokenFetchError>() {
+            Ok(e) => return *e,
+            Err(e) => e,
+        };
+        let e = match e.downcast::<std::io::Error>() {
+            Ok(e) => return TokenFetchError::Io(*e),
+            Err(e) => e,
+        };
+        let e = match e.downcast::<reqwest::Error>() {
+            Ok(e) => return TokenFetchError::Http(*e),
+            Err(e) => e,
+        };
+        match e.downcast::<serde_json::Error>() {
+            Ok(e) => TokenFetchError::Parse(*e),
+            Err(e) => TokenFetchError::Other(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boxed_errors_keep_their_variant() {
+        let boxed: Box<dyn std::error::Error> = TokenFetchError::NotVerified.into();
+        assert!(matches!(TokenFetchError::from(boxed), TokenFetchError::NotVerified));
+
+        let boxed: Box<dyn std::error::Error> = serde_json::from_str::<serde_json::Value>("{").unwrap_err().into();
+        assert_eq!(TokenFetchError::from(boxed).exit_code(), 5);
+
+        let boxed: Box<dyn std::error::Error> = "No contract address given".into();
+        let err = TokenFetchError::from(boxed);
+        assert_eq!(err.exit_code(), 1);
+        assert_eq!(err.to_string(), "No contract address given");
+
+        let err = TokenFetchError::DeadlineExceeded(std::time::Duration::from_secs(90));
+        assert_eq!((err.exit_code(), err.to_string()), (124, "Deadline of 90s exceeded".to_string()));
+    }
+
+    #[test]
+    fn exit_codes_are_listed_once_and_documented() {
+        let codes: Vec<u8> = TokenFetchError::exit_codes().iter().map(|(code, _)| *code).collect();
+        let mut unique = codes.clone();
+        unique.dedup();
+        assert_eq!(codes, unique);
+
+        let readme = include_str!("../README.md");
+        for code in codes {
+            assert!(readme.contains(&format!("\n| {} | ", code)), "exit code {} is missing from the README", code);
+        }
+    }
+}
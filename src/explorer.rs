@@ -0,0 +1,160 @@
+use crate::chain::{chain_config, ChainConfig};
+use crate::error::TokenFetchError;
+use crate::response::ExplorerEnvelope;
+use crate::fetch::{FetchOptions, Fetcher};
+use crate::keys::{self, KeyPool};
+use crate::source::{self, ContractSource};
+use crate::throttle::Throttle;
+use crate::{address, http};
+
+/// Explorer URL for the verified source of `address`.
+pub fn build_url(config: &ChainConfig, address: &str, api_key: &str) -> String {
+    format!(
+        "{}?module=contract&action=getsourcecode&address={}&apikey={}",
+        config.api_url, address, api_key
+    )
+}
+
+/// Explorer URL for the runtime code at `address`, via its `eth_getCode` proxy.
+pub fn build_code_url(config: &ChainConfig, address: &str, api_key: &str) -> String {
+    format!(
+        "{}?module=proxy&action=eth_getCode&address={}&tag=latest&apikey={}",
+        config.api_url, address, api_key
+    )
+}
+
+/// Explorer URL for the transaction that deployed `address`.
+pub fn build_creation_url(config: &ChainConfig, address: &str, api_key: &str) -> String {
+    format!(
+        "{}?module=contract&action=getcontractcreation&contractaddresses={}&apikey={}",
+        config.api_url, address, api_key
+    )
+}
+
+/// Explorer URL for the transaction `tx_hash`, via its `eth_getTransactionByHash` proxy.
+pub fn build_transaction_url(config: &ChainConfig, tx_hash: &str, api_key: &str) -> String {
+    format!(
+        "{}?module=proxy&action=eth_getTransactionByHash&txhash={}&apikey={}",
+        config.api_url, tx_hash, api_key
+    )
+}
+
+/// Explorer URL for the receipt of the transaction `tx_hash`, via its
+/// `eth_getTransactionReceipt` proxy.
+pub fn build_receipt_url(config: &ChainConfig, tx_hash: &str, api_key: &str) -> String {
+    format!(
+        "{}?module=proxy&action=eth_getTransactionReceipt&txhash={}&apikey={}",
+        config.api_url, tx_hash, api_key
+    )
+}
+
+/// Explorer URL for the name, symbol, decimals and supply of the token at `address`, on
+/// explorers that have the endpoint.
+pub fn build_token_info_url(config: &ChainConfig, address: &str, api_key: &str) -> String {
+    format!(
+        "{}?module=token&action=tokeninfo&contractaddress={}&apikey={}",
+        config.api_url, address, api_key
+    )
+}
+
+/// Explorer URL for the total supply of the token at `address`.
+pub fn build_token_supply_url(config: &ChainConfig, address: &str, api_key: &str) -> String {
+    format!(
+        "{}?module=stats&action=tokensupply&contractaddress={}&apikey={}",
+        config.api_url, address, api_key
+    )
+}
+
+/// Explorer URL for calling `address` with `data`, via its `eth_call` proxy.
+pub fn build_call_url(config: &ChainConfig, address: &str, data: &str, api_key: &str) -> String {
+    format!(
+        "{}?module=proxy&action=eth_call&to={}&data={}&tag=latest&apikey={}",
+        config.api_url, address, data, api_key
+    )
+}
+
+/// The `result` of a response from the explorer's `proxy` module, which answers like a
+/// JSON-RPC node, with errors in an `error` object instead of a status.
+pub fn proxy_result(json: &serde_json::Value) -> Result<&serde_json::Value, TokenFetchError> {
+    match json.get("error") {
+        Some(error) => Err(TokenFetchError::ExplorerApi {
+            message: error["message"].as_str().unwrap_or("Unknown error").to_string(),
+        }),
+        None => Ok(&json["result"]),
+    }
+}
+
+/// Parses a response body and checks its status.
+pub fn parse_response(body: &str) -> Result<serde_json::Value, TokenFetchError> {
+    let json: serde_json::Value = serde_json::from_str(body)?;
+    check_status(json)
+}
+
+/// Turns a response with a non-`1` status into an API error.
+pub fn check_status(json: serde_json::Value) -> Result<serde_json::Value, TokenFetchError> {
+    match ExplorerEnvelope::from_response(&json)?.error() {
+        Some(error) => Err(error),
+        None => Ok(json),
+    }
+}
+
+/// Fetches the explorer's getsourcecode response for `address` on `chain`, with the
+/// status checked but nothing written, for tools that only want the metadata.
+///
+/// Requests go through a client using the proxy settings from the environment, with the
+/// default [`FetchOptions`]: retried like the CLI's, but not cached.
+pub async fn fetch_raw(chain: &str, address: &str, api_key: &str) -> Result<serde_json::Value, TokenFetchError> {
+    let config = chain_config(chain)?;
+    let address = address::normalize(address, false)?;
+    let fetcher = Fetcher::new(http::Client::new(None, None)?, FetchOptions::default());
+    fetcher.fetch_cached(config, &address, &KeyPool::new(keys::parse_keys(api_key)), &Throttle::new(1, config.rate_limit)).await
+}
+
+/// Fetches and extracts the verified source of `address` from the explorer of `config`
+/// through `fetcher`, as a clone does: retried, cached and rotating through the
+/// comma-separated keys in `api_key` as `fetcher` is set up to, and fetching an unverified
+/// minimal proxy as the contract it delegates to. Nothing is written.
+pub async fn fetch_contract_source(fetcher: &Fetcher, config: &ChainConfig, address: &str, api_key: &str) -> Result<ContractSource, TokenFetchError> {
+    let address = address::normalize(address, false)?;
+    let keys = KeyPool::new(keys::parse_keys(api_key));
+    let loaded = fetcher.load_contract(config, &keys, &Throttle::new(1, config.rate_limit), None, &address).await?;
+    source::extract_sources(&loaded.json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn serve(json: serde_json::Value) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200).set_body_json(json)).mount(&server).await;
+        server
+    }
+
+    #[tokio::test]
+    async fn fetches_the_checked_source() {
+        let fetcher = Fetcher::new(http::Client::new(None, None).unwrap(), FetchOptions::default());
+        let server = serve(serde_json::json!({
+            "status": "1",
+            "message": "OK",
+            "result": [{ "SourceCode": "contract Token {}", "ContractName": "Token", "CompilerVersion": "v0.8.19+commit.7dd6d404" }]
+        }))
+        .await;
+        let config = ChainConfig::custom("explorer-test", &server.uri(), 9999, None, None, None).unwrap();
+        let contract = fetch_contract_source(&fetcher, &config, "0x1111111111111111111111111111111111111111", "key").await.unwrap();
+        assert_eq!(contract.contract_name.as_deref(), Some("Token"));
+        assert_eq!(contract.sources()["Token.sol"]["content"], "contract Token {}");
+
+        let server = serve(serde_json::json!({ "status": "0", "message": "NOTOK", "result": "Invalid API Key" })).await;
+        let config = ChainConfig::custom("explorer-rejects", &server.uri(), 9998, None, None, None).unwrap();
+        let err = fetch_contract_source(&fetcher, &config, "0x1111111111111111111111111111111111111111", "key").await.unwrap_err();
+        assert!(matches!(err, TokenFetchError::ExplorerApi { .. }), "{:?}", err);
+        let err = fetch_contract_source(&fetcher, &config, "0x1111111111111111111111111111111111111111", "").await.unwrap_err();
+        assert_eq!(err.exit_code(), 2);
+
+        let err = fetch_raw("polygon", "0x0000000000000000000000000000000000000001", "key").await.unwrap_err();
+        assert_eq!(err.exit_code(), 2);
+    }
+}
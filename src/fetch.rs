@@ -0,0 +1,532 @@
+use crate::chain::ChainConfig;
+use crate::error::TokenFetchError;
+use crate::explorer::{build_code_url, build_url, check_status, proxy_result};
+use crate::keys::{KeyPool, Pick};
+use crate::response::SourceCodeResult;
+use crate::throttle::Throttle;
+use crate::{cache, http, progress, rpc, verify};
+use log::{debug, error, info, trace, warn};
+use std::time::Duration;
+
+/// How explorer requests are retried and cached.
+#[derive(Clone, Debug)]
+pub struct FetchOptions {
+    /// Retry transient failures this many times (connection errors, timeouts and 5xx responses)
+    pub retries: u32,
+    /// Delay before the first retry, doubled on every further attempt
+    pub retry_delay: Duration,
+    /// How many times to wait and retry when every key is throttled
+    pub rate_limit_retries: u32,
+    /// Neither read nor write the response cache
+    pub no_cache: bool,
+    /// Always query the explorer, overwriting the cached response
+    pub refresh: bool,
+    /// Never use the network, failing with `NotCached` on anything that isn't cached
+    pub offline: bool,
+    /// Treat cached responses older than this as missing
+    pub max_age: Option<Duration>,
+}
+
+/// The CLI's defaults, except that nothing is cached: a library caller opts in with
+/// `no_cache: false`.
+impl Default for FetchOptions {
+    fn default() -> Self {
+        FetchOptions {
+            retries: 2,
+            retry_delay: Duration::from_secs(1),
+            rate_limit_retries: 5,
+            no_cache: true,
+            refresh: false,
+            offline: false,
+            max_age: None,
+        }
+    }
+}
+
+/// Queries explorers the way the CLI does: through one client, so connections are
+/// reused, with retries, key rotation and the response cache as `options` set them.
+pub struct Fetcher {
+    pub client: http::Client,
+    pub options: FetchOptions,
+}
+
+/// A getsourcecode response as the explorer sent it, along with its entry, read once for
+/// everything done with it.
+#[derive(Clone)]
+pub struct Loaded {
+    pub json: serde_json::Value,
+    pub entry: SourceCodeResult,
+    /// Where the response was fetched from, when that isn't the address asked for
+    pub source_address: Option<String>,
+}
+
+impl Loaded {
+    pub fn read(json: serde_json::Value) -> Result<Self, TokenFetchError> {
+        let entry = SourceCodeResult::from_response(&json)?;
+        Ok(Loaded { json, entry, source_address: None })
+    }
+}
+
+impl Fetcher {
+    pub fn new(client: http::Client, options: FetchOptions) -> Self {
+        Fetcher { client, options }
+    }
+
+    /// Queries the explorer, building the request URL for whichever API key is next in `keys`.
+    ///
+    /// Transient failures are retried with backoff. A key the explorer throttles is benched
+    /// for a while and the next one used; once every key is cooling down, the request waits
+    /// for the first to come back, up to `rate_limit_retries` times. With several keys, one
+    /// the explorer rejects is dropped, so the request only fails once every key is exhausted.
+    /// Every attempt, retries included, waits its turn at `throttle`.
+    pub async fn query(&self, throttle: &Throttle, keys: &KeyPool, url_for: impl Fn(&str) -> String) -> Result<serde_json::Value, TokenFetchError> {
+        if self.options.offline {
+            return Err(TokenFetchError::NotCached("An explorer response".into()));
+        }
+        let mut throttled = 0;
+        let mut last_error = None;
+        loop {
+            let (slot, key) = match keys.pick() {
+                Pick::Key(slot, key) => (slot, key),
+                Pick::Wait(delay) if throttled < self.options.rate_limit_retries => {
+                    throttled += 1;
+                    info!(
+                        "Explorer is throttling requests, waiting {:.1}s before retry {}/{}",
+                        delay.as_secs_f64(),
+                        throttled,
+                        self.options.rate_limit_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+                // A key is only benched or dropped after an error, so without one there were no keys
+                Pick::Wait(_) | Pick::Exhausted => match last_error {
+                    Some(json) => return check_status(json),
+                    None => return Err(TokenFetchError::InvalidInput("No API key to query the explorer with".into())),
+                },
+            };
+
+            if keys.len() > 1 {
+                debug!("Querying the explorer with API key {}", slot + 1);
+            }
+            let json = self.fetch_json(throttle, &url_for(&key)).await?;
+            if let Some(reason) = throttle_reason(&json) {
+                let cooldown = backoff(RATE_LIMIT_DELAY, throttled + 1);
+                if keys.len() > 1 {
+                    info!("API key {} is throttled ({}), benching it for {:.1}s", slot + 1, reason, cooldown.as_secs_f64());
+                } else {
+                    info!("Explorer is throttling requests ({})", reason);
+                }
+                keys.bench(slot, cooldown);
+                last_error = Some(json);
+                continue;
+            }
+            if keys.len() > 1 && is_invalid_key(&json) {
+                warn!("API key {} was rejected, dropping it", slot + 1);
+                keys.reject(slot);
+                last_error = Some(json);
+                continue;
+            }
+
+            // Every other API error is fatal
+            return check_status(json);
+        }
+    }
+
+    /// Sends one explorer request, retrying transient failures with backoff.
+    pub async fn fetch_json(&self, throttle: &Throttle, url: &str) -> Result<serde_json::Value, TokenFetchError> {
+        let mut attempt = 1;
+        loop {
+            let response = {
+                let _permit = throttle.acquire().await;
+                self.client.get_text(url).await
+            };
+            match response {
+                Ok(body) => return Ok(serde_json::from_str(&body)?),
+                Err(http::RequestError::Transient(e)) if attempt <= self.options.retries => {
+                    let delay = backoff(self.options.retry_delay, attempt);
+                    info!("Attempt {} failed ({}), retrying in {:.1}s", attempt, e, delay.as_secs_f64());
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(http::RequestError::Transient(e)) => {
+                    trace!("Giving up after {} attempts", attempt);
+                    return Err(e);
+                }
+                Err(http::RequestError::Fatal(e)) => {
+                    trace!("Not retrying, a retry would fail the same way");
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    /// Fetches a contract's getsourcecode response, reusing a cached one when available.
+    pub async fn fetch_cached(&self, config: &ChainConfig, address: &str, keys: &KeyPool, throttle: &Throttle) -> Result<serde_json::Value, TokenFetchError> {
+        let options = &self.options;
+        if !options.no_cache && !options.refresh {
+            if let Some(json) = cache::load(config.chain_id, address, options.max_age) {
+                return Ok(json);
+            }
+        }
+        if options.offline {
+            return Err(TokenFetchError::NotCached(format!("The source of {} on {}", address, config.name)));
+        }
+
+        let json = {
+            info!("Fetching contract from API...");
+            let _spinner = progress::spinner(format!("Fetching {}", address));
+            self.query(throttle, keys, |key| build_url(config, address, key)).await?
+        };
+
+        if !options.no_cache {
+            if let Err(e) = cache::store(config.chain_id, address, &json) {
+                error!("Failed to cache response: {}", e);
+            }
+        }
+
+        Ok(json)
+    }
+
+    /// The runtime code at `address`, read through `rpc_url` when given and otherwise the explorer.
+    pub async fn deployed_code(
+        &self,
+        config: &ChainConfig,
+        keys: &KeyPool,
+        throttle: &Throttle,
+        rpc_url: Option<&str>,
+        address: &str,
+    ) -> Result<String, TokenFetchError> {
+        if let Some(rpc_url) = rpc_url {
+            return rpc::get_code(self.client.reqwest(), rpc_url, address).await;
+        }
+        let json = self.query(throttle, keys, |key| build_code_url(config, address, key)).await?;
+        match proxy_result(&json)?.as_str() {
+            Some(code) => Ok(code.to_string()),
+            None => Err(TokenFetchError::Other("No code in explorer response".into())),
+        }
+    }
+
+    /// Fetches the getsourcecode response of `address`. An unverified EIP-1167 minimal
+    /// proxy is fetched as the contract it delegates to, its code read through `rpc_url`
+    /// when given.
+    pub async fn load_contract(
+        &self,
+        config: &ChainConfig,
+        keys: &KeyPool,
+        throttle: &Throttle,
+        rpc_url: Option<&str>,
+        address: &str,
+    ) -> Result<Loaded, TokenFetchError> {
+        let json = Loaded::read(self.fetch_cached(config, address, keys, throttle).await?)?;
+        if !json.entry.source_code.is_empty() {
+            return Ok(json);
+        }
+        let code = match self.deployed_code(config, keys, throttle, rpc_url, address).await {
+            Ok(code) => code,
+            Err(e) => {
+                debug!("Not checking whether {} is a minimal proxy: {}", address, e);
+                return Ok(json);
+            }
+        };
+        let Some(target) = verify::decode_hex(&code).ok().and_then(|code| verify::minimal_proxy_target(&code)) else {
+            return Ok(json);
+        };
+        info!("{} is an EIP-1167 minimal proxy for {}, fetching that instead", address, target);
+        let target = target.to_string();
+        let loaded = Loaded::read(self.fetch_cached(config, &target, keys, throttle).await?)?;
+        Ok(Loaded { source_address: Some(target), ..loaded })
+    }
+}
+
+/// Whether the explorer refused the request's API key.
+fn is_invalid_key(json: &serde_json::Value) -> bool {
+    json["status"].as_str() == Some("0")
+        && json["result"]
+            .as_str()
+            .is_some_and(|result| result.to_lowercase().contains("invalid api key"))
+}
+
+/// Initial wait after the explorer reports a rate limit, doubled on repeats.
+const RATE_LIMIT_DELAY: Duration = Duration::from_millis(1500);
+
+/// Returns the explorer's message when a `NOTOK` response only means "slow down".
+///
+/// Explorers answer throttled requests with HTTP 200 and `status: "0"`, so these have
+/// to be told apart from fatal API errors by their text.
+fn throttle_reason(json: &serde_json::Value) -> Option<&str> {
+    if json["status"].as_str() != Some("0") {
+        return None;
+    }
+    let result = json["result"].as_str()?;
+
+    let lower = result.to_lowercase();
+    let transient = lower.contains("rate limit")
+        || lower.contains("try again later")
+        || lower.contains("query timeout");
+    transient.then_some(result)
+}
+
+/// Exponential backoff from `base`, with up to 50% random jitter so parallel
+/// fetches don't retry in lockstep.
+pub fn backoff(base: Duration, attempt: u32) -> Duration {
+    use std::hash::{BuildHasher, Hasher};
+
+    let delay = base.saturating_mul(2u32.saturating_pow(attempt - 1));
+    // RandomState is seeded randomly per instance, which is plenty for jitter
+    let random = std::collections::hash_map::RandomState::new().build_hasher().finish();
+    let jitter = delay.mul_f64((random % 1000) as f64 / 2000.0);
+
+    delay.saturating_add(jitter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn options() -> FetchOptions {
+        FetchOptions { retry_delay: Duration::from_millis(1), ..FetchOptions::default() }
+    }
+
+    fn fetcher_with(options: FetchOptions) -> Fetcher {
+        Fetcher::new(http::Client::new(None, None).unwrap(), options)
+    }
+
+    fn fetcher() -> Fetcher {
+        fetcher_with(options())
+    }
+
+    async fn fetch(fetcher: &Fetcher, url: &str) -> Result<serde_json::Value, TokenFetchError> {
+        fetcher.query(&Throttle::new(1, 1000), &KeyPool::new(vec!["key".to_string()]), |_| url.to_string()).await
+    }
+
+    fn response(source_code: &str) -> serde_json::Value {
+        serde_json::json!({
+            "status": "1",
+            "message": "OK",
+            "result": [{ "SourceCode": source_code, "ABI": "[]", "ContractName": "Token", "CompilerVersion": "v0.8.19+commit.7dd6d404" }]
+        })
+    }
+
+    #[test]
+    fn backoff_saturates_instead_of_overflowing() {
+        let delay = backoff(Duration::from_secs(1), 2);
+        assert!(delay >= Duration::from_secs(2) && delay < Duration::from_secs(3), "{:?}", delay);
+        assert_eq!(backoff(Duration::MAX / 2, 100), Duration::MAX);
+    }
+
+    /// Serves `body` to requests made with `key`.
+    async fn mount_for_key(server: &MockServer, key: &str, body: serde_json::Value) {
+        Mock::given(method("GET"))
+            .and(query_param("apikey", key))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(server)
+            .await;
+    }
+
+    fn key_url(server: &MockServer, key: &str) -> String {
+        format!("{}/api?module=contract&apikey={}", server.uri(), key)
+    }
+
+    #[tokio::test]
+    async fn rotates_past_throttled_and_rejected_keys() {
+        let server = MockServer::start().await;
+        let throttled = serde_json::json!({ "status": "0", "message": "NOTOK", "result": "Max calls per sec rate limit reached (5/sec)" });
+        let rejected = serde_json::json!({ "status": "0", "message": "NOTOK", "result": "Invalid API Key" });
+        mount_for_key(&server, "k1", throttled).await;
+        mount_for_key(&server, "k2", rejected).await;
+        mount_for_key(&server, "k3", response("contract Token {}")).await;
+
+        let keys = KeyPool::new(vec!["k1".to_string(), "k2".to_string(), "k3".to_string()]);
+        let fetcher = fetcher();
+        let throttle = Throttle::new(1, 1000);
+        let json = fetcher.query(&throttle, &keys, |key| key_url(&server, key)).await.unwrap();
+        assert_eq!(json["result"][0]["SourceCode"], "contract Token {}");
+
+        // k1 is benched and k2 dropped, so the next request goes straight to k3
+        fetcher.query(&throttle, &keys, |key| key_url(&server, key)).await.unwrap();
+        assert_eq!(keys.served(), [1, 1, 2]);
+        // Every attempt took its own turn at the throttle
+        assert_eq!(throttle.requests(), 4);
+    }
+
+    #[tokio::test]
+    async fn fails_once_every_key_is_rejected() {
+        let server = MockServer::start().await;
+        let rejected = serde_json::json!({ "status": "0", "message": "NOTOK", "result": "Invalid API Key" });
+        mount_for_key(&server, "k1", rejected.clone()).await;
+        mount_for_key(&server, "k2", rejected).await;
+
+        let keys = KeyPool::new(vec!["k1".to_string(), "k2".to_string()]);
+        let err = fetcher().query(&Throttle::new(1, 1000), &keys, |key| key_url(&server, key))
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "API error: NOTOK - Invalid API Key");
+        assert_eq!(keys.served(), [1, 1]);
+    }
+
+    #[tokio::test]
+    async fn no_records_found_is_not_an_api_error() {
+        let body = serde_json::json!({ "status": "0", "message": "No records found", "result": [] });
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200).set_body_json(body)).mount(&server).await;
+        let err = fetch(&fetcher(), &server.uri()).await.unwrap_err();
+        assert!(matches!(err, TokenFetchError::NotFound), "{:?}", err);
+        assert_eq!(err.to_string(), "Contract not verified or does not exist");
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(502))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response("contract Token {}")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let json = fetch(&fetcher(), &server.uri()).await.unwrap();
+        assert_eq!(json["result"][0]["SourceCode"], "contract Token {}");
+    }
+
+    #[tokio::test]
+    async fn html_error_pages_are_retried_and_reported() {
+        let server = MockServer::start().await;
+        let page = ResponseTemplate::new(503).insert_header("content-type", "text/html").set_body_string("<html><title>Just a moment...</title></html>");
+        Mock::given(method("GET")).respond_with(page).up_to_n_times(2).expect(2).mount(&server).await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response("contract Token {}")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let err = fetch(&fetcher_with(FetchOptions { retries: 0, ..options() }), &server.uri()).await.unwrap_err();
+        assert_eq!(err.to_string(), "explorer returned HTTP 503 Service Unavailable / challenge page");
+        let json = fetch(&fetcher(), &server.uri()).await.unwrap();
+        assert_eq!(json["result"][0]["SourceCode"], "contract Token {}");
+    }
+
+    #[tokio::test]
+    async fn identifies_itself() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header("user-agent", concat!("tokenfetch/", env!("CARGO_PKG_VERSION"))))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response("contract Token {}")))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        // Both requests go through the fetcher's one client
+        let fetcher = fetcher();
+        fetch(&fetcher, &server.uri()).await.unwrap();
+        fetch(&fetcher, &server.uri()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(403))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let err = fetch(&fetcher(), &server.uri()).await.unwrap_err();
+        assert_eq!(err.to_string(), "API error: explorer returned HTTP 403 Forbidden");
+    }
+
+    #[tokio::test]
+    async fn client_error_pages_are_api_errors() {
+        let server = MockServer::start().await;
+        let page = ResponseTemplate::new(404).insert_header("content-type", "text/html").set_body_string("<html>Not Found</html>");
+        Mock::given(method("GET")).respond_with(page).expect(1).mount(&server).await;
+
+        let err = fetch(&fetcher(), &server.uri()).await.unwrap_err();
+        assert_eq!(err.to_string(), "API error: explorer returned HTTP 404 Not Found / HTML error page");
+        assert_eq!(err.exit_code(), 5);
+    }
+
+    #[tokio::test]
+    async fn waits_out_rate_limits() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "status": "0",
+                "message": "NOTOK",
+                "result": "Max calls per sec rate limit reached (5/sec)"
+            })))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response("contract Token {}")))
+            .mount(&server)
+            .await;
+
+        let fetcher = fetcher_with(FetchOptions { rate_limit_retries: 1, ..options() });
+        let json = fetch(&fetcher, &server.uri()).await.unwrap();
+        assert_eq!(json["result"][0]["SourceCode"], "contract Token {}");
+    }
+
+    #[tokio::test]
+    async fn invalid_api_key_is_fatal() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "status": "0",
+                "message": "NOTOK",
+                "result": "Invalid API Key"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let fetcher = fetcher_with(FetchOptions { rate_limit_retries: 3, ..options() });
+        let err = fetch(&fetcher, &server.uri()).await.unwrap_err();
+        assert_eq!(err.to_string(), "API error: NOTOK - Invalid API Key");
+    }
+
+    #[tokio::test]
+    async fn reports_timeouts_readably() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(2)))
+            .mount(&server)
+            .await;
+
+        let client = http::Client::new(None, Some(Duration::from_millis(100))).unwrap();
+        let err = fetch(&Fetcher::new(client, FetchOptions { retries: 0, ..options() }), &server.uri()).await.unwrap_err();
+        assert_eq!(err.to_string(), "request to 127.0.0.1 timed out after 0.1s");
+    }
+
+    #[tokio::test]
+    async fn errors_never_show_the_api_key() {
+        // Nothing listens on port 1, so the request fails with its URL in the error
+        let fetcher = fetcher_with(FetchOptions { retries: 0, ..options() });
+        let url = "http://127.0.0.1:1/api?module=contract&apikey=s3cr3t";
+        let err = fetch(&fetcher, url).await.unwrap_err();
+
+        assert!(!err.to_string().contains("s3cr3t"), "{}", err);
+        assert!(err.to_string().contains("apikey=REDACTED"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn rate_limited_response() {
+        let body = serde_json::json!({
+            "status": "0",
+            "message": "NOTOK",
+            "result": "Max rate limit reached"
+        });
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200).set_body_json(body)).mount(&server).await;
+        let err = fetch(&fetcher_with(FetchOptions { rate_limit_retries: 0, ..options() }), &server.uri()).await.unwrap_err();
+        assert_eq!(err.to_string(), "API error: NOTOK - Max rate limit reached");
+    }
+}
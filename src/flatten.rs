@@ -0,0 +1,112 @@
+use crate::imports;
+use log::warn;
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+
+/// The sources in an order that has every file after the files it imports, starting from
+/// the first by path. Import cycles are broken where they close.
+fn order(sources: &Map<String, Value>, remappings: &[(String, String)]) -> Vec<String> {
+    fn visit(file: &str, sources: &Map<String, Value>, remappings: &[(String, String)], seen: &mut BTreeSet<String>, order: &mut Vec<String>) {
+        if !seen.insert(file.to_string()) {
+            return;
+        }
+        for import in imports::imports(sources[file]["content"].as_str().unwrap_or_default()) {
+            if let Some(imported) = imports::resolve(file, &import, sources, remappings) {
+                visit(&imported, sources, remappings, seen, order);
+            }
+        }
+        order.push(file.to_string());
+    }
+    let (mut seen, mut order) = (BTreeSet::new(), Vec::new());
+    for file in sources.keys() {
+        visit(file, sources, remappings, &mut seen, &mut order);
+    }
+    order
+}
+
+/// Whether `line`, with its indentation trimmed, starts an import statement.
+fn starts_import(line: &str) -> bool {
+    line.strip_prefix("import").is_some_and(|rest| rest.starts_with(|c: char| c.is_whitespace() || c == '"' || c == '\'' || c == '{' || c == '*'))
+}
+
+/// The sources as a single file, each under a `// File: <path>` header after the files it
+/// imports. Import statements are dropped, as are SPDX lines after the first and repeated
+/// pragmas, which solc would reject. Imports that rename what they import (`as`) are
+/// warned about, since the flattened file refers to the original names.
+pub fn flatten(sources: &Map<String, Value>, remappings: &[(String, String)]) -> String {
+    let mut out = String::new();
+    let mut spdx = false;
+    let mut pragmas = BTreeSet::new();
+    for file in order(sources, remappings) {
+        out.push_str(&format!("// File: {}\n\n", file));
+        let mut import = None::<String>;
+        for line in sources[&file]["content"].as_str().unwrap_or_default().lines() {
+            let trimmed = line.trim();
+            if import.is_some() || starts_import(trimmed) {
+                let statement = import.get_or_insert_with(String::new);
+                statement.push_str(trimmed);
+                statement.push(' ');
+                if trimmed.contains(';') {
+                    if statement.contains(" as ") {
+                        warn!("{} renames what it imports ({}), the flattened source still uses the original names", file, statement.trim());
+                    }
+                    import = None;
+                }
+                continue;
+            }
+            if trimmed.starts_with("//") && trimmed.contains("SPDX-License-Identifier:") {
+                if spdx {
+                    continue;
+                }
+                spdx = true;
+            }
+            if trimmed.starts_with("pragma ") && !pragmas.insert(trimmed.to_string()) {
+                continue;
+            }
+            out.push_str(line);
+            out.push('\n');
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The sources one after the other, each under a `// File: <path>` header, as verified.
+pub fn concatenate(sources: &Map<String, Value>) -> String {
+    let mut out = String::new();
+    for (file, source) in sources {
+        out.push_str(&format!("// File: {}\n\n", file));
+        out.push_str(source["content"].as_str().unwrap_or_default());
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flattens_imports_before_their_importers() {
+        let sources: Map<String, Value> = [
+            ("src/Token.sol", "// SPDX-License-Identifier: MIT\npragma solidity ^0.8.0;\nimport {\n  ERC20\n} from \"@oz/ERC20.sol\";\nimport \"./Math.sol\";\ncontract Token is ERC20 {}"),
+            ("src/Math.sol", "// SPDX-License-Identifier: MIT\npragma solidity ^0.8.0;\nlibrary Math {}"),
+            ("lib/oz/ERC20.sol", "// SPDX-License-Identifier: MIT\npragma solidity ^0.8.0;\n// import \"./Nope.sol\";\nimport \"../../src/Math.sol\";\ncontract ERC20 {}"),
+        ]
+        .into_iter()
+        .map(|(path, content)| (path.to_string(), serde_json::json!({ "content": content })))
+        .collect();
+        let remappings = [("@oz/".to_string(), "lib/oz/".to_string())];
+
+        assert_eq!(
+            flatten(&sources, &remappings),
+            "// File: src/Math.sol\n\n// SPDX-License-Identifier: MIT\npragma solidity ^0.8.0;\nlibrary Math {}\n\n\
+             // File: lib/oz/ERC20.sol\n\n// import \"./Nope.sol\";\ncontract ERC20 {}\n\n\
+             // File: src/Token.sol\n\ncontract Token is ERC20 {}\n\n"
+        );
+        assert!(concatenate(&sources).starts_with("// File: lib/oz/ERC20.sol\n\n// SPDX-License-Identifier: MIT\n"));
+    }
+}
@@ -0,0 +1,627 @@
+use crate::error::TokenFetchError;
+use crate::{progress, summary};
+use log::{debug, info, warn};
+use std::path::Path;
+use std::time::Duration;
+
+/// Runs a forge command, failing with forge's own error output if it doesn't succeed.
+///
+/// Forge can hang, e.g. while downloading solc on a broken network, so it's killed if
+/// it hasn't finished within `timeout`.
+pub async fn run(forge: &mut tokio::process::Command, timeout: Duration) -> Result<(), TokenFetchError> {
+    forge.kill_on_drop(true);
+    debug!("Running {:?}", forge.as_std());
+    let step = forge.as_std().get_args().next().map(|arg| arg.to_string_lossy().into_owned()).unwrap_or_default();
+    let _spinner = progress::spinner(format!("Running forge {}", step));
+    let output = match tokio::time::timeout(timeout, forge.output()).await {
+        Ok(output) => output.map_err(|e| -> TokenFetchError {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                "forge not found, install Foundry from https://getfoundry.sh".into()
+            } else {
+                format!("Failed to run forge: {}", e).into()
+            }
+        })?,
+        Err(_) => {
+            return Err(TokenFetchError::ForgeFailed {
+                stderr: format!(
+                    "timed out after {}s and was killed; check that the Foundry install works (`forge --version`) or raise --forge-timeout",
+                    timeout.as_secs_f64()
+                ),
+            })
+        }
+    };
+    if !output.status.success() {
+        return Err(TokenFetchError::ForgeFailed {
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Applies the verified compiler configuration to the project's `foundry.toml`, so a
+/// local build uses the same solc version and settings as the on-chain deployment.
+///
+/// `src_dir` is where the contract's sources were written, relative to `root`. Any
+/// profile other than `default` is scoped to that directory, so several contracts with
+/// different settings can live in one project and be built with `FOUNDRY_PROFILE`.
+pub fn apply_compiler_settings(
+    root: &Path,
+    profile_name: &str,
+    src_dir: &str,
+    solc: Option<&str>,
+    settings: Option<&serde_json::Value>,
+) -> Result<(), TokenFetchError> {
+    let config_path = root.join("foundry.toml");
+    let mut doc: toml::Table = match std::fs::read_to_string(&config_path) {
+        Ok(contents) => contents.parse().map_err(TokenFetchError::other)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml::Table::new(),
+        Err(e) => return Err(e.into()),
+    };
+
+    let profile = profile_table(&mut doc, profile_name);
+    if profile_name != "default" {
+        profile.insert("src".to_string(), toml::Value::String(src_dir.to_string()));
+    }
+
+    if let Some(version) = solc {
+        info!("Pinning solc version {}", version);
+        profile.insert("solc_version".to_string(), toml::Value::String(version.to_string()));
+    }
+
+    if let Some(settings) = settings {
+        settings_to_profile(settings, src_dir, profile);
+    }
+
+    std::fs::write(&config_path, toml::to_string(&doc).map_err(TokenFetchError::other)?)?;
+    summary::file_written(&config_path);
+    info!("Updated {:?}", config_path);
+
+    Ok(())
+}
+
+/// The `src` directory the default profile of the project's `foundry.toml` sets, if any,
+/// as a template may use `contracts/` instead of forge's `src/`.
+pub fn configured_src(root: &Path) -> Option<String> {
+    let doc: toml::Table = std::fs::read_to_string(root.join("foundry.toml")).ok()?.parse().ok()?;
+    let src = doc.get("profile")?.get("default")?.get("src")?.as_str()?;
+    Some(src.trim_end_matches('/').to_string())
+}
+
+/// Points the default profile of the project's `foundry.toml` at `src_dir`.
+pub fn set_src(root: &Path, src_dir: &str) -> Result<(), TokenFetchError> {
+    let config_path = root.join("foundry.toml");
+    let mut doc: toml::Table = match std::fs::read_to_string(&config_path) {
+        Ok(contents) => contents.parse().map_err(TokenFetchError::other)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml::Table::new(),
+        Err(e) => return Err(e.into()),
+    };
+
+    profile_table(&mut doc, "default").insert("src".to_string(), toml::Value::String(src_dir.to_string()));
+    std::fs::write(&config_path, toml::to_string(&doc).map_err(TokenFetchError::other)?)?;
+    summary::file_written(&config_path);
+    Ok(())
+}
+
+/// Adds a `compliance` profile building only `compliance/`, with a solc new enough for
+/// forge-std, for a project whose pinned solc is older than that.
+pub fn add_compliance_profile(root: &Path, solc: &str) -> Result<(), TokenFetchError> {
+    let config_path = root.join("foundry.toml");
+    let mut doc: toml::Table = match std::fs::read_to_string(&config_path) {
+        Ok(contents) => contents.parse().map_err(TokenFetchError::other)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml::Table::new(),
+        Err(e) => return Err(e.into()),
+    };
+
+    let profile = profile_table(&mut doc, "compliance");
+    for (key, value) in [("src", "compliance"), ("test", "compliance"), ("solc_version", solc), ("evm_version", "paris")] {
+        profile.insert(key.to_string(), toml::Value::String(value.to_string()));
+    }
+
+    std::fs::write(&config_path, toml::to_string(&doc).map_err(TokenFetchError::other)?)?;
+    summary::file_written(&config_path);
+    Ok(())
+}
+
+/// Renders a forge test that forks the chain, at `fork_block` or the latest block, and
+/// points `contract` (imported from `import_path`) at its deployment.
+///
+/// The fork URL comes from the `rpc_env` environment variable, so no endpoint ends up
+/// in the project.
+pub fn fork_test(
+    contract: &str,
+    import_path: &str,
+    address: &str,
+    compiler_version: Option<&str>,
+    rpc_env: &str,
+    fork_block: Option<u64>,
+) -> Result<String, TokenFetchError> {
+    // Solidity only accepts checksummed address literals
+    let address = address.parse::<alloy_primitives::Address>().map_err(TokenFetchError::other)?.to_checksum(None);
+    let pragma = compiler_version.and_then(solc_version).unwrap_or_else(|| "0.8.0".to_string());
+    let fork = match fork_block {
+        Some(block) => format!("vm.createSelectFork(vm.envString(\"{}\"), {});", rpc_env, block),
+        None => format!("vm.createSelectFork(vm.envString(\"{}\"));", rpc_env),
+    };
+
+    Ok(format!(
+        r#"// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^{pragma};
+
+import {{Test}} from "forge-std/Test.sol";
+import {{{contract}}} from "{import_path}";
+
+contract {contract}ForkTest is Test {{
+    {contract} internal target;
+
+    function setUp() public {{
+        {fork}
+        target = {contract}({address});
+    }}
+
+    function test_IsDeployed() public view {{
+        assertGt(address(target).code.length, 0);
+    }}
+}}
+"#
+    ))
+}
+
+/// Returns the `[profile.<name>]` table, creating it if missing.
+fn profile_table<'a>(doc: &'a mut toml::Table, name: &str) -> &'a mut toml::Table {
+    let profiles = doc
+        .entry("profile")
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+    if !profiles.is_table() {
+        *profiles = toml::Value::Table(toml::Table::new());
+    }
+    let profiles = profiles.as_table_mut().unwrap();
+
+    let profile = profiles
+        .entry(name)
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+    if !profile.is_table() {
+        *profile = toml::Value::Table(toml::Table::new());
+    }
+    profile.as_table_mut().unwrap()
+}
+
+/// Whether forge already has solc `version`, i.e. svm (which forge manages compilers
+/// with) installed it under `~/.svm` or, on newer setups, the platform data directory.
+pub fn solc_installed(version: &str) -> bool {
+    let dirs = [dirs::home_dir().map(|home| home.join(".svm")), dirs::data_dir().map(|data| data.join("svm"))];
+    dirs.into_iter().flatten().any(|dir| svm_has(&dir, version))
+}
+
+fn svm_has(svm_dir: &Path, version: &str) -> bool {
+    svm_dir.join(version).join(format!("solc-{}", version)).is_file()
+}
+
+/// Extracts `0.8.19` from explorer versions such as `v0.8.19+commit.7dd6d404`.
+pub fn solc_version(compiler_version: &str) -> Option<String> {
+    let version = compiler_version.trim().trim_start_matches('v');
+    let version = version.split('+').next()?;
+
+    let parts: Vec<&str> = version.split('.').collect();
+    if parts.len() == 3 && parts.iter().all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit())) {
+        Some(version.to_string())
+    } else {
+        None
+    }
+}
+
+/// Translates a standard-JSON `settings` object into foundry profile entries.
+///
+/// Source files are written under `src_dir`, so paths in `libraries` and remapping
+/// targets are rebased onto that directory.
+fn settings_to_profile(settings: &serde_json::Value, src_dir: &str, profile: &mut toml::Table) {
+    let optimizer = &settings["optimizer"];
+    if let Some(enabled) = optimizer["enabled"].as_bool() {
+        profile.insert("optimizer".to_string(), toml::Value::Boolean(enabled));
+    }
+    if let Some(runs) = optimizer["runs"].as_i64() {
+        profile.insert("optimizer_runs".to_string(), toml::Value::Integer(runs));
+    }
+    if let Some(details) = json_to_toml(&optimizer["details"]) {
+        profile.insert("optimizer_details".to_string(), details);
+    }
+
+    if let Some(evm_version) = settings["evmVersion"].as_str() {
+        profile.insert("evm_version".to_string(), toml::Value::String(evm_version.to_string()));
+    }
+    if let Some(via_ir) = settings["viaIR"].as_bool() {
+        profile.insert("via_ir".to_string(), toml::Value::Boolean(via_ir));
+    }
+
+    let metadata = &settings["metadata"];
+    if let Some(hash) = metadata["bytecodeHash"].as_str() {
+        profile.insert("bytecode_hash".to_string(), toml::Value::String(hash.to_string()));
+    }
+    if let Some(append_cbor) = metadata["appendCBOR"].as_bool() {
+        profile.insert("cbor_metadata".to_string(), toml::Value::Boolean(append_cbor));
+    }
+    if let Some(literal) = metadata["useLiteralContent"].as_bool() {
+        profile.insert("use_literal_content".to_string(), toml::Value::Boolean(literal));
+    }
+
+    if let Some(libraries) = settings["libraries"].as_object() {
+        let mut entries = Vec::new();
+        for (file, names) in libraries {
+            let Some(names) = names.as_object() else { continue };
+            for (name, address) in names {
+                if let Some(address) = address.as_str() {
+                    entries.push(toml::Value::String(format!("{}:{}:{}", rebase(src_dir, file), name, address)));
+                }
+            }
+        }
+        if !entries.is_empty() {
+            profile.insert("libraries".to_string(), toml::Value::Array(entries));
+        }
+    }
+
+    if let Some(remappings) = settings["remappings"].as_array() {
+        let entries: Vec<toml::Value> = remappings
+            .iter()
+            .filter_map(|r| r.as_str())
+            .map(|r| match r.split_once('=') {
+                Some((prefix, target)) => toml::Value::String(format!("{}={}", prefix, rebase(src_dir, target))),
+                None => toml::Value::String(r.to_string()),
+            })
+            .collect();
+        if !entries.is_empty() {
+            profile.insert("remappings".to_string(), toml::Value::Array(entries));
+        }
+    }
+}
+
+/// Translates the verified compiler configuration into `forge build` flags, so an
+/// existing project can be compiled the way the deployment was without editing its
+/// `foundry.toml`. Metadata settings are left out, as they only affect the metadata hash.
+pub fn build_args(src_dir: &str, compiler_version: Option<&str>, settings: Option<&serde_json::Value>) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(version) = compiler_version.and_then(solc_version) {
+        args.extend(["--use".to_string(), version]);
+    }
+    let Some(settings) = settings else {
+        return args;
+    };
+
+    let optimizer = &settings["optimizer"];
+    if optimizer["enabled"].as_bool() == Some(true) {
+        args.push("--optimize".to_string());
+    }
+    if let Some(runs) = optimizer["runs"].as_i64() {
+        args.extend(["--optimizer-runs".to_string(), runs.to_string()]);
+    }
+    if let Some(evm_version) = settings["evmVersion"].as_str() {
+        args.extend(["--evm-version".to_string(), evm_version.to_string()]);
+    }
+    if settings["viaIR"].as_bool() == Some(true) {
+        args.push("--via-ir".to_string());
+    }
+
+    if let Some(libraries) = settings["libraries"].as_object() {
+        for (file, names) in libraries {
+            let Some(names) = names.as_object() else { continue };
+            for (name, address) in names {
+                if let Some(address) = address.as_str() {
+                    args.extend(["--libraries".to_string(), format!("{}:{}:{}", rebase(src_dir, file), name, address)]);
+                }
+            }
+        }
+    }
+
+    args
+}
+
+/// Maps a path from the verified source tree onto its location under `src_dir`.
+fn rebase(src_dir: &str, path: &str) -> String {
+    if path.is_empty() || path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("{}/{}", src_dir, path)
+    }
+}
+
+fn json_to_toml(value: &serde_json::Value) -> Option<toml::Value> {
+    match value {
+        serde_json::Value::Null => None,
+        serde_json::Value::Bool(b) => Some(toml::Value::Boolean(*b)),
+        serde_json::Value::Number(n) => n
+            .as_i64()
+            .map(toml::Value::Integer)
+            .or_else(|| n.as_f64().map(toml::Value::Float)),
+        serde_json::Value::String(s) => Some(toml::Value::String(s.clone())),
+        serde_json::Value::Array(items) => Some(toml::Value::Array(items.iter().filter_map(json_to_toml).collect())),
+        serde_json::Value::Object(map) => Some(toml::Value::Table(
+            map.iter()
+                .filter_map(|(k, v)| json_to_toml(v).map(|v| (k.clone(), v)))
+                .collect(),
+        )),
+    }
+}
+
+/// Project files captured before `forge init --force` runs over an existing directory,
+/// so the user's own versions survive it.
+#[derive(Debug, Default)]
+pub struct PreservedFiles {
+    foundry_toml: Option<String>,
+    gitignore: Option<String>,
+}
+
+impl PreservedFiles {
+    /// Captures the existing `.gitignore`, and `foundry.toml` unless `overwrite_config` is set.
+    pub fn capture(root: &Path, overwrite_config: bool) -> std::io::Result<Self> {
+        let read = |name: &str| match std::fs::read_to_string(root.join(name)) {
+            Ok(contents) => Ok(Some(contents)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        };
+
+        Ok(PreservedFiles {
+            foundry_toml: if overwrite_config { None } else { read("foundry.toml")? },
+            gitignore: read(".gitignore")?,
+        })
+    }
+
+    /// Whether a user `foundry.toml` was captured and must be left as it is.
+    pub fn keeps_config(&self) -> bool {
+        self.foundry_toml.is_some()
+    }
+
+    pub fn restore(&self, root: &Path) -> std::io::Result<()> {
+        if let Some(contents) = &self.foundry_toml {
+            std::fs::write(root.join("foundry.toml"), contents)?;
+            info!("Kept existing foundry.toml");
+        }
+        if let Some(contents) = &self.gitignore {
+            std::fs::write(root.join(".gitignore"), contents)?;
+            info!("Kept existing .gitignore");
+        }
+        Ok(())
+    }
+}
+
+/// The example test and script `forge init` generates alongside its Counter.
+const TEMPLATE_FILES: &[&str] = &["test/Counter.t.sol", "script/Counter.s.sol"];
+
+/// forge's example contract. Versions differ in the pragma and formatting, so it's
+/// compared ignoring those and comments.
+const COUNTER: &str = "contract Counter {
+    uint256 public number;
+
+    function setNumber(uint256 newNumber) public {
+        number = newNumber;
+    }
+
+    function increment() public {
+        number++;
+    }
+}";
+
+fn is_template_counter(contents: &str) -> bool {
+    let code = |text: &str| -> String {
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with("//") && !line.starts_with("pragma "))
+            .flat_map(|line| line.split_whitespace())
+            .collect()
+    };
+    code(contents) == code(COUNTER)
+}
+
+/// Removes forge's Counter example from a freshly initialized project whose sources go
+/// in `src_dir`. Only a `Counter.sol` with forge's example contract in it is removed, so
+/// a template's own Counter is kept, along with the test and script that go with it;
+/// contracts such as `CounterfactualVault.sol` are never touched. forge's `src/` goes too
+/// when it's left empty and the sources go elsewhere.
+pub fn remove_template(root: &Path, src_dir: &str) -> std::io::Result<()> {
+    let moved = format!("{}/Counter.sol", src_dir);
+    let mut kept = false;
+    for file in std::iter::once("src/Counter.sol").chain((src_dir != "src").then_some(moved.as_str())) {
+        let path = root.join(file);
+        match std::fs::read_to_string(&path) {
+            Ok(contents) if is_template_counter(&contents) => {
+                std::fs::remove_file(&path)?;
+                info!("Removing Counter file: {:?}", path);
+            }
+            Ok(_) => {
+                info!("Keeping {:?}, it isn't forge's Counter example", path);
+                kept = true;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    for file in TEMPLATE_FILES.iter().filter(|_| !kept) {
+        let path = root.join(file);
+        match std::fs::remove_file(&path) {
+            Ok(()) => info!("Removing Counter file: {:?}", path),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    if src_dir != "src" && std::fs::read_dir(root.join("src")).is_ok_and(|mut entries| entries.next().is_none()) {
+        std::fs::remove_dir(root.join("src"))?;
+    }
+    Ok(())
+}
+
+/// Adds `remappings` (`prefix=target`) to the project's `remappings.txt`, keeping the
+/// entries already there. A prefix the file already remaps elsewhere is left as it is,
+/// with a warning.
+pub fn add_remappings(root: &Path, remappings: &[String]) -> std::io::Result<()> {
+    let path = root.join("remappings.txt");
+    let mut contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+
+    let existing: Vec<(String, String)> = contents
+        .lines()
+        .filter_map(|line| line.trim().split_once('='))
+        .map(|(prefix, target)| (prefix.to_string(), target.to_string()))
+        .collect();
+    let mut missing = Vec::new();
+    for remapping in remappings {
+        let Some((prefix, target)) = remapping.split_once('=') else { continue };
+        match existing.iter().find(|(existing, _)| existing == prefix) {
+            Some((_, existing)) if existing != target => {
+                warn!("remappings.txt already remaps {} to {}, leaving out {}", prefix, existing, remapping);
+            }
+            Some(_) => {}
+            None => missing.push(remapping.as_str()),
+        }
+    }
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    for remapping in &missing {
+        contents.push_str(remapping);
+        contents.push('\n');
+    }
+    std::fs::write(&path, contents)?;
+    summary::file_written(&path);
+    info!("Added {} remappings to remappings.txt", missing.len());
+
+    Ok(())
+}
+
+/// Appends any of `entries` missing from the project's `.gitignore`, creating it if needed.
+pub fn ensure_gitignore(root: &Path, entries: &[&str]) -> std::io::Result<()> {
+    let path = root.join(".gitignore");
+    let mut contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+
+    let missing: Vec<&str> = entries
+        .iter()
+        .copied()
+        .filter(|entry| !contents.lines().any(|line| line.trim() == *entry))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    for entry in &missing {
+        contents.push_str(entry);
+        contents.push('\n');
+    }
+    std::fs::write(&path, contents)?;
+    summary::file_written(&path);
+    info!("Added {} to .gitignore", missing.join(", "));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn kills_a_hanging_command() {
+        let mut command = tokio::process::Command::new("sleep");
+        command.arg("10");
+        let started = std::time::Instant::now();
+
+        let err = run(&mut command, Duration::from_millis(100)).await.unwrap_err();
+        assert!(err.to_string().starts_with("forge failed: timed out after 0.1s"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn finds_solc_installed_by_svm() {
+        let svm = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(svm.path().join("0.8.19")).unwrap();
+        std::fs::write(svm.path().join("0.8.19/solc-0.8.19"), "").unwrap();
+
+        assert!(svm_has(svm.path(), "0.8.19"));
+        assert!(!svm_has(svm.path(), "0.8.20"));
+    }
+
+    #[test]
+    fn fork_test_points_at_the_deployment() {
+        let test = fork_test(
+            "Token",
+            "../src/contracts/Token.sol",
+            "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed",
+            Some("v0.8.19+commit.7dd6d404"),
+            "ETH_RPC_URL",
+            Some(19_000_000),
+        )
+        .unwrap();
+        assert!(test.contains("pragma solidity ^0.8.19;"));
+        assert!(test.contains("import {Token} from \"../src/contracts/Token.sol\";"));
+        assert!(test.contains("vm.createSelectFork(vm.envString(\"ETH_RPC_URL\"), 19000000);"));
+        assert!(test.contains("target = Token(0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed);"));
+    }
+
+    #[test]
+    fn removes_only_the_forge_template() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let counter = format!("// SPDX-License-Identifier: UNLICENSED\npragma solidity ^0.8.13;\n\n{}\n", COUNTER);
+        let files = [
+            ("src/Counter.sol", counter.as_str()),
+            ("test/Counter.t.sol", "contract CounterTest {}"),
+            ("script/Counter.s.sol", "contract CounterScript {}"),
+            ("src/CounterfactualVault.sol", "contract C {}"),
+            ("test/CounterfactualVault.t.sol", "contract C {}"),
+        ];
+        for (file, contents) in files {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+
+        remove_template(root, "src").unwrap();
+
+        for file in ["src/Counter.sol", "test/Counter.t.sol", "script/Counter.s.sol"] {
+            assert!(!root.join(file).exists(), "{} was kept", file);
+        }
+        assert!(root.join("src/CounterfactualVault.sol").is_file());
+        assert!(root.join("test/CounterfactualVault.t.sol").is_file());
+
+        // A template's own Counter stays, and so do its test and script
+        for (file, contents) in &files[..3] {
+            let contents = if *file == "src/Counter.sol" { "contract Counter {\n    uint256 public count;\n}\n" } else { contents };
+            std::fs::write(root.join(file), contents).unwrap();
+        }
+        remove_template(root, "src").unwrap();
+        for (file, _) in &files[..3] {
+            assert!(root.join(file).is_file(), "{} was removed", file);
+        }
+    }
+
+    #[test]
+    fn reads_and_sets_the_source_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        assert_eq!(configured_src(root), None);
+        std::fs::write(root.join("foundry.toml"), "[profile.default]\nsrc = \"contracts/\"\nout = \"out\"\n").unwrap();
+        assert_eq!(configured_src(root).as_deref(), Some("contracts"));
+
+        set_src(root, "sources").unwrap();
+        assert_eq!(configured_src(root).as_deref(), Some("sources"));
+        assert!(std::fs::read_to_string(root.join("foundry.toml")).unwrap().contains("out = \"out\""));
+
+        // A template whose sources go elsewhere leaves forge's Counter there and an empty src/
+        for (file, contents) in [("src/Counter.sol", COUNTER), ("sources/Counter.sol", COUNTER), ("test/Counter.t.sol", "contract CounterTest {}")] {
+            std::fs::create_dir_all(root.join(file).parent().unwrap()).unwrap();
+            std::fs::write(root.join(file), contents).unwrap();
+        }
+        remove_template(root, "sources").unwrap();
+        assert!(!root.join("src").exists());
+        assert!(!root.join("sources/Counter.sol").exists() && root.join("sources").is_dir());
+    }
+}
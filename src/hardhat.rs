@@ -0,0 +1,196 @@
+use crate::error::TokenFetchError;
+use crate::summary;
+use log::{info, warn};
+use std::path::Path;
+
+/// The Hardhat release the scaffolded project pins.
+pub const HARDHAT_VERSION: &str = "2.22.17";
+/// The toolbox release matching it, which brings ethers, chai and typechain along as peers.
+pub const TOOLBOX_VERSION: &str = "5.0.0";
+
+/// Where the sources are written, keeping the paths they were verified with.
+pub const SOURCES_DIR: &str = "contracts";
+
+/// Entries every scaffolded project keeps out of git.
+pub const GITIGNORE: &[&str] = &["node_modules/", "artifacts/", "cache/", "typechain-types/"];
+
+/// Standard-JSON settings Hardhat passes on to solc as they are. Remappings are left out,
+/// Hardhat resolves imports itself, as is anything solc wouldn't accept in `settings`.
+const SETTINGS: &[&str] = &["evmVersion", "libraries", "metadata", "optimizer", "viaIR"];
+
+/// A contract to scaffold the project around.
+pub struct Project<'a> {
+    pub name: &'a str,
+    pub address: &'a str,
+    pub chain: &'a str,
+    /// The solc release to compile with, e.g. `0.8.19`
+    pub solc: &'a str,
+    pub settings: Option<&'a serde_json::Value>,
+    /// Whether the optimizer was enabled and with how many runs, for verifications without
+    /// standard-JSON settings
+    pub optimizer: Option<(bool, Option<u64>)>,
+}
+
+/// The solc `settings` for `hardhat.config.ts`.
+fn settings(project: &Project) -> serde_json::Value {
+    let mut settings: serde_json::Map<String, serde_json::Value> = project
+        .settings
+        .and_then(|settings| settings.as_object())
+        .into_iter()
+        .flatten()
+        .filter(|(key, _)| SETTINGS.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    if let (false, Some((enabled, runs))) = (settings.contains_key("optimizer"), project.optimizer) {
+        settings.insert("optimizer".to_string(), serde_json::json!({ "enabled": enabled, "runs": runs.unwrap_or(200) }));
+    }
+    if project.settings.is_some_and(|settings| settings["remappings"].as_array().is_some_and(|r| !r.is_empty())) {
+        warn!("Hardhat doesn't apply the verified remappings, imports relying on them won't resolve");
+    }
+    serde_json::Value::Object(settings)
+}
+
+/// Renders `hardhat.config.ts`. Hardhat resolves an import such as `@openzeppelin/...` to
+/// a local file when its first directory exists in the project root, so the root is
+/// `contracts/`, where the sources keep the paths they were verified with, and the build
+/// output goes back up next to the config.
+pub fn config(project: &Project) -> Result<String, TokenFetchError> {
+    let settings = serde_json::to_string_pretty(&settings(project))?.replace('\n', "\n    ");
+    Ok(format!(
+        r#"import {{ HardhatUserConfig }} from "hardhat/config";
+import "@nomicfoundation/hardhat-toolbox";
+
+// Generated by TokenFetch with the verified compiler settings of {name} ({address}) on {chain}
+const config: HardhatUserConfig = {{
+  solidity: {{
+    version: "{solc}",
+    settings: {settings},
+  }},
+  paths: {{
+    root: "./{sources}",
+    sources: ".",
+    artifacts: "../artifacts",
+    cache: "../cache",
+    tests: "../test",
+  }},
+  typechain: {{
+    outDir: "../typechain-types",
+  }},
+}};
+
+export default config;
+"#,
+        name = project.name,
+        address = project.address,
+        chain = project.chain,
+        solc = project.solc,
+        sources = SOURCES_DIR,
+    ))
+}
+
+/// Renders `package.json`, with Hardhat and the toolbox pinned.
+pub fn package_json(project: &Project) -> Result<String, TokenFetchError> {
+    let package = serde_json::json!({
+        "name": package_name(project.name),
+        "version": "1.0.0",
+        "private": true,
+        "description": format!("Verified source of {} ({}) on {}, fetched with TokenFetch", project.name, project.address, project.chain),
+        "scripts": { "compile": "hardhat compile" },
+        "devDependencies": {
+            "@nomicfoundation/hardhat-toolbox": TOOLBOX_VERSION,
+            "hardhat": HARDHAT_VERSION,
+        },
+    });
+    Ok(serde_json::to_string_pretty(&package)? + "\n")
+}
+
+/// A valid npm package name for the contract: lowercase, dashes between words.
+fn package_name(name: &str) -> String {
+    let mut package = String::new();
+    let mut previous = ' ';
+    for c in name.chars() {
+        // Splits `MyToken` but not `USDT`
+        if c.is_ascii_uppercase() && (previous.is_ascii_lowercase() || previous.is_ascii_digit()) {
+            package.push('-');
+        }
+        previous = c;
+        match c {
+            c if c.is_ascii_alphanumeric() => package.push(c.to_ascii_lowercase()),
+            _ if !package.is_empty() && !package.ends_with('-') => package.push('-'),
+            _ => {}
+        }
+    }
+    let package = package.trim_matches('-');
+    if package.is_empty() { "contract".to_string() } else { package.to_string() }
+}
+
+/// The TypeScript settings Hardhat's sample projects use.
+const TSCONFIG: &str = r#"{
+  "compilerOptions": {
+    "target": "es2020",
+    "module": "commonjs",
+    "esModuleInterop": true,
+    "forceConsistentCasingInFileNames": true,
+    "strict": true,
+    "skipLibCheck": true,
+    "resolveJsonModule": true
+  }
+}
+"#;
+
+fn write(path: &Path, contents: &str) -> std::io::Result<()> {
+    if std::fs::read_to_string(path).is_ok_and(|existing| existing != contents) {
+        warn!("Overwriting existing file: {:?}", path);
+    }
+    std::fs::write(path, contents)?;
+    summary::file_written(path);
+    Ok(())
+}
+
+/// Writes the Hardhat config, `package.json` and `tsconfig.json` at `root`.
+pub fn scaffold(root: &Path, project: &Project) -> Result<(), TokenFetchError> {
+    write(&root.join("hardhat.config.ts"), &config(project)?)?;
+    write(&root.join("package.json"), &package_json(project)?)?;
+    write(&root.join("tsconfig.json"), TSCONFIG)?;
+    info!("Wrote a Hardhat {} project for solc {}, build it with npm install && npx hardhat compile", HARDHAT_VERSION, project.solc);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaffolds_the_verified_settings() {
+        let settings = serde_json::json!({
+            "optimizer": { "enabled": true, "runs": 999 },
+            "evmVersion": "paris",
+            "remappings": ["@oz/=lib/openzeppelin/"],
+            "outputSelection": { "*": { "*": ["abi"] } },
+            "compilationTarget": { "contracts/Token.sol": "Token" }
+        });
+        let project = Project {
+            name: "MyToken",
+            address: "0x1111111111111111111111111111111111111111",
+            chain: "eth",
+            solc: "0.8.20",
+            settings: Some(&settings),
+            optimizer: None,
+        };
+        let config = config(&project).unwrap();
+        assert!(config.contains("version: \"0.8.20\""), "{}", config);
+        assert!(config.contains("\"evmVersion\": \"paris\""), "{}", config);
+        assert!(config.contains("\"runs\": 999"), "{}", config);
+        assert!(config.contains("root: \"./contracts\""), "{}", config);
+        assert!(!config.contains("remappings") && !config.contains("outputSelection") && !config.contains("compilationTarget"));
+
+        let package: serde_json::Value = serde_json::from_str(&package_json(&project).unwrap()).unwrap();
+        assert_eq!(package["name"], "my-token");
+        assert_eq!(package["devDependencies"]["hardhat"], HARDHAT_VERSION);
+
+        // Single-file verifications only report the optimizer
+        let single = Project { settings: None, optimizer: Some((true, Some(200))), ..project };
+        assert_eq!(super::settings(&single), serde_json::json!({ "optimizer": { "enabled": true, "runs": 200 } }));
+        assert_eq!(package_name("USDT_Token v2"), "usdt-token-v2");
+    }
+}
@@ -0,0 +1,193 @@
+use log::warn;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// `content` with its comments blanked out, so commented-out imports and declarations
+/// aren't picked up. String literals are kept as they are.
+fn strip_comments(content: &str) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut quote = None;
+    while let Some(c) = chars.next() {
+        match (quote, c, chars.peek()) {
+            (Some(q), _, _) => {
+                out.push(c);
+                if c == '\\' {
+                    out.extend(chars.next());
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            (None, '"' | '\'', _) => {
+                quote = Some(c);
+                out.push(c);
+            }
+            (None, '/', Some('/')) => {
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    chars.next();
+                }
+            }
+            (None, '/', Some('*')) => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+                out.push(' ');
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// The paths imported by a Solidity source, in any of the `import` forms.
+pub fn imports(content: &str) -> Vec<String> {
+    let content = strip_comments(content);
+    content
+        .split(';')
+        .map(str::trim)
+        .filter(|statement| statement.starts_with("import") && statement[6..].starts_with(|c: char| c.is_whitespace() || c == '"' || c == '\'' || c == '{' || c == '*'))
+        .filter_map(|statement| {
+            // The path is the only string literal in an import statement
+            let start = statement.find(['"', '\''])?;
+            let quote = statement[start..].chars().next()?;
+            let end = statement[start + 1..].find(quote)?;
+            Some(statement[start + 1..start + 1 + end].to_string())
+        })
+        .collect()
+}
+
+/// Joins a relative import onto the directory of the importing file, resolving `.` and
+/// `..` the way solc does.
+fn join(importer: &str, path: &str) -> String {
+    let mut parts: Vec<&str> = importer.split('/').collect();
+    parts.pop();
+    for part in path.split('/') {
+        match part {
+            "." | "" => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// The source that `path`, imported from `importer`, refers to: relative to the importer
+/// for `./` and `../` imports, otherwise by its path, with `remappings` applied.
+pub fn resolve(importer: &str, path: &str, sources: &Map<String, Value>, remappings: &[(String, String)]) -> Option<String> {
+    if path.starts_with("./") || path.starts_with("../") {
+        let joined = join(importer, path);
+        return sources.contains_key(&joined).then_some(joined);
+    }
+    if sources.contains_key(path) {
+        return Some(path.to_string());
+    }
+    // The longest matching prefix wins, as with solc
+    remappings
+        .iter()
+        .filter(|(prefix, _)| path.starts_with(prefix.as_str()))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(prefix, target)| format!("{}{}", target, &path[prefix.len()..]))
+        .filter(|remapped| sources.contains_key(remapped))
+}
+
+/// Reads standard-JSON `remappings` such as `@oz/=lib/openzeppelin/`, dropping any
+/// `context:` they are limited to.
+pub fn remappings(settings: Option<&Value>) -> Vec<(String, String)> {
+    settings
+        .and_then(|settings| settings["remappings"].as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .filter_map(|remapping| {
+            let remapping = remapping.split_once(':').map_or(remapping, |(_, rest)| rest);
+            let (prefix, target) = remapping.split_once('=')?;
+            Some((prefix.to_string(), target.to_string()))
+        })
+        .collect()
+}
+
+/// The contracts and libraries declared in `sources`, with the file declaring each.
+pub fn declared_contracts(sources: &Map<String, Value>) -> BTreeMap<String, String> {
+    let mut declared = BTreeMap::new();
+    for (file, source) in sources {
+        let content = strip_comments(source["content"].as_str().unwrap_or_default());
+        let tokens: Vec<&str> = content.split(|c: char| c.is_whitespace() || c == '{').filter(|t| !t.is_empty()).collect();
+        for pair in tokens.windows(2) {
+            if matches!(pair[0], "contract" | "library") && pair[1].chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$') {
+                declared.entry(pair[1].to_string()).or_insert_with(|| file.clone());
+            }
+        }
+    }
+    declared
+}
+
+/// `file` and every source it imports, directly or through other imports. Imports that
+/// don't resolve to one of `sources` are warned about, as the subset won't build.
+pub fn closure(file: &str, sources: &Map<String, Value>, remappings: &[(String, String)]) -> BTreeSet<String> {
+    let mut included = BTreeSet::new();
+    let mut pending = vec![file.to_string()];
+    while let Some(file) = pending.pop() {
+        if !included.insert(file.clone()) {
+            continue;
+        }
+        let content = sources[&file]["content"].as_str().unwrap_or_default();
+        for import in imports(content) {
+            match resolve(&file, &import, sources, remappings) {
+                Some(imported) => pending.push(imported),
+                None => warn!("Couldn't find {}, imported by {}, among the verified sources", import, file),
+            }
+        }
+    }
+    included
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources(files: &[(&str, &str)]) -> Map<String, Value> {
+        files.iter().map(|(path, content)| (path.to_string(), serde_json::json!({ "content": content }))).collect()
+    }
+
+    #[test]
+    fn reads_every_import_form() {
+        let content = r#"
+            pragma solidity ^0.8.0;
+            import "./A.sol";
+            import {B, C as D} from '../lib/B.sol';
+            import * as E from "@oz/E.sol";
+            import "F.sol" as F;
+            // import "./Commented.sol";
+            /* import "./Blocked.sol"; */
+            contract Importer { string s = "import \"nope.sol\";"; }
+        "#;
+        assert_eq!(imports(content), ["./A.sol", "../lib/B.sol", "@oz/E.sol", "F.sol"]);
+    }
+
+    #[test]
+    fn selects_a_contract_and_its_imports() {
+        let sources = sources(&[
+            ("src/Vault.sol", "import \"./utils/Math.sol\";\nimport \"@oz/token/ERC20.sol\";\ncontract Vault {}"),
+            ("src/utils/Math.sol", "import \"../Errors.sol\";\nlibrary Math {}"),
+            ("src/Errors.sol", "// contract Fake {}\nerror Nope();"),
+            ("lib/openzeppelin/token/ERC20.sol", "abstract contract ERC20 {}"),
+            ("src/Router.sol", "import \"./Vault.sol\";\ncontract Router {}"),
+        ]);
+        let remappings = remappings(Some(&serde_json::json!({ "remappings": ["ctx:@oz/=lib/openzeppelin/"] })));
+        assert_eq!(remappings, [("@oz/".to_string(), "lib/openzeppelin/".to_string())]);
+
+        let declared = declared_contracts(&sources);
+        assert_eq!(declared.keys().collect::<Vec<_>>(), ["ERC20", "Math", "Router", "Vault"]);
+        assert_eq!(
+            closure(&declared["Vault"], &sources, &remappings).into_iter().collect::<Vec<_>>(),
+            ["lib/openzeppelin/token/ERC20.sol", "src/Errors.sol", "src/Vault.sol", "src/utils/Math.sol"]
+        );
+    }
+}
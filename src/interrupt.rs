@@ -0,0 +1,78 @@
+use log::{info, warn};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The project directory this run created, which is a partial clone until the run ends.
+/// Process-wide, since it's only needed when Ctrl-C cancels whatever was running.
+static CREATED: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// The outermost of `path` and its ancestors that doesn't exist yet, which is what
+/// creating `path` adds to the file system.
+fn first_missing(path: &Path) -> Option<PathBuf> {
+    let mut missing = None;
+    for ancestor in path.ancestors().filter(|ancestor| !ancestor.as_os_str().is_empty()) {
+        if ancestor.exists() {
+            break;
+        }
+        missing = Some(ancestor.to_path_buf());
+    }
+    missing
+}
+
+/// Creates the project directory `path` with its parents, remembering the outermost one
+/// created so an interrupted run can remove the lot.
+pub fn create_dir_all(path: &Path) -> std::io::Result<()> {
+    let missing = first_missing(path);
+    std::fs::create_dir_all(path)?;
+    let mut created = CREATED.lock().unwrap();
+    if created.is_none() {
+        *created = missing;
+    }
+    Ok(())
+}
+
+/// After an interrupt, removes the directory this run created when `remove` is set, or
+/// else says where it was left. A directory that existed before the run is never touched.
+pub fn clean_up(remove: bool) {
+    if let Some(dir) = CREATED.lock().unwrap().take() {
+        remove_partial(&dir, remove);
+    }
+}
+
+fn remove_partial(dir: &Path, remove: bool) {
+    if !remove {
+        warn!("Left the partial clone at {}, pass --clean-on-interrupt to remove it", dir.display());
+        return;
+    }
+    // A forge process that was just killed may still be writing into it for a moment
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match std::fs::remove_dir_all(dir) {
+            Ok(()) => return info!("Removed the partial clone at {}", dir.display()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+            Err(_) if attempts < 3 => std::thread::sleep(std::time::Duration::from_millis(100)),
+            Err(e) => return warn!("Couldn't remove the partial clone at {}: {}", dir.display(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes_only_what_the_run_created() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("nested/new/project");
+        assert_eq!(first_missing(&project), Some(dir.path().join("nested")));
+        assert_eq!(first_missing(dir.path()), None);
+
+        std::fs::create_dir_all(&project).unwrap();
+        remove_partial(&project, false);
+        assert!(project.exists());
+        remove_partial(&dir.path().join("nested"), true);
+        assert!(!dir.path().join("nested").exists());
+        assert!(dir.path().exists());
+    }
+}
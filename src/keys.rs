@@ -0,0 +1,151 @@
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// The explorer API keys available for one chain, handed out round-robin so requests
+/// are spread across them.
+///
+/// A key the explorer throttles is benched for a cooldown, and one it rejects is
+/// dropped for the rest of the run.
+pub struct KeyPool {
+    keys: Vec<String>,
+    state: Mutex<PoolState>,
+}
+
+struct PoolState {
+    cursor: usize,
+    benched_until: Vec<Option<Instant>>,
+    rejected: Vec<bool>,
+    served: Vec<usize>,
+}
+
+/// What [`KeyPool::pick`] found.
+pub enum Pick {
+    /// Use the key at this position next
+    Key(usize, String),
+    /// Every usable key is cooling down; the first is available again after this long
+    Wait(Duration),
+    /// The explorer rejected every key
+    Exhausted,
+}
+
+impl KeyPool {
+    pub fn new(keys: Vec<String>) -> Self {
+        let count = keys.len();
+        KeyPool {
+            keys,
+            state: Mutex::new(PoolState {
+                cursor: 0,
+                benched_until: vec![None; count],
+                rejected: vec![false; count],
+                served: vec![0; count],
+            }),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Returns the next usable key, moving on to the following one for the next call.
+    pub fn pick(&self) -> Pick {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let count = self.keys.len();
+
+        for offset in 0..count {
+            let idx = (state.cursor + offset) % count;
+            if state.rejected[idx] || state.benched_until[idx].is_some_and(|until| until > now) {
+                continue;
+            }
+            state.cursor = idx + 1;
+            state.served[idx] += 1;
+            return Pick::Key(idx, self.keys[idx].clone());
+        }
+
+        let ready_at = (0..count)
+            .filter(|&idx| !state.rejected[idx])
+            .filter_map(|idx| state.benched_until[idx])
+            .min();
+        match ready_at {
+            Some(until) => Pick::Wait(until - now),
+            None => Pick::Exhausted,
+        }
+    }
+
+    /// Keeps the key at `idx` out of rotation for `cooldown`.
+    pub fn bench(&self, idx: usize, cooldown: Duration) {
+        self.state.lock().unwrap().benched_until[idx] = Some(Instant::now() + cooldown);
+    }
+
+    /// Drops the key at `idx` for the rest of the run.
+    pub fn reject(&self, idx: usize) {
+        self.state.lock().unwrap().rejected[idx] = true;
+    }
+
+    /// How many requests each key has been used for, in the order the keys were given.
+    pub fn served(&self) -> Vec<usize> {
+        self.state.lock().unwrap().served.clone()
+    }
+}
+
+/// Splits a key setting into its individual keys. Keys are separated by commas or
+/// whitespace, so a key file may also hold one key per line.
+pub fn parse_keys(value: &str) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::new();
+    for key in value.split(|c: char| c == ',' || c.is_whitespace()) {
+        if !key.is_empty() && !keys.iter().any(|k| k == key) {
+            keys.push(key.to_string());
+        }
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(keys: &[&str]) -> KeyPool {
+        KeyPool::new(keys.iter().map(|k| k.to_string()).collect())
+    }
+
+    fn picked(pool: &KeyPool) -> Option<String> {
+        match pool.pick() {
+            Pick::Key(_, key) => Some(key),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn rotates_round_robin() {
+        let pool = pool(&["a", "b", "c"]);
+        let picks: Vec<_> = (0..4).filter_map(|_| picked(&pool)).collect();
+        assert_eq!(picks, ["a", "b", "c", "a"]);
+        assert_eq!(pool.served(), [2, 1, 1]);
+    }
+
+    #[test]
+    fn skips_benched_and_rejected_keys() {
+        let pool = pool(&["a", "b", "c"]);
+        pool.bench(0, Duration::from_secs(60));
+        pool.reject(1);
+        assert_eq!(picked(&pool).as_deref(), Some("c"));
+        assert_eq!(picked(&pool).as_deref(), Some("c"));
+
+        pool.bench(2, Duration::from_secs(30));
+        assert!(matches!(pool.pick(), Pick::Wait(wait) if wait <= Duration::from_secs(30)));
+
+        pool.reject(0);
+        pool.reject(2);
+        assert!(matches!(pool.pick(), Pick::Exhausted));
+    }
+
+    #[test]
+    fn parses_key_lists() {
+        assert_eq!(parse_keys(" k1, k2,,k1\nk3 "), ["k1", "k2", "k3"]);
+    }
+}
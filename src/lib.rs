@@ -0,0 +1,55 @@
+//! Fetches the verified source of smart contracts from Etherscan-compatible explorers
+//! and lays it out as Foundry or Hardhat projects. The `TokenFetch` binary is a command
+//! line front end over this crate.
+//!
+//! To embed it, look the chain up with [`chain::chain_config`], or in a [`ChainRegistry`]
+//! holding chains of your own, fetch with
+//! [`fetch_contract_source`] through a [`Fetcher`], which retries as the CLI does, and
+//! write the files out with [`ContractSource::write_sources`]. Every fallible call
+//! returns a [`TokenFetchError`].
+//! The crate is 0.x: the modules behind the CLI are public too, but may change between
+//! releases.
+
+pub mod address;
+pub mod archive;
+pub mod cache;
+pub mod chain;
+pub mod compliance;
+pub mod config;
+pub mod credentials;
+pub mod deps;
+pub mod diff;
+pub mod ens;
+pub mod envfile;
+pub mod error;
+pub mod explorer;
+pub mod fetch;
+pub mod flatten;
+pub mod foundry;
+pub mod hardhat;
+pub mod http;
+pub mod imports;
+pub mod interrupt;
+pub mod keys;
+pub mod license;
+pub mod lint;
+pub mod manifest;
+pub mod progress;
+pub mod readme;
+pub mod refresh;
+pub mod response;
+pub mod rpc;
+pub mod selectors;
+pub mod source;
+pub mod standard;
+pub mod summary;
+pub mod throttle;
+pub mod token;
+pub mod verify;
+
+pub use chain::{ChainConfig, ChainRegistry};
+pub use error::TokenFetchError;
+pub use response::SourceCodeResult;
+pub use explorer::{fetch_contract_source, fetch_raw};
+pub use fetch::{FetchOptions, Fetcher};
+pub use source::{ContractSource, WriteOptions, WrittenFiles};
@@ -0,0 +1,131 @@
+use crate::error::TokenFetchError;
+use log::{info, warn};
+use std::path::Path;
+
+/// Bundled license texts, by SPDX identifier. The permissive ones keep the SPDX template's
+/// `<year> <copyright holders>`, as the explorer doesn't say who holds the copyright.
+const TEXTS: &[(&str, &str)] = &[
+    ("Apache-2.0", include_str!("licenses/Apache-2.0.txt")),
+    ("BSD-2-Clause", include_str!("licenses/BSD-2-Clause.txt")),
+    ("BSD-3-Clause", include_str!("licenses/BSD-3-Clause.txt")),
+    ("GPL-2.0", include_str!("licenses/GPL-2.0.txt")),
+    ("GPL-3.0", include_str!("licenses/GPL-3.0.txt")),
+    ("LGPL-2.1", include_str!("licenses/LGPL-2.1.txt")),
+    ("LGPL-3.0", include_str!("licenses/LGPL-3.0.txt")),
+    ("MIT", include_str!("licenses/MIT.txt")),
+    ("MPL-2.0", include_str!("licenses/MPL-2.0.txt")),
+    ("Unlicense", include_str!("licenses/Unlicense.txt")),
+];
+
+/// Licenses the explorer offers whose text isn't bundled, so their LICENSE only names them.
+const UNBUNDLED: &[&str] = &["AGPL-3.0", "BUSL-1.1", "OSL-3.0"];
+
+/// Maps an explorer `LicenseType` onto its SPDX identifier.
+pub fn spdx_identifier(license_type: &str) -> Option<&str> {
+    let spdx = match license_type {
+        "None" => "UNLICENSED",
+        "GNU GPLv2" => "GPL-2.0",
+        "GNU GPLv3" => "GPL-3.0",
+        "GNU LGPLv2.1" => "LGPL-2.1",
+        "GNU LGPLv3" => "LGPL-3.0",
+        "GNU AGPLv3" => "AGPL-3.0",
+        "BSL 1.1" => "BUSL-1.1",
+        // Most other types, such as MIT or Apache-2.0, already are SPDX identifiers
+        other if !other.is_empty() && !other.contains(char::is_whitespace) => other,
+        _ => return None,
+    };
+    Some(spdx)
+}
+
+/// The contents of the LICENSE file for sources verified under `spdx`, if it's a license
+/// the explorer offers.
+fn contents(spdx: &str) -> Option<String> {
+    if let Some((_, text)) = TEXTS.iter().find(|(id, _)| *id == spdx) {
+        return Some(text.to_string());
+    }
+    UNBUNDLED.contains(&spdx).then(|| {
+        format!(
+            "SPDX-License-Identifier: {}\n\nThese sources were verified under {}, whose full text is at\nhttps://spdx.org/licenses/{}.html\n",
+            spdx, spdx, spdx
+        )
+    })
+}
+
+/// Writes `LICENSE` into `dir` for the contract at `address`, verified under the explorer's
+/// `license_type`, and returns its SPDX identifier. A contract without a license, or with
+/// one that isn't known, gets a warning and no file, but doesn't fail the clone. A LICENSE
+/// that's already there with other text is the project's own and is left alone.
+pub fn write(dir: &Path, license_type: Option<&str>, address: &str) -> Result<Option<String>, TokenFetchError> {
+    let Some(license_type) = license_type else {
+        warn!("The explorer reports no license for {}, so no LICENSE file was written", address);
+        return Ok(None);
+    };
+    let spdx = spdx_identifier(license_type).map(str::to_string);
+    let contents = match spdx.as_deref() {
+        Some("UNLICENSED") => {
+            warn!("{} was verified with no license (\"{}\"), so no LICENSE file was written", address, license_type);
+            return Ok(spdx);
+        }
+        Some(spdx) => contents(spdx),
+        None => None,
+    };
+    let Some(contents) = contents else {
+        warn!("Unknown license type \"{}\" for {}, so no LICENSE file was written", license_type, address);
+        return Ok(spdx);
+    };
+    let spdx = spdx.expect("only known licenses have contents");
+    if UNBUNDLED.contains(&spdx.as_str()) {
+        warn!("The text of {} isn't bundled, so the LICENSE of {} only names it", spdx, address);
+    }
+
+    let path = dir.join("LICENSE");
+    match std::fs::read_to_string(&path) {
+        Ok(existing) if existing == contents => {
+            crate::manifest::record(&path);
+            return Ok(Some(spdx));
+        }
+        Ok(_) => {
+            warn!("Keeping the existing {:?}, {} is licensed under {}", path, address, spdx);
+            return Ok(Some(spdx));
+        }
+        Err(_) => {}
+    }
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(&path, contents)?;
+    crate::summary::file_written(&path);
+    info!("Wrote the {} license to {:?}", spdx, path);
+    Ok(Some(spdx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_the_text_of_known_licenses_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let address = "0x1111111111111111111111111111111111111111";
+
+        assert_eq!(write(dir.path(), Some("GNU GPLv3"), address).unwrap().as_deref(), Some("GPL-3.0"));
+        let text = std::fs::read_to_string(dir.path().join("LICENSE")).unwrap();
+        assert!(text.contains("GNU GENERAL PUBLIC LICENSE") && text.contains("Version 3, 29 June 2007"));
+
+        let unbundled = tempfile::tempdir().unwrap();
+        assert_eq!(write(unbundled.path(), Some("BSL 1.1"), address).unwrap().as_deref(), Some("BUSL-1.1"));
+        let text = std::fs::read_to_string(unbundled.path().join("LICENSE")).unwrap();
+        assert!(text.starts_with("SPDX-License-Identifier: BUSL-1.1\n"), "{}", text);
+
+        // Another license already there is the project's own, and stays
+        assert_eq!(write(dir.path(), Some("MIT"), address).unwrap().as_deref(), Some("MIT"));
+        assert!(std::fs::read_to_string(dir.path().join("LICENSE")).unwrap().contains("GNU GENERAL PUBLIC LICENSE"));
+        std::fs::write(dir.path().join("LICENSE"), "All rights reserved").unwrap();
+        assert_eq!(write(dir.path(), Some("MIT"), address).unwrap().as_deref(), Some("MIT"));
+        assert_eq!(std::fs::read_to_string(dir.path().join("LICENSE")).unwrap(), "All rights reserved");
+
+        for (license_type, spdx) in [(Some("None"), Some("UNLICENSED")), (Some("Custom License"), None), (None, None)] {
+            let dir = tempfile::tempdir().unwrap();
+            assert_eq!(write(dir.path(), license_type, address).unwrap().as_deref(), spdx);
+            assert!(!dir.path().join("LICENSE").exists());
+        }
+    }
+}
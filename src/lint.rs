@@ -0,0 +1,238 @@
+use crate::error::TokenFetchError;
+use log::{info, warn};
+use semver::{Version, VersionReq};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The last patch release of each solc minor version, every patch before it released too.
+const SOLC_RELEASES: &[(u64, u64)] = &[(4, 26), (5, 17), (6, 12), (7, 6), (8, 30)];
+
+/// How far past the newest release in [`SOLC_RELEASES`] to look for a version the pragmas
+/// allow, for sources written for a solc released after this list.
+const UNLISTED_PATCHES: u64 = 100;
+
+/// What `--check` found in the Solidity files of a clone.
+#[derive(Debug, Default)]
+struct Scan {
+    files: usize,
+    missing_spdx: Vec<PathBuf>,
+    /// Each distinct `pragma solidity` range, with the files declaring it
+    pragmas: BTreeMap<String, Vec<PathBuf>>,
+}
+
+fn scan(files: &[PathBuf]) -> std::io::Result<Scan> {
+    let mut scan = Scan::default();
+    for path in files.iter().filter(|path| path.extension().is_some_and(|ext| ext == "sol")) {
+        let content = std::fs::read_to_string(path)?;
+        scan.files += 1;
+        if !content.contains("SPDX-License-Identifier") {
+            scan.missing_spdx.push(path.clone());
+        }
+        for range in pragma_ranges(&content) {
+            let declared_in = scan.pragmas.entry(range).or_default();
+            if !declared_in.contains(path) {
+                declared_in.push(path.clone());
+            }
+        }
+    }
+    Ok(scan)
+}
+
+/// The version ranges of every `pragma solidity` line in `content`, e.g. `^0.8.0`.
+fn pragma_ranges(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("pragma solidity"))
+        .filter_map(|rest| rest.split(';').next())
+        .map(|range| range.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|range| !range.is_empty())
+        .collect()
+}
+
+/// Whether `version` is in the pragma `range`, or `None` if the range can't be read.
+///
+/// Pragmas use npm-style ranges: comparators separated by spaces, alternatives by `||`,
+/// and a bare version meaning exactly that version.
+fn allows(range: &str, version: &Version) -> Option<bool> {
+    let mut allowed = false;
+    for alternative in range.split("||") {
+        let mut comparators: Vec<String> = Vec::new();
+        let mut operator = String::new();
+        for token in alternative.split_whitespace() {
+            // `>= 0.6.0` is the same as `>=0.6.0`
+            if token.chars().all(|c| "<>=^~".contains(c)) {
+                operator.push_str(token);
+                continue;
+            }
+            let token = format!("{}{}", std::mem::take(&mut operator), token);
+            match token.starts_with(|c: char| c.is_ascii_digit()) {
+                true => comparators.push(format!("={}", token)),
+                false => comparators.push(token),
+            }
+        }
+        if comparators.is_empty() || !operator.is_empty() {
+            return None;
+        }
+        allowed |= VersionReq::parse(&comparators.join(", ")).ok()?.matches(version);
+    }
+    Some(allowed)
+}
+
+/// The newest stable solc release that every `pragma solidity` in `sources` allows, for
+/// a contract whose reported compiler version isn't a release. `None` when the sources
+/// declare no range that can be read.
+pub fn solc_from_pragmas(sources: &serde_json::Map<String, serde_json::Value>) -> Result<Option<Version>, TokenFetchError> {
+    let mut pragmas: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for (path, source) in sources.iter().filter(|(path, _)| path.ends_with(".sol")) {
+        for range in pragma_ranges(source["content"].as_str().unwrap_or_default()) {
+            let declared_in = pragmas.entry(range).or_default();
+            if !declared_in.contains(&path.as_str()) {
+                declared_in.push(path);
+            }
+        }
+    }
+    let check = Version::new(0, 8, 0);
+    pragmas.retain(|range, _| {
+        let readable = allows(range, &check).is_some();
+        if !readable {
+            warn!("Couldn't read the version range in pragma solidity {}, leaving it out", range);
+        }
+        readable
+    });
+    if pragmas.is_empty() {
+        return Ok(None);
+    }
+
+    let newest = SOLC_RELEASES
+        .iter()
+        .rev()
+        .flat_map(|&(minor, last)| (0..=last).rev().map(move |patch| Version::new(0, minor, patch)))
+        .find(|version| pragmas.keys().all(|range| allows(range, version) == Some(true)));
+    // Releases newer than the list only come into it when no listed one will do, and then
+    // the oldest the pragmas allow is the likeliest to exist
+    let newest = newest.or_else(|| {
+        let &(minor, last) = SOLC_RELEASES.last()?;
+        let mut unlisted = (last + 1..=last + UNLISTED_PATCHES)
+            .map(|patch| Version::new(0, minor, patch))
+            .chain((0..UNLISTED_PATCHES).map(|patch| Version::new(0, minor + 1, patch)));
+        let version = unlisted.find(|version| pragmas.keys().all(|range| allows(range, version) == Some(true)))?;
+        warn!("solc {} is newer than the releases this version of tokenfetch knows of, pinning it as the pragmas ask", version);
+        Some(version)
+    });
+    match newest {
+        Some(version) => Ok(Some(version)),
+        None => {
+            let ranges: Vec<String> = pragmas.iter().map(|(range, files)| format!("{} ({})", range, files.join(", "))).collect();
+            Err(TokenFetchError::Other(format!(
+                "No solc release satisfies every pragma solidity in the sources: {}. Pass --contract-name to clone only the contract you need, whose files may agree on one",
+                ranges.join(", ")
+            )))
+        }
+    }
+}
+
+fn list(paths: &[PathBuf], root: &Path) -> String {
+    paths
+        .iter()
+        .map(|path| path.strip_prefix(root).unwrap_or(path).display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn files_noun(count: usize) -> &'static str {
+    if count == 1 { "file" } else { "files" }
+}
+
+/// Reports Solidity files among `files` without an SPDX license identifier, and the
+/// `pragma solidity` ranges they declare, warning about each range that rules out the
+/// pinned `solc` version. Paths are shown relative to `root`.
+pub fn check_sources(files: &[PathBuf], root: &Path, solc: Option<&str>) -> std::io::Result<()> {
+    let scan = scan(files)?;
+    info!("Checked {} Solidity {}", scan.files, files_noun(scan.files));
+    if !scan.missing_spdx.is_empty() {
+        warn!(
+            "No SPDX license identifier in {} {}: {}",
+            scan.missing_spdx.len(),
+            files_noun(scan.missing_spdx.len()),
+            list(&scan.missing_spdx, root)
+        );
+    }
+
+    let solc = solc.and_then(|version| Version::parse(version).ok());
+    for (range, declared_in) in &scan.pragmas {
+        info!("pragma solidity {} in {} {}", range, declared_in.len(), files_noun(declared_in.len()));
+        match (&solc, solc.as_ref().map(|version| allows(range, version))) {
+            (Some(version), Some(Some(false))) => warn!(
+                "pragma solidity {} doesn't allow the pinned solc {}: {}",
+                range,
+                version,
+                list(declared_in, root)
+            ),
+            (Some(_), Some(None)) => warn!("Couldn't read the version range in pragma solidity {}", range),
+            _ => {}
+        }
+    }
+    if scan.pragmas.len() > 1 && solc.is_none() {
+        warn!("Found {} different pragma solidity ranges and no pinned solc version to check them against", scan.pragmas.len());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_pragma_ranges() {
+        let version = Version::new(0, 8, 19);
+        assert_eq!(allows("^0.8.0", &version), Some(true));
+        assert_eq!(allows(">=0.6.0 <0.8.0", &version), Some(false));
+        assert_eq!(allows(">= 0.4.22 < 0.9.0", &version), Some(true));
+        assert_eq!(allows("0.8.20", &version), Some(false));
+        assert_eq!(allows("^0.7.0 || ^0.8.0", &version), Some(true));
+        assert_eq!(allows(">=", &version), None);
+        assert_eq!(pragma_ranges("// SPDX-License-Identifier: MIT\npragma solidity  >=0.8.0 <0.9.0 ;\n"), [">=0.8.0 <0.9.0"]);
+    }
+
+    #[test]
+    fn picks_the_newest_solc_every_pragma_allows() {
+        let sources = |files: &[(&str, &str)]| -> serde_json::Map<String, serde_json::Value> {
+            files.iter().map(|(path, content)| (path.to_string(), serde_json::json!({ "content": content }))).collect()
+        };
+        let pick = |files: &[(&str, &str)]| solc_from_pragmas(&sources(files)).map(|v| v.map(|v| v.to_string()));
+
+        assert_eq!(pick(&[("A.sol", "pragma solidity ^0.8.0;"), ("B.sol", "pragma solidity >=0.6.2 <0.8.20;")]).unwrap().as_deref(), Some("0.8.19"));
+        assert_eq!(pick(&[("A.sol", "pragma solidity >=0.5.0 <0.7.0;"), ("B.sol", "pragma solidity ^0.5.16;")]).unwrap().as_deref(), Some("0.5.17"));
+        assert_eq!(pick(&[("A.sol", "contract A {}"), ("B.vy", "pragma solidity ^0.4.0;")]).unwrap(), None);
+        // Releases after the known ones
+        assert_eq!(pick(&[("A.sol", "pragma solidity ^0.8.0;"), ("B.sol", "pragma solidity 0.8.33;")]).unwrap().as_deref(), Some("0.8.33"));
+        assert_eq!(pick(&[("A.sol", "pragma solidity >=0.8.31;")]).unwrap().as_deref(), Some("0.8.31"));
+        assert_eq!(pick(&[("A.sol", "pragma solidity ^0.9.0;")]).unwrap().as_deref(), Some("0.9.0"));
+
+        let err = pick(&[("Old.sol", "pragma solidity ^0.6.0;"), ("New.sol", "pragma solidity ^0.8.0;")]).unwrap_err();
+        assert!(err.to_string().contains("^0.6.0 (Old.sol), ^0.8.0 (New.sol)"), "{}", err);
+        assert!(err.to_string().contains("--contract-name"), "{}", err);
+    }
+
+    #[test]
+    fn scans_written_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, content: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, content).unwrap();
+            path
+        };
+        let files = [
+            write("Token.sol", "// SPDX-License-Identifier: MIT\npragma solidity ^0.8.0;\ncontract Token {}"),
+            write("Old.sol", "pragma solidity ^0.6.0;\ncontract Old {}"),
+            write("Math.sol", "pragma solidity ^0.8.0;\nlibrary Math {}"),
+            write("Vault.vy", "# @version 0.3.10"),
+        ];
+
+        let scan = scan(&files).unwrap();
+        assert_eq!(scan.files, 3);
+        assert_eq!(scan.missing_spdx, [files[1].clone(), files[2].clone()]);
+        assert_eq!(scan.pragmas.keys().collect::<Vec<_>>(), ["^0.6.0", "^0.8.0"]);
+        assert_eq!(scan.pragmas["^0.8.0"], [files[0].clone(), files[2].clone()]);
+    }
+}
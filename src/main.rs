@@ -1,10 +1,29 @@
-use std::io::Write;
-use clap::Parser;
-use log::{self, info, error};
-use walkdir::WalkDir;
-use std::path::PathBuf;
+use std::io::{IsTerminal, Write};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use log::{self, debug, info, warn, error};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::env;
-use dotenv::dotenv;
+use std::time::Duration;
+use futures::stream::{self, StreamExt};
+use tokenfetch::error::TokenFetchError;
+use keys::KeyPool;
+use throttle::{Throttle, Tier};
+
+mod man;
+
+use tokenfetch::chain::{chain_config, chains, get_chain_config, register_custom_chains, ChainConfig};
+use tokenfetch::explorer::{
+    build_call_url, build_creation_url, build_receipt_url, build_token_info_url, build_token_supply_url, build_transaction_url, build_url,
+    parse_response, proxy_result,
+};
+use tokenfetch::fetch::{FetchOptions, Fetcher, Loaded};
+use tokenfetch::response::{non_empty, SourceCodeResult};
+use tokenfetch::source::{declaring_file, extract_sources, normalize_whitespace, safe_name, write_sources, ContractSource, WrittenFiles};
+use tokenfetch::{
+    address, archive, cache, compliance, config, credentials, deps, diff, ens, envfile, flatten, foundry, hardhat, http, imports, interrupt,
+    keys, license, lint, manifest, progress, readme, refresh, rpc, selectors, standard, summary, throttle, token, verify,
+};
 
 #[derive(Parser, Debug)]
 #[command(
@@ -14,192 +33,3597 @@ use dotenv::dotenv;
     You can specify the chain by name, supported chains are:
     eth: Ethereum (requires ETHERSCAN_API_KEY in .env)
     base: Base (requires BASESCAN_API_KEY in .env)
-    "
+
+    Leaving out `clone`, as in `TokenFetch eth 0x... ./out`, still clones but is
+    deprecated; use `TokenFetch clone eth 0x... ./out`.
+    ",
+    arg_required_else_help = true
 )]
 struct Args {
-    /// Chain name, for more info see the help
+    #[command(subcommand)]
+    command: Command,
+    #[command(flatten)]
+    fetch: FetchArgs,
+    /// Read defaults from this config file instead of ~/.config/tokenfetch/config.toml
+    #[arg(long, global = true, value_name = "PATH", conflicts_with = "no_config")]
+    config: Option<PathBuf>,
+    /// Ignore the config file
+    #[arg(long, global = true)]
+    no_config: bool,
+    /// Load environment variables from this file instead of the nearest .env
+    #[arg(long, global = true, value_name = "PATH")]
+    env_file: Option<PathBuf>,
+    /// Log more: -v for debug output, -vv for trace output including every request
+    #[arg(short, long, global = true, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+    /// Only log warnings and errors
+    #[arg(short, long, global = true)]
+    quiet: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Clone a verified contract into a new foundry project (the default command)
+    Clone(Box<CloneArgs>),
+    /// Print the verified ABI of a contract without creating a project
+    Abi(AbiArgs),
+    /// Print what the explorer knows about a contract (compiler, optimizer, license,
+    /// proxy) without cloning it
+    Meta(MetaArgs),
+    /// List the 4-byte selectors of a contract's functions and errors and the topics of
+    /// its events
+    Selectors(SelectorsArgs),
+    /// Compare the verified sources of two contracts, e.g. a fork and its upstream
+    Diff(DiffArgs),
+    /// Print the runtime bytecode deployed at an address, or with --creation the code it
+    /// was created with
+    Bytecode(BytecodeArgs),
+    /// Re-fetch the contracts of an earlier clone and show how their sources changed
+    Refresh(RefreshArgs),
+    /// Recompile a local project and check its bytecode against the deployed contract
+    VerifyMatch(VerifyArgs),
+    /// Manage explorer API keys stored in the OS keyring
+    Key {
+        #[command(subcommand)]
+        action: KeyCommand,
+    },
+    /// Inspect or empty the cache of explorer responses
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommand,
+    },
+    /// Print a shell completion script to stdout
+    #[command(hide = true)]
+    Completions {
+        shell: clap_complete::Shell,
+    },
+    /// Print the man page to stdout
+    #[command(hide = true)]
+    Man,
+}
+
+#[derive(Subcommand, Debug)]
+enum KeyCommand {
+    /// Store the API key for a chain, read from a prompt or stdin
+    Set {
+        /// Chain name, for more info see the help
+        chain: String,
+    },
+    /// List the chains that have a stored API key
+    List,
+    /// Remove the stored API key for a chain
+    Delete {
+        /// Chain name, for more info see the help
+        chain: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheCommand {
+    /// Remove every cached response
+    Clear,
+    /// Print how many responses are cached and the space they take up
+    Size,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+#[command(group = clap::ArgGroup::new("existing").args(["force", "merge"]))]
+#[command(group = clap::ArgGroup::new("fork_tests").args(["tests", "gen_compliance_tests"]).multiple(true))]
+struct CloneArgs {
+    /// Chain name (for more info see the help), or an explorer link to the contract such
+    /// as https://etherscan.io/address/0x..., which names the address too
+    chain: String,
+    /// Address of the contract to clone (or its deployment transaction hash), several
+    /// comma-separated addresses, or - to read them from stdin
+    address: Option<String>,
+    /// Path to clone the contract to, ./<ContractName> (or ./<address>) by default
+    path: Option<String>,
+    /// Read addresses to clone from a file (one per line, # comments allowed), or - for stdin
+    #[arg(long, value_name = "FILE")]
+    addresses_file: Option<String>,
+    /// Read a saved getsourcecode response instead of querying the explorer
+    #[arg(long, value_name = "RESPONSE_JSON")]
+    from_file: Option<PathBuf>,
+    /// In batch mode, stop at the first address that fails to clone
+    #[arg(long)]
+    fail_fast: bool,
+
+    /// In batch mode, exit successfully even if some addresses failed to clone
+    #[arg(long, conflicts_with = "fail_fast")]
+    keep_going: bool,
+    /// In batch mode, how many contracts to fetch at the same time
+    /// (default: by --tier, 3 on the free tier)
+    #[arg(long, visible_alias = "max-concurrency", value_parser = clap::value_parser!(u32).range(1..))]
+    concurrency: Option<u32>,
+    /// Only write imported dependency packages (e.g. @openzeppelin/...), not the contract's own sources
+    #[arg(long)]
+    include_deps_only: bool,
+    /// Don't write the dependency packages, remap their imports in remappings.txt onto
+    /// where they're installed instead: lib/ (forge install, the default) or node_modules/
+    #[arg(long, value_name = "DIR", num_args = 0..=1, default_missing_value = "lib", conflicts_with_all = ["include_deps_only", "stdout"])]
+    link_deps: Option<deps::LinkDir>,
+    /// Only write the source declaring this contract and the files it imports, for
+    /// verifications that bundle several contracts
+    #[arg(long, value_name = "NAME", conflicts_with = "include_deps_only")]
+    contract_name: Option<String>,
+    /// Convert CRLF line endings in the sources to LF
+    #[arg(long)]
+    normalize_eol: bool,
+    /// Strip trailing spaces and tabs from each line of the sources
+    #[arg(long)]
+    trim_trailing_whitespace: bool,
+    /// JSON-RPC endpoint for resolving deployment transaction hashes and ENS names, ERC-165
+    /// checks and --check-onchain (default: rpc_url from the config file, then for ENS
+    /// names and --check-onchain the chain's public endpoint)
+    #[arg(long, value_name = "URL")]
+    rpc_url: Option<String>,
+    /// Clone into a directory that already exists
+    #[arg(long, conflicts_with = "merge")]
+    force: bool,
+    /// Add the contract to the existing foundry project at the path, without running forge init
+    #[arg(long, conflicts_with = "name_from_contract")]
+    merge: bool,
+    /// With --force or --merge, apply the verified compiler settings to the existing foundry.toml
+    #[arg(long, requires = "existing")]
+    overwrite_config: bool,
+    /// Keep forge's Counter example contract, test and script
+    #[arg(long)]
+    keep_template: bool,
+    /// Write the sources to this directory of the project, instead of the one its foundry.toml sets (src by default)
+    #[arg(long, value_name = "NAME")]
+    src_dir: Option<String>,
+    /// Treat the path as a parent directory and create the project in <PATH>/<ContractName>
+    #[arg(long)]
+    name_from_contract: bool,
+    /// When the explorer reports the contract as a proxy, also clone its implementation into
+    /// src/<ContractName>_<addr-prefix>/, fetching it while the proxy's files are written
+    #[arg(long, conflicts_with = "stdout")]
+    follow_proxy: bool,
+    /// Also write the contract's ABI to abi/<ContractName>.json
+    #[arg(long, overrides_with = "no_abi")]
+    abi: bool,
+    /// Don't write the ABI, even if always_fetch_abi is set in the config file
+    #[arg(long, overrides_with = "abi")]
+    no_abi: bool,
+    /// Also write a fork test at test/<ContractName>.fork.t.sol that sets up the contract
+    /// at its address, forking from the URL in <CHAIN>_RPC_URL
+    #[arg(long)]
+    tests: bool,
+    /// Block the fork tests fork at, instead of the latest
+    #[arg(long, value_name = "BLOCK", requires = "fork_tests")]
+    fork_block: Option<u64>,
+    /// For an ERC-20 or ERC-721 token, also write a fork test suite at
+    /// test/<ContractName>.compliance.t.sol checking it behaves as the standard says
+    #[arg(long)]
+    gen_compliance_tests: bool,
+    /// An account holding the token, for the compliance tests to transfer from (may be
+    /// repeated; addresses among the constructor arguments are tried after these)
+    #[arg(long, value_name = "ADDRESS", requires = "gen_compliance_tests")]
+    holder: Vec<String>,
+    /// For an ERC-721 token, the token the compliance tests transfer around
+    #[arg(long, value_name = "ID", requires = "gen_compliance_tests")]
+    token_id: Option<alloy_primitives::U256>,
+    /// After cloning, compile the contract and warn if it no longer matches the code
+    /// deployed at its address, e.g. after a self-destruct (over --rpc-url, or the chain's
+    /// public RPC endpoint)
+    #[arg(long)]
+    check_onchain: bool,
+    /// After writing the sources, report files without an SPDX license identifier and the
+    /// pragma solidity ranges found, warning about those that rule out the pinned solc
+    #[arg(long)]
+    check: bool,
+    /// Stop forge if it hasn't finished after this long (e.g. 90s, 10m)
+    #[arg(long, default_value = "10m", value_parser = parse_duration)]
+    forge_timeout: Duration,
+    /// Print a JSON summary of the run to stdout when it ends, also on failure
+    #[arg(long)]
+    json: bool,
+    /// Have forge download the contract's solc version right away, if it isn't installed,
+    /// with a first forge build
+    #[arg(long)]
+    install_solc: bool,
+    /// Don't write token.json with the token's name, symbol, decimals and total supply
+    #[arg(long)]
+    no_token_info: bool,
+    /// Also write .env.example with the environment variables the project needs: the
+    /// chain's API key and, with fork tests or RPC checks, its RPC endpoint
+    #[arg(long)]
+    env_example: bool,
+    /// Render the generated README.md from this template, with {{placeholders}} such as
+    /// {{contract_name}} and {{file_tree}} (see the README for the list)
+    #[arg(long, value_name = "PATH")]
+    readme_template: Option<PathBuf>,
+    /// If Ctrl-C stops the clone, remove the directory it created
+    #[arg(long)]
+    clean_on_interrupt: bool,
+    /// Print the source to stdout instead of writing a project: a single file as it is,
+    /// several under `// File: <path>` headers
+    #[arg(long, conflicts_with_all = ["json", "archive", "force", "merge", "name_from_contract"])]
+    stdout: bool,
+    /// With --stdout, print the sources as one flattened file, without their imports
+    #[arg(long, requires = "stdout")]
+    flatten: bool,
+    /// Clone into a temporary directory and pack it into this .zip or .tar.gz instead,
+    /// under a folder named after the contract
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["force", "merge"])]
+    archive: Option<PathBuf>,
+    /// What to write besides the sources: json saves the complete getsourcecode response,
+    /// verbatim, to explorer-response.json, hardhat scaffolds a Hardhat project instead of
+    /// a forge one (comma-separated for both)
+    #[arg(long, value_name = "FORMAT", value_delimiter = ',')]
+    format: Vec<OutputFormat>,
+}
+
+impl CloneArgs {
+    fn hardhat(&self) -> bool {
+        self.format.contains(&OutputFormat::Hardhat)
+    }
+
+    /// Whether to write the ABI, `--no-abi` winning over the config file's default.
+    fn abi(&self) -> bool {
+        self.abi && !self.no_abi
+    }
+
+    /// The flags that pick and rewrite the sources, as the manifest records them.
+    fn source_options(&self) -> manifest::SourceOptions {
+        manifest::SourceOptions {
+            contract_name: self.contract_name.clone(),
+            include_deps_only: self.include_deps_only,
+            link_deps: self.link_deps.is_some(),
+            normalize_eol: self.normalize_eol,
+            trim_trailing_whitespace: self.trim_trailing_whitespace,
+        }
+    }
+}
+
+/// What `--format` writes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum OutputFormat {
+    /// The explorer's response, as pretty-printed JSON
+    Json,
+    /// A Hardhat project, with the sources under contracts/
+    Hardhat,
+}
+
+#[derive(clap::Args, Debug)]
+struct AbiArgs {
+    /// Chain name, for more info see the help (may be left out with a default chain)
+    chain: String,
+    /// Address of the contract
+    address: Option<String>,
+    /// Write the ABI to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+struct MetaArgs {
+    /// Chain name, for more info see the help (may be left out with a default chain)
+    chain: String,
+    /// Address of the contract
+    address: Option<String>,
+    /// Print the metadata as a JSON object
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct SelectorsArgs {
+    /// Chain name, for more info see the help (may be left out with a default chain)
+    #[arg(required_unless_present = "abi")]
+    chain: Option<String>,
+    /// Address of the contract
+    address: Option<String>,
+    /// Read the ABI from this file instead of the explorer, either the ABI array or a
+    /// compiler artifact holding it
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["chain", "address"])]
+    abi: Option<PathBuf>,
+    /// Print the selectors as a JSON array, with each function's state mutability
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct DiffArgs {
+    /// Chain of the first contract, for more info see the help
+    #[arg(value_name = "CHAIN1")]
+    left_chain: String,
+    /// Address of the first contract, shown as a/
+    #[arg(value_name = "ADDR1")]
+    left_address: String,
+    /// Chain of the second contract
+    #[arg(value_name = "CHAIN2")]
+    right_chain: String,
+    /// Address of the second contract, shown as b/
+    #[arg(value_name = "ADDR2")]
+    right_address: String,
+    /// Only print the summary of which files differ, without the diffs
+    #[arg(long)]
+    names_only: bool,
+    /// Print the comparison as a JSON object, with each modified file's diff unless
+    /// --names-only is given
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct BytecodeArgs {
+    /// Chain name, for more info see the help (may be left out with a default chain)
+    chain: String,
+    /// Address of the contract
+    address: Option<String>,
+    /// Write the bytecode to this file instead of stdout
+    #[arg(short, long)]
+    out: Option<PathBuf>,
+    /// Print the creation bytecode, the input of the transaction that deployed the
+    /// contract, instead of the runtime code
+    #[arg(long)]
+    creation: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct RefreshArgs {
+    /// Project cloned with a .tokenfetch.json manifest
+    #[arg(default_value = ".")]
+    path: PathBuf,
+    /// Write the changed and added files, leaving locally modified ones alone
+    #[arg(long)]
+    apply: bool,
+    /// With --apply, overwrite locally modified files too
+    #[arg(long, requires = "apply")]
+    force: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct VerifyArgs {
+    /// Chain name, for more info see the help (may be left out with a default chain)
     chain: String,
-    /// Address of the contract to clone
+    /// Address of the deployed contract
     address: String,
-    /// Path to clone the contract to
-    path: String,
+    /// Forge project holding the local sources
+    path: Option<PathBuf>,
+    /// Foundry profile the contract is built with, e.g. the one batch mode created for it
+    #[arg(long)]
+    profile: Option<String>,
+    /// Read the deployed code from this JSON-RPC endpoint instead of the explorer
+    #[arg(long, value_name = "URL")]
+    rpc_url: Option<String>,
+    /// Stop forge build if it hasn't finished after this long (e.g. 90s, 10m)
+    #[arg(long, default_value = "10m", value_parser = parse_duration)]
+    forge_timeout: Duration,
 }
 
-struct ChainConfig {
-    api_key_env: &'static str,
-    api_url: &'static str,
-    chain_id: i32,
+/// Options controlling how the explorer is queried, shared by every command.
+#[derive(clap::Args, Debug)]
+struct FetchArgs {
+    /// Neither read nor write the local response cache
+    #[arg(long, global = true, conflicts_with = "refresh")]
+    no_cache: bool,
+    /// Always query the explorer and overwrite the cached response
+    #[arg(long, global = true)]
+    refresh: bool,
+    /// Never use the network: take every response from the cache and fail on anything
+    /// that isn't there
+    #[arg(long, global = true, conflicts_with_all = ["no_cache", "refresh"])]
+    offline: bool,
+    /// Treat cached responses older than this as missing (e.g. 3600, 30m, 12h, 7d)
+    #[arg(long, global = true, value_parser = parse_duration)]
+    max_age: Option<Duration>,
+    /// Send explorer requests through this proxy, overriding HTTPS_PROXY/HTTP_PROXY
+    #[arg(long, global = true, value_name = "URL")]
+    proxy: Option<String>,
+    /// Connect and request timeout for explorer requests (default 10s to connect, 30s overall)
+    #[arg(long, global = true, value_parser = parse_duration)]
+    timeout: Option<Duration>,
+    /// Retry failed requests this many times (connection errors, timeouts and 5xx responses)
+    #[arg(long, global = true, default_value_t = 2)]
+    retries: u32,
+    /// Delay before the first retry, doubled on every further attempt (e.g. 500ms, 2s)
+    #[arg(long, global = true, default_value = "1s", value_parser = parse_duration)]
+    retry_delay: Duration,
+    /// How many times to wait and retry when the explorer reports a rate limit
+    #[arg(long, global = true, default_value_t = 5)]
+    rate_limit_retries: u32,
+    /// Explorer requests per second across all concurrent fetches (default: by --tier, or
+    /// the chain's free-tier limit)
+    #[arg(long, global = true, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    rps: Option<u32>,
+    /// The explorer API plan of the key, which sets the request rate and batch concurrency:
+    /// free 5 requests per second, standard 10, advanced 20, professional 30
+    #[arg(long, global = true, value_enum)]
+    tier: Option<Tier>,
+    /// Explorer API key, taking precedence over the chain's environment variable
+    #[arg(long, global = true, value_name = "KEY", conflicts_with = "api_key_file")]
+    api_key: Option<String>,
+    /// Read the explorer API key from this file
+    #[arg(long, global = true, value_name = "PATH")]
+    api_key_file: Option<PathBuf>,
+    /// Fail on a mixed-case address whose EIP-55 checksum doesn't match, instead of warning
+    #[arg(long, global = true)]
+    strict: bool,
+    /// Define a chain served by an Etherscan-compatible explorer, as
+    /// name=<NAME>,url=<API_URL>,id=<CHAIN_ID>[,key_env=<VAR>][,explorer=<HOST>][,rps=<N>]; repeatable
+    #[arg(long, global = true, value_name = "SPEC")]
+    custom_chain: Vec<String>,
+    /// Fail unless the chain has this chain id, so a mistyped name or a misconfigured
+    /// custom chain doesn't query another chain's explorer
+    #[arg(long, global = true, value_name = "ID")]
+    chain_id: Option<i32>,
+    /// Give up on the whole command after this long, retries, backoff and forge included
+    /// (e.g. 300, 5m), removing what a clone had written
+    #[arg(long, global = true, value_parser = parse_duration)]
+    deadline: Option<Duration>,
 }
 
-fn get_chain_config(chain: &str) -> Option<ChainConfig> {
-    match chain.to_lowercase().as_str() {
-        "eth" => Some(ChainConfig {
-            api_key_env: "ETHERSCAN_API_KEY",
-            api_url: "https://api.etherscan.io/api",
-            chain_id: 1,
-        }),
-        "base" => Some(ChainConfig {
-            api_key_env: "BASESCAN_API_KEY",
-            api_url: "https://api.basescan.org/api",
-            chain_id: 8453,
-        }),
-        _ => None,
+/// Parses durations such as `3600`, `500ms`, `90s`, `30m`, `12h` or `7d`.
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let (digits, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(idx) => value.split_at(idx),
+        None => (value, "s"),
+    };
+
+    let amount: u64 = digits
+        .parse()
+        .map_err(|_| format!("invalid duration: {}", value))?;
+    let millis = match unit {
+        "ms" => 1,
+        "s" => 1000,
+        "m" => 60 * 1000,
+        "h" => 60 * 60 * 1000,
+        "d" => 24 * 60 * 60 * 1000,
+        _ => return Err(format!("invalid duration unit in {}, expected ms, s, m, h or d", value)),
+    };
+
+    amount
+        .checked_mul(millis)
+        .map(Duration::from_millis)
+        .ok_or_else(|| format!("duration {} is too long", value))
+}
+
+/// Picks the explorer API keys from `--api-key`, `--api-key-file`, the chain's
+/// environment variable (or its plural, e.g. `ETHERSCAN_API_KEYS`), the config file or
+/// the OS keyring, in that order. Each may hold several comma-separated keys to rotate through.
+fn load_api_keys(config: &ChainConfig, ctx: &AppContext) -> Result<KeyPool, Box<dyn std::error::Error>> {
+    let fetch_args = &ctx.fetch;
+    let value = if let Some(key) = &fetch_args.api_key {
+        key.clone()
+    } else if let Some(path) = &fetch_args.api_key_file {
+        std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read API key file {:?}: {}", path, e))?
+    } else if let Some(key) = env::var(config.api_key_env.as_ref())
+        .or_else(|_| env::var(format!("{}S", config.api_key_env)))
+        .ok()
+        .or_else(|| ctx.user_config.chain(&config.name)?.api_key.clone())
+        .or_else(|| credentials::get(&config.name))
+    {
+        key
+    } else if fetch_args.offline {
+        // Cached responses are all an offline run reads, and they need no key
+        return Ok(KeyPool::new(Vec::new()));
+    } else {
+        return Err(TokenFetchError::MissingApiKey {
+            env: config.api_key_env.to_string(),
+            chain: config.name.to_string(),
+        }
+        .into());
+    };
+
+    let keys = keys::parse_keys(&value);
+    if keys.is_empty() {
+        return Err(TokenFetchError::MissingApiKey {
+            env: config.api_key_env.to_string(),
+            chain: config.name.to_string(),
+        }
+        .into());
+    }
+    if keys.len() > 1 {
+        info!("Rotating between {} API keys", keys.len());
     }
+    Ok(KeyPool::new(keys))
 }
 
-fn build_url(config: &ChainConfig, address: &str, api_key: &str) -> String {
-    format!(
-        "{}?module=contract&action=getsourcecode&address={}&apikey={}",
-        config.api_url, address, api_key
-    )
+/// State shared by every command: one HTTP client, so connections are reused across
+/// requests, and the options controlling how it queries the explorer.
+struct AppContext {
+    fetcher: Fetcher,
+    fetch: FetchArgs,
+    user_config: config::Config,
+    /// Set once the explorer turns `tokeninfo` down for the key's plan, so a batch
+    /// doesn't spend a request per token on an answer it already has
+    tokeninfo_refused: AtomicBool,
 }
 
-async fn fetch_contract_source(url: &str) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
-    let res = client.get(url).send().await?;
-    let body = res.text().await?;
-    
-    let json: serde_json::Value = serde_json::from_str(&body)?;
-    
-    if let Some(status) = json["status"].as_str() {
-        if status != "1" {
-            let message = json["message"].as_str().unwrap_or("Unknown error");
-            let result = json["result"].as_str().unwrap_or("No additional info");
-            error!("API Error: {} - {}", message, result);
-            return Err(format!("API error: {} - {}", message, result).into());
+impl AppContext {
+    fn new(mut fetch: FetchArgs, mut user_config: config::Config) -> Result<Self, Box<dyn std::error::Error>> {
+        // The config file's rate and concurrency only stand in for a command line that
+        // sets neither, so `--tier` still beats a configured `rps` or `concurrency`
+        if fetch.tier.is_some() {
+            user_config.concurrency = None;
+        } else {
+            if let Some(tier) = &user_config.tier {
+                let tier = <Tier as clap::ValueEnum>::from_str(tier, true)
+                    .map_err(|_| TokenFetchError::InvalidInput(format!("Unknown tier \"{}\" in the config file", tier)))?;
+                fetch.tier = Some(tier);
+            }
+            if fetch.rps.is_none() {
+                fetch.rps = user_config.rps;
+            }
+        }
+        let client = http::Client::new(fetch.proxy.as_deref(), fetch.timeout)?;
+        let options = FetchOptions {
+            retries: fetch.retries,
+            retry_delay: fetch.retry_delay,
+            rate_limit_retries: fetch.rate_limit_retries,
+            no_cache: fetch.no_cache,
+            refresh: fetch.refresh,
+            offline: fetch.offline,
+            max_age: fetch.max_age,
+        };
+        Ok(AppContext { fetcher: Fetcher::new(client, options), fetch, user_config, tokeninfo_refused: Default::default() })
+    }
+
+    /// The configuration of `chain`, checked against `--chain-id` when given.
+    fn chain(&self, chain: &str) -> Result<&'static ChainConfig, TokenFetchError> {
+        let config = chain_config(chain)?;
+        match self.fetch.chain_id {
+            Some(expected) if expected != config.chain_id => Err(TokenFetchError::InvalidInput(format!(
+                "--chain-id {} doesn't match chain {}, whose chain id is {}",
+                expected, config.name, config.chain_id
+            ))),
+            _ => Ok(config),
+        }
+    }
+
+    /// The RPC endpoint from `--rpc-url`, or else the one configured for `chain`.
+    fn rpc_url<'a>(&'a self, flag: Option<&'a str>, chain: &ChainConfig) -> Option<&'a str> {
+        if self.fetch.offline {
+            return None;
+        }
+        flag.or_else(|| self.user_config.chain(&chain.name)?.rpc_url.as_deref())
+    }
+
+    /// The RPC endpoint for `what`, a feature that can't do without one: the configured
+    /// one, or else the chain's public default.
+    fn required_rpc_url<'a>(&'a self, flag: Option<&'a str>, chain: &ChainConfig, what: &str) -> Result<&'a str, TokenFetchError> {
+        if self.fetch.offline {
+            return Err(TokenFetchError::NotCached(format!("{} over RPC", what)));
         }
+        if let Some(url) = self.rpc_url(flag, chain) {
+            return Ok(url);
+        }
+        let url = chain.rpc_url.ok_or_else(|| {
+            TokenFetchError::InvalidInput(format!(
+                "{} needs an RPC endpoint for {}, pass --rpc-url or set rpc_url for the chain in the config file",
+                what, chain.name
+            ))
+        })?;
+        info!("{} through the public endpoint {}, pass --rpc-url to use your own node", what, url);
+        Ok(url)
+    }
+
+    /// Paces explorer requests for `chain`, at `--rps`, the `--tier` rate or else the
+    /// chain's own rate limit.
+    fn throttle(&self, concurrency: usize, chain: &ChainConfig) -> Throttle {
+        let rps = self.fetch.rps.or(self.fetch.tier.map(Tier::rps)).unwrap_or(chain.rate_limit);
+        Throttle::new(concurrency, rps)
     }
+}
 
-    Ok(json)
+fn load_response_file(path: &Path) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    let body = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read response file {:?}: {}", path, e))?;
+
+    Ok(parse_response(&body)?)
 }
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Load .env file
-    dotenv().ok();
-
-    env_logger::builder()
+async fn main() -> std::process::ExitCode {
+    // The logger is needed before clap parses the arguments, so -v and -q are picked
+    // out of the raw arguments
+    let raw_args: Vec<std::ffi::OsString> = env::args_os().collect();
+    let mut builder = env_logger::builder();
+    // RUST_LOG's directives apply on top of the info default
+    builder
         .filter_level(log::LevelFilter::Info)
         .format_target(false)
         .format_timestamp(None)
-        .init();
-    
-    let args = Args::parse();
+        .parse_default_env();
+    // Verbose output is only this crate's, the HTTP stack's debug logs drown it out
+    match config::raw_count(&raw_args, 'v', "--verbose") {
+        _ if config::raw_count(&raw_args, 'q', "--quiet") > 0 => builder.filter_level(log::LevelFilter::Warn),
+        0 => &mut builder,
+        1 => builder.filter_module(env!("CARGO_CRATE_NAME"), log::LevelFilter::Debug),
+        _ => builder.filter_module(env!("CARGO_CRATE_NAME"), log::LevelFilter::Trace),
+    };
+    let logger = builder.build();
+    log::set_max_level(logger.filter());
+    log::set_boxed_logger(Box::new(summary::Logger(logger))).expect("the logger is only set once");
 
-    // Get chain configuration
-    let config = get_chain_config(&args.chain)
-        .expect("Unsupported chain");
+    let result = run(raw_args).await;
+    if let Err(e) = &result {
+        error!("{}", e);
+    }
+    if summary::enabled() {
+        println!("{}", summary::finish(result.as_ref().map(|_| ())));
+    }
+    match result {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(e) => std::process::ExitCode::from(e.exit_code()),
+    }
+}
+
+async fn run(raw_args: Vec<std::ffi::OsString>) -> Result<(), TokenFetchError> {
+    // The env file and the config file are needed before clap parses the arguments,
+    // the config file because it supplies their defaults
+    // Known this early so failures before the clone starts are reported as JSON too
+    if config::raw_flag(&raw_args, "--json") {
+        summary::enable();
+    }
+    manifest::track();
+    envfile::load(config::raw_flag_value(&raw_args, "--env-file").as_deref(), &env::current_dir()?)?;
+    let mut user_config = config::load_from_args(&raw_args)?;
+    if let Some(chain) = env::var("TOKENFETCH_CHAIN").ok().filter(|chain| !chain.is_empty()) {
+        user_config.default_chain = Some(chain);
+    }
+    let raw_args = with_default_subcommand(&Args::command(), raw_args);
+    let matches = match with_config_defaults(Args::command(), &user_config).try_get_matches_from(raw_args) {
+        Ok(matches) => matches,
+        // Help and version aren't failures, and without --json nothing has started yet,
+        // so clap reports them itself
+        Err(e) if !e.use_stderr() || !summary::enabled() => e.exit(),
+        Err(e) => return Err(usage_error(e)),
+    };
+    let args = Args::from_arg_matches(&matches).map_err(usage_error)?;
+    // Progress is for people watching a terminal, not for logs or scripts
+    let interactive = std::io::stdout().is_terminal() && std::io::stderr().is_terminal();
+    // Only a clone reports with --json, `meta --json` prints its own document
+    if !matches!(&args.command, Command::Clone(clone_args) if clone_args.json) {
+        summary::disable();
+    }
+    progress::init(interactive && !args.quiet && !summary::enabled());
+    register_custom_chains(&args.fetch.custom_chain, &user_config)?;
 
-    // Get API key from environment
-    let api_key = env::var(config.api_key_env)
-        .unwrap_or_else(|_| panic!("{} environment variable not set", config.api_key_env));
+    // Commands that never touch the network
+    match args.command {
+        Command::Completions { shell } => {
+            write_completions(shell, &mut std::io::stdout());
+            return Ok(());
+        }
+        Command::Man => return Ok(man::write(Args::command(), &mut std::io::stdout())?),
+        Command::Key { action } => return Ok(manage_keys(action)?),
+        Command::Cache { action } => return Ok(manage_cache(action)?),
+        _ => {}
+    }
 
-    // Rest of the implementation remains the same, starting from here:
-    let path = PathBuf::from(&args.path);
-    if path.exists() {
-        error!("Path {} already exists", args.path);
-        panic!("Path already exists");
+    let deadline = args.fetch.deadline;
+    let ctx = AppContext::new(args.fetch, user_config)?;
+    let clean_on_interrupt = matches!(&args.command, Command::Clone(clone_args) if clone_args.clean_on_interrupt || clone_args.archive.is_some());
+    let mut command = Box::pin(async {
+        match args.command {
+            Command::Clone(clone_args) => clone_contract(*clone_args, &ctx).await,
+            Command::Abi(abi_args) => print_abi(abi_args, &ctx).await,
+            Command::Meta(meta_args) => print_meta(meta_args, &ctx).await,
+            Command::Selectors(selectors_args) => print_selectors(selectors_args, &ctx).await,
+            Command::Diff(diff_args) => print_diff(diff_args, &ctx).await,
+            Command::Bytecode(bytecode_args) => print_bytecode(bytecode_args, &ctx).await,
+            Command::Refresh(refresh_args) => refresh_project(refresh_args, &ctx).await,
+            Command::VerifyMatch(verify_args) => verify_match(verify_args, &ctx).await,
+            Command::Completions { .. } | Command::Man | Command::Key { .. } | Command::Cache { .. } => Ok(()),
+        }
+    });
+    let result = tokio::select! {
+        result = &mut command => result,
+        _ = tokio::signal::ctrl_c() => {
+            warn!("Interrupted, cleaning up");
+            // Dropping the command kills any forge process it started
+            drop(command);
+            interrupt::clean_up(clean_on_interrupt);
+            return Err(TokenFetchError::Interrupted);
+        }
+        _ = expire(deadline) => {
+            warn!("Deadline exceeded, cleaning up");
+            drop(command);
+            // What the clone wrote so far is a partial project either way
+            interrupt::clean_up(true);
+            return Err(TokenFetchError::DeadlineExceeded(deadline.unwrap_or_default()));
+        }
+    };
+    Ok(result?)
+}
+
+/// Resolves once `deadline` has passed, or never without one.
+async fn expire(deadline: Option<Duration>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep(deadline).await,
+        None => std::future::pending().await,
     }
-    
-    std::fs::create_dir(&path)?;
-    info!("Created directory: {}", args.path);
+}
 
-    info!("Chain id: {}", config.chain_id);
-    info!("Cloning contract at address {} to path {}", args.address, args.path);
-
-    // Initialize forge project
-    let output = std::process::Command::new("forge")
-        .arg("init")
-        .arg(&args.path)
-        .arg("--no-commit")
-        .output()?;
-
-    if !output.status.success() {
-        error!("Failed to initialize forge project: {}", String::from_utf8_lossy(&output.stderr));
-        panic!("Forge initialization failed");
-    }
-    info!("Initialized forge project");
-
-    // Find and remove Counter files
-    let src_path = path.join("src");
-    info!("Searching for Counter files in: {:?}", src_path);
-    
-    for entry in WalkDir::new(&src_path) {
-        match entry {
-            Ok(entry) => {
-                let path = entry.path();
-                if path.is_file() && path.to_string_lossy().contains("Counter") {
-                    info!("Removing Counter file: {:?}", path);
-                    std::fs::remove_file(path)?;
+fn manage_cache(action: CacheCommand) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = cache::dir().ok_or("No cache directory available on this platform")?;
+    match action {
+        CacheCommand::Clear => {
+            let freed = cache::clear()?;
+            info!("Removed {} cached responses ({}) from {}", freed.entries, cache::format_size(freed.bytes), dir.display());
+        }
+        CacheCommand::Size => {
+            let usage = cache::usage();
+            println!("{} cached responses, {} in {}", usage.entries, cache::format_size(usage.bytes), dir.display());
+        }
+    }
+    Ok(())
+}
+
+fn manage_keys(action: KeyCommand) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        KeyCommand::Set { chain } => {
+            let config = chain_config(&chain)?;
+            let key = if std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+                rpassword::prompt_password(format!("API key for {}: ", config.name))?
+            } else {
+                let mut line = String::new();
+                std::io::stdin().read_line(&mut line)?;
+                line
+            };
+            let key = key.trim();
+            if key.is_empty() {
+                return Err(TokenFetchError::InvalidInput("No API key given".into()).into());
+            }
+
+            credentials::set(&config.name, key)?;
+            info!("Stored API key for {} in the OS keyring", config.name);
+        }
+        KeyCommand::List => {
+            for config in chains() {
+                if credentials::exists(&config.name)? {
+                    println!("{}", config.name);
                 }
             }
-            Err(e) => error!("Error walking directory: {}", e),
+        }
+        KeyCommand::Delete { chain } => {
+            let config = chain_config(&chain)?;
+            if credentials::delete(&config.name)? {
+                info!("Deleted API key for {}", config.name);
+            } else {
+                warn!("No API key stored for {}", config.name);
+            }
         }
     }
+    Ok(())
+}
+
+/// Makes config file values the defaults of their flags, so the command line still wins.
+fn with_config_defaults(command: clap::Command, user_config: &config::Config) -> clap::Command {
+    command.mut_subcommand("clone", |clone| {
+        clone.mut_args(|arg| match arg.get_id().as_str() {
+            "abi" if user_config.always_fetch_abi => arg.default_value("true"),
+            _ => arg,
+        })
+    })
+}
 
-    // Fetch contract source
-    let url = build_url(&config, &args.address, &api_key);
-    info!("Fetching contract from API...");
-    
-    let json = fetch_contract_source(&url).await?;
-    
-    let result = json["result"].as_array()
-        .ok_or("No result array in response")?;
-    
-    let source_code = result[0]["SourceCode"].as_str()
-        .ok_or("No source code in response")?;
+/// Inserts `clone` where the subcommand would go when none is given, so invocations
+/// from before there were subcommands, like `TokenFetch eth 0x... ./out`, keep working.
+///
+/// The subcommand goes before the first argument that isn't one of the top-level flags
+/// (or a top-level flag's value). Help and version flags are left for clap to handle.
+fn with_default_subcommand(command: &clap::Command, mut raw_args: Vec<std::ffi::OsString>) -> Vec<std::ffi::OsString> {
+    let takes_value = |arg: &clap::Arg| arg.get_action().takes_values();
+    let mut idx = 1;
+    while let Some(raw) = raw_args.get(idx) {
+        let Some(token) = raw.to_str() else { break };
+        if let Some(long) = token.strip_prefix("--").filter(|long| !long.is_empty()) {
+            let (name, inline_value) = match long.split_once('=') {
+                Some((name, _)) => (name, true),
+                None => (long, false),
+            };
+            if name == "help" || name == "version" {
+                return raw_args;
+            }
+            match command.get_arguments().find(|arg| arg.get_long() == Some(name)) {
+                Some(arg) => idx += if takes_value(arg) && !inline_value { 2 } else { 1 },
+                None => break,
+            }
+        } else if let Some(cluster) = token.strip_prefix('-').filter(|cluster| !cluster.is_empty() && !cluster.starts_with('-')) {
+            if cluster.contains(['h', 'V']) {
+                return raw_args;
+            }
+            let args: Option<Vec<&clap::Arg>> = cluster
+                .chars()
+                .map(|short| command.get_arguments().find(|arg| arg.get_short() == Some(short)))
+                .collect();
+            match args {
+                // A short flag taking a value takes the next argument unless it ends the cluster
+                Some(args) => idx += if args.last().is_some_and(|arg| takes_value(arg)) { 2 } else { 1 },
+                None => break,
+            }
+        } else {
+            if command.find_subcommand(token).is_some() || token == "help" {
+                return raw_args;
+            }
+            break;
+        }
+    }
+    if idx < raw_args.len() {
+        raw_args.insert(idx, "clone".into());
+    }
+    raw_args
+}
 
-    if source_code.is_empty() {
-        error!("Contract source code is empty. The contract might not be verified.");
-        return Err("Contract source code is empty".into());
+/// Writes a completion script for `shell`, completing chain names as well as flags.
+fn write_completions(shell: clap_complete::Shell, out: &mut dyn Write) {
+    // Chain names are validated at runtime, so they are only listed for completion here,
+    // custom chains included. mut_args edits in place, keeping the positional order intact,
+    // and every level of subcommands is covered, down to `key set <chain>`.
+    fn with_chain_names(command: clap::Command) -> clap::Command {
+        command
+            .mut_args(|arg| match arg.get_id().as_str() {
+                "chain" => arg.value_parser(clap::builder::PossibleValuesParser::new(chains().map(|config| config.name.as_ref()))),
+                _ => arg,
+            })
+            .mut_subcommands(with_chain_names)
     }
 
-    // Handle different source code formats
-    let sources = if source_code.starts_with('{') {
-        // Handle JSON format
-        let contract: serde_json::Value = if source_code.contains("{{") {
-            // Handle double-braced format
-            let cleaned = source_code.replace("{{", "{").replace("}}", "}");
-            serde_json::from_str(&cleaned)?
-        } else {
-            serde_json::from_str(source_code)?
-        };
+    let mut command = with_chain_names(Args::command());
+    let name = command.get_name().to_string();
+
+    clap_complete::generate(shell, &mut command, name, out);
+}
+
+async fn print_abi(args: AbiArgs, ctx: &AppContext) -> Result<(), Box<dyn std::error::Error>> {
+    let (config, address, keys) = resolve_target(args.chain, args.address, "abi", None, ctx).await?;
+
+    let throttle = ctx.throttle(1, config);
+    let json = ctx.fetcher.fetch_cached(config, &address, &keys, &throttle).await?;
+
+    let abi = extract_abi(&SourceCodeResult::from_response(&json)?)?;
+    let abi = serde_json::to_string_pretty(&abi)?;
+
+    match args.output {
+        Some(output) => {
+            std::fs::write(&output, abi + "\n")?;
+            info!("Wrote ABI to {:?}", output);
+        }
+        None => println!("{}", abi),
+    }
+
+    Ok(())
+}
+
+async fn print_meta(args: MetaArgs, ctx: &AppContext) -> Result<(), Box<dyn std::error::Error>> {
+    let (config, address, keys) = resolve_target(args.chain, args.address, "meta", None, ctx).await?;
+
+    let throttle = ctx.throttle(1, config);
+    let json = match ctx.fetcher.fetch_cached(config, &address, &keys, &throttle).await {
+        Ok(json) => Some(json),
+        Err(TokenFetchError::NotFound) => None,
+        Err(e) => return Err(e.into()),
+    };
+    let contract = match json.as_ref().map(extract_sources) {
+        Some(Ok(contract)) => Some(contract),
+        // Not being verified is the answer here rather than a failure
+        Some(Err(TokenFetchError::NotVerified)) | None => None,
+        Some(Err(e)) => return Err(e.into()),
+    };
+    let mut meta = contract_meta(&config.name, &address, contract.as_ref());
+    if let Some(contract) = &contract {
+        meta["standard"] = classify(ctx, ctx.rpc_url(None, config), &address, &contract.entry, None).await.to_json();
+    }
 
-        contract["sources"].as_object()
-            .ok_or("No sources object in contract")?
-            .clone()
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&meta)?);
     } else {
-        // Handle single file format
-        let mut map = serde_json::Map::new();
-        map.insert(
-            "Single.sol".to_string(),
-            serde_json::json!({
-                "content": source_code
-            }),
-        );
-        map
+        for line in meta_table(&meta) {
+            println!("{}", line);
+        }
+    }
+    // Printed all the same, with the exit code scripts expect for it
+    match contract {
+        Some(_) => Ok(()),
+        None => Err(TokenFetchError::NotVerified.into()),
+    }
+}
+
+async fn print_selectors(args: SelectorsArgs, ctx: &AppContext) -> Result<(), Box<dyn std::error::Error>> {
+    let abi = match (&args.abi, args.chain) {
+        (Some(path), _) => {
+            let contents = std::fs::read_to_string(path).map_err(|e| format!("Failed to read ABI file {:?}: {}", path, e))?;
+            serde_json::from_str(&contents)?
+        }
+        (None, chain) => {
+            let (config, address, keys) = resolve_target(chain.unwrap_or_default(), args.address, "selectors", None, ctx).await?;
+            let throttle = ctx.throttle(1, config);
+            extract_abi(&SourceCodeResult::from_response(&ctx.fetcher.fetch_cached(config, &address, &keys, &throttle).await?)?)?
+        }
     };
 
-    // Create contract files
-    for (key, value) in sources {
-        let mut file_path = src_path.clone();
-        
-        let parts: Vec<&str> = key.split('/').collect();
-        for dir in &parts[..parts.len()-1] {
-            file_path.push(dir);
-            std::fs::create_dir_all(&file_path)?;
-            info!("Created directory: {:?}", file_path);
+    let selectors = selectors::selectors(&abi)?;
+    if args.json {
+        let selectors: Vec<serde_json::Value> = selectors.iter().map(selectors::Selector::to_json).collect();
+        println!("{}", serde_json::to_string_pretty(&selectors)?);
+    } else {
+        for selector in &selectors {
+            println!("{} {}", selector.selector, selector.signature);
         }
-        
-        file_path.push(parts.last().unwrap());
-        
-        if let Some(content) = value["content"].as_str() {
-            info!("Creating file: {:?}", file_path);
-            let mut file = std::fs::File::create(file_path)?;
-            file.write_all(content.as_bytes())?;
+    }
+    Ok(())
+}
+
+async fn print_diff(args: DiffArgs, ctx: &AppContext) -> Result<(), Box<dyn std::error::Error>> {
+    let left = fetch_sources(&args.left_chain, &args.left_address, ctx).await?;
+    let right = fetch_sources(&args.right_chain, &args.right_address, ctx).await?;
+    info!("a: {} {}", args.left_chain, args.left_address);
+    info!("b: {} {}", args.right_chain, args.right_address);
+
+    let pairing = diff::pair_sources(&left.sources, &right.sources);
+    let mut identical = Vec::new();
+    let mut modified = Vec::new();
+    for (l, r) in &pairing.pairs {
+        let (old, new) = (diff::content(&left.sources[l]), diff::content(&right.sources[r]));
+        match diff::unified(&format!("a/{}", l), &format!("b/{}", r), &old, &new) {
+            Some(unified) => modified.push((l, r, unified)),
+            None => identical.push(l),
         }
     }
+    let compiler_differs = left.compiler_version != right.compiler_version;
+    // Single-file verifications only tell the optimizer configuration
+    let settings = match (&left.settings, &right.settings) {
+        (Some(l), Some(r)) => diff::settings_differences(l, r),
+        _ => diff::settings_differences(&optimizer_settings(&left), &optimizer_settings(&right)),
+    };
+    let same = modified.is_empty() && pairing.only_left.is_empty() && pairing.only_right.is_empty();
+    let matches = same && !compiler_differs && settings.is_empty();
 
-    info!("Contract cloning completed successfully!");
+    let mut stdout = std::io::stdout().lock();
+    let pair_name = |l: &str, r: &str| if l == r { l.to_string() } else { format!("{} -> {}", l, r) };
+    if args.json {
+        let modified: Vec<serde_json::Value> = modified
+            .iter()
+            .map(|(l, r, unified)| {
+                let mut entry = serde_json::json!({ "a": l, "b": r });
+                if !args.names_only {
+                    entry["diff"] = unified.as_str().into();
+                }
+                entry
+            })
+            .collect();
+        let side = |chain: &str, address: &str, contract: &ContractSource| {
+            serde_json::json!({ "chain": chain, "address": address, "compiler_version": contract.compiler_version })
+        };
+        let doc = serde_json::json!({
+            "a": side(&args.left_chain, &args.left_address, &left),
+            "b": side(&args.right_chain, &args.right_address, &right),
+            "identical": matches,
+            "compiler_differs": compiler_differs,
+            "settings_differ": settings,
+            "files": {
+                "identical": identical,
+                "modified": modified,
+                "only_in_a": pairing.only_left,
+                "only_in_b": pairing.only_right,
+            },
+        });
+        writeln!(stdout, "{}", serde_json::to_string_pretty(&doc)?)?;
+    } else {
+        // Compiler differences first, they matter even when every source file matches
+        if compiler_differs {
+            let version = |contract: &ContractSource| contract.compiler_version.clone().unwrap_or_else(|| "unknown".to_string());
+            writeln!(stdout, "Compiler differs: a {}, b {}", version(&left), version(&right))?;
+        }
+        if !settings.is_empty() {
+            writeln!(stdout, "Settings differ: {}", settings.join(", "))?;
+        }
+        writeln!(stdout, "{} files identical", identical.len())?;
+        for (l, r, _) in &modified {
+            writeln!(stdout, "Modified: {}", pair_name(l, r))?;
+        }
+        for path in &pairing.only_left {
+            writeln!(stdout, "Only in a: {}", path)?;
+        }
+        for path in &pairing.only_right {
+            writeln!(stdout, "Only in b: {}", path)?;
+        }
+        if !args.names_only {
+            let color = std::io::stdout().is_terminal();
+            for (_, _, unified) in &modified {
+                let unified = if color { diff::colorize(unified) } else { unified.clone() };
+                stdout.write_all(unified.as_bytes())?;
+            }
+        }
+    }
+
+    info!(
+        "{} files differ, {} are identical, {} only in a, {} only in b",
+        modified.len(),
+        identical.len(),
+        pairing.only_left.len(),
+        pairing.only_right.len()
+    );
+    match matches {
+        true => Ok(()),
+        false if same => Err(TokenFetchError::Differs("The sources match, but they weren't compiled the same way".into()).into()),
+        false => Err(TokenFetchError::Differs("The contracts differ".into()).into()),
+    }
+}
+
+/// The optimizer configuration of a contract shaped like standard-JSON settings, to
+/// compare with a contract that only has the explorer's fields.
+fn optimizer_settings(contract: &ContractSource) -> serde_json::Value {
+    match contract.optimizer {
+        Some((enabled, runs)) => serde_json::json!({ "optimizer": { "enabled": enabled, "runs": runs } }),
+        None => serde_json::json!({}),
+    }
+}
+
+/// Re-fetches every contract in a clone's manifest and prints a unified diff of each file
+/// that changed, from the copy on disk (a/) to the verified source (b/). Files edited since
+/// the clone are flagged and only overwritten with `--apply --force`.
+async fn refresh_project(args: RefreshArgs, ctx: &AppContext) -> Result<(), Box<dyn std::error::Error>> {
+    let root = args.path.as_path();
+    let Some(manifest) = manifest::Manifest::read(root) else {
+        return Err(TokenFetchError::InvalidInput(format!(
+            "{} has no {}, only clones made with one can be refreshed",
+            root.display(),
+            manifest::FILE
+        ))
+        .into());
+    };
+    let config = ctx.chain(&manifest.chain)?;
+    let keys = load_api_keys(config, ctx)?;
+    let throttle = ctx.throttle(1, config);
+
+    let color = std::io::stdout().is_terminal();
+    let mut stdout = std::io::stdout().lock();
+    let (mut changed, mut added, mut removed, mut edited) = (0, 0, 0, 0);
+    let mut contracts = Vec::new();
+    for cloned in &manifest.contracts {
+        info!("Refreshing {} from {}", cloned.name.as_deref().unwrap_or(&cloned.address), config.api_url);
+        // A minimal proxy's sources are those of the contract it delegates to
+        let fetched_from = cloned.source_address.as_deref().unwrap_or(&cloned.address);
+        // Always asks the explorer, re-verification is what this looks for
+        let json = ctx.fetcher.query(&throttle, &keys, |key| build_url(config, fetched_from, key)).await?;
+        if !ctx.fetch.no_cache {
+            if let Err(e) = cache::store(config.chain_id, fetched_from, &json) {
+                error!("Failed to cache response: {}", e);
+            }
+        }
+        // Picked and rewritten as the clone did, so only upstream changes show
+        let mut contract = extract_sources(&json)?;
+        select_sources(&cloned.options, &mut contract, fetched_from)?;
+        if cloned.options.link_deps {
+            contract.sources.retain(|key, _| deps::package(key).is_none());
+        }
+        if contract.compiler_version != cloned.compiler_version || contract.settings != cloned.settings {
+            warn!(
+                "{} is now verified with {} and different settings, foundry.toml isn't updated; clone it again to apply them",
+                cloned.address,
+                contract.compiler_version.as_deref().unwrap_or("an unknown compiler")
+            );
+        }
+
+        let plan = refresh::plan(root, &manifest, &cloned.source_dir, &contract.sources);
+        for path in &plan.locally_modified {
+            warn!("MODIFIED LOCALLY: {} differs from what was cloned", path);
+        }
+        for change in &plan.changes {
+            let old_label = match change.old {
+                Some(_) => format!("a/{}", change.path),
+                None => "/dev/null".to_string(),
+            };
+            let old = change.old.as_deref().unwrap_or_default();
+            if let Some(unified) = diff::unified(&old_label, &format!("b/{}", change.path), old, &change.new) {
+                let unified = if color { diff::colorize(&unified) } else { unified };
+                stdout.write_all(unified.as_bytes())?;
+            }
+            match change.old {
+                Some(_) => changed += 1,
+                None => added += 1,
+            }
+            if !args.apply {
+                continue;
+            }
+            if change.locally_modified && !args.force {
+                warn!("Not overwriting {}, which was modified locally; pass --apply --force to replace it", change.path);
+                continue;
+            }
+            let path = root.join(&change.path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, &change.new)?;
+            summary::file_written(&path);
+        }
+        for path in &plan.removed {
+            writeln!(stdout, "Removed upstream: {}", path)?;
+        }
+        removed += plan.removed.len();
+        edited += plan.locally_modified.len();
+
+        contracts.push(manifest::Contract {
+            address: cloned.address.clone(),
+            source_address: cloned.source_address.clone(),
+            name: contract.contract_name.filter(|name| !name.is_empty()),
+            compiler_version: contract.compiler_version,
+            settings: contract.settings,
+            license: contract.license.as_deref().and_then(license::spdx_identifier).map(str::to_string),
+            source_dir: cloned.source_dir.clone(),
+            options: cloned.options.clone(),
+        });
+    }
+
+    info!(
+        "{} files changed upstream, {} added, {} removed upstream, {} modified locally",
+        changed, added, removed, edited
+    );
+    if args.apply {
+        manifest::write(root, &config.name, config.chain_id, &config.api_url, contracts, manifest::fetched_at(false))?;
+        if removed > 0 {
+            info!("Files removed upstream are left in place, delete them once nothing imports them");
+        }
+    } else if changed + added > 0 {
+        info!("Pass --apply to write the changes");
+    }
+    Ok(())
+}
+
+async fn print_bytecode(args: BytecodeArgs, ctx: &AppContext) -> Result<(), Box<dyn std::error::Error>> {
+    let (config, address, keys) = resolve_target(args.chain, args.address, "bytecode", None, ctx).await?;
+    let throttle = ctx.throttle(1, config);
+
+    let code = if args.creation {
+        creation_code(ctx, &keys, &throttle, config, &address).await?
+    } else {
+        ctx.fetcher.deployed_code(config, &keys, &throttle, None, &address).await?
+    };
+    if code.trim_start_matches("0x").is_empty() {
+        return Err(TokenFetchError::NoCode(address).into());
+    }
+
+    match args.out {
+        Some(out) => {
+            std::fs::write(&out, code + "\n")?;
+            info!("Wrote bytecode to {:?}", out);
+        }
+        None => println!("{}", code),
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// The input of the transaction that created `address`, which for a contract deployed
+/// directly rather than by a factory is its creation code and constructor arguments.
+async fn creation_code(
+    ctx: &AppContext,
+    keys: &KeyPool,
+    throttle: &Throttle,
+    config: &ChainConfig,
+    address: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let creation = match ctx.fetcher.query(throttle, keys, |key| build_creation_url(config, address, key)).await {
+        Ok(json) => json,
+        // An address that was never deployed to has no creation record
+        Err(TokenFetchError::NotFound) => return Err(TokenFetchError::NoCode(address.to_string()).into()),
+        Err(e) => return Err(e.into()),
+    };
+    let tx_hash = creation["result"][0]["txHash"]
+        .as_str()
+        .ok_or_else(|| TokenFetchError::NoCode(address.to_string()))?;
+    debug!("{} was created in transaction {}", address, tx_hash);
+
+    let transaction = ctx.fetcher.query(throttle, keys, |key| build_transaction_url(config, tx_hash, key)).await?;
+    let transaction = proxy_result(&transaction)?;
+    if !transaction["to"].is_null() {
+        return Err(format!(
+            "{} was deployed by the contract at {} in transaction {}, so its creation code isn't the transaction input",
+            address,
+            transaction["to"].as_str().unwrap_or("?"),
+            tx_hash
+        )
+        .into());
+    }
+    Ok(transaction["input"].as_str().ok_or("No input in explorer transaction response")?.to_string())
+}
+
+/// Fetches the verified sources of the contract at `address` on `chain`, as for a clone.
+async fn fetch_sources(chain: &str, address: &str, ctx: &AppContext) -> Result<ContractSource, Box<dyn std::error::Error>> {
+    let (config, address, keys) = resolve_target(chain.to_string(), Some(address.to_string()), "diff", None, ctx).await?;
+    let throttle = ctx.throttle(1, config);
+    Ok(extract_sources(&ctx.fetcher.fetch_cached(config, &address, &keys, &throttle).await?)?)
+}
+
+/// The verification metadata of the contract at `address`, or with just `verified: false`
+/// when it isn't verified.
+fn contract_meta(chain: &str, address: &str, contract: Option<&ContractSource>) -> serde_json::Value {
+    let Some(contract) = contract else {
+        return serde_json::json!({ "chain": chain, "address": address, "verified": false });
+    };
+    let result = &contract.entry;
+    let evm_version = non_empty(&result.evm_version)
+        .filter(|version| !version.eq_ignore_ascii_case("default"))
+        .or_else(|| contract.settings.as_ref().and_then(|settings| settings["evmVersion"].as_str()));
+    serde_json::json!({
+        "chain": chain,
+        "address": address,
+        "verified": true,
+        "contract_name": contract.contract_name,
+        "compiler_version": contract.compiler_version,
+        "optimizer": contract.optimizer.map(|(enabled, runs)| serde_json::json!({ "enabled": enabled, "runs": runs })),
+        "evm_version": evm_version,
+        "license": contract.license,
+        "spdx": contract.license.as_deref().and_then(license::spdx_identifier),
+        "proxy": result.is_proxy(),
+        "implementation": non_empty(&result.implementation),
+        "source_files": contract.sources.len(),
+    })
+}
+
+/// `meta` as aligned `label  value` rows.
+fn meta_table(meta: &serde_json::Value) -> Vec<String> {
+    let text = |value: &serde_json::Value| value.as_str().unwrap_or("-").to_string();
+    let mut rows = vec![("Address", text(&meta["address"])), ("Chain", text(&meta["chain"]))];
+    if meta["verified"] == false {
+        rows.push(("Verified", "no".to_string()));
+    } else {
+        let optimizer = &meta["optimizer"];
+        rows.extend([
+            ("Verified", "yes".to_string()),
+            ("Contract", text(&meta["contract_name"])),
+            ("Compiler", text(&meta["compiler_version"])),
+            (
+                "Optimizer",
+                match (optimizer["enabled"].as_bool(), optimizer["runs"].as_u64()) {
+                    (Some(true), Some(runs)) => format!("enabled, {} runs", runs),
+                    (Some(true), None) => "enabled".to_string(),
+                    (Some(false), _) => "disabled".to_string(),
+                    (None, _) => "-".to_string(),
+                },
+            ),
+            ("EVM version", meta["evm_version"].as_str().unwrap_or("default").to_string()),
+            (
+                "License",
+                match (meta["license"].as_str(), meta["spdx"].as_str()) {
+                    (Some(license), Some(spdx)) if license != spdx => format!("{} ({})", license, spdx),
+                    _ => text(&meta["license"]),
+                },
+            ),
+            (
+                "Proxy",
+                match (meta["proxy"].as_bool(), meta["implementation"].as_str()) {
+                    (Some(true), Some(implementation)) => format!("yes, implementation {}", implementation),
+                    (Some(true), None) => "yes".to_string(),
+                    _ => "no".to_string(),
+                },
+            ),
+            ("Source files", meta["source_files"].to_string()),
+        ]);
+        if let Some(standard) = meta["standard"]["standard"].as_str() {
+            let extensions: Vec<&str> = meta["standard"]["extensions"].as_array().into_iter().flatten().filter_map(|e| e.as_str()).collect();
+            rows.push(match extensions.is_empty() {
+                true => ("Standard", standard.to_string()),
+                false => ("Standard", format!("{} ({})", standard, extensions.join(", "))),
+            });
+        }
+    }
+    let width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    rows.into_iter().map(|(label, value)| format!("{:width$}  {}", label, value)).collect()
+}
+
+async fn verify_match(mut args: VerifyArgs, ctx: &AppContext) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(default_chain) = omitted_chain(&args.chain, ctx)? {
+        if let Some(extra) = &args.path {
+            return Err(TokenFetchError::InvalidInput(format!("Unexpected argument {}", extra.display())).into());
+        }
+        args.path = Some(PathBuf::from(std::mem::replace(&mut args.address, std::mem::replace(&mut args.chain, default_chain))));
+    }
+    let path = args.path.clone().ok_or_else(|| missing_arguments("verify-match", "<PATH>"))?;
+    let (config, address, keys) = resolve_target(args.chain, Some(args.address), "verify-match", args.rpc_url.as_deref(), ctx).await?;
+
+    let throttle = ctx.throttle(1, config);
+    let json = ctx.fetcher.fetch_cached(config, &address, &keys, &throttle).await?;
+    let contract = extract_sources(&json)?;
+    let name = contract.contract_name.as_deref().ok_or("The explorer reports no contract name")?;
+
+    let onchain = ctx.fetcher.deployed_code(config, &keys, &throttle, ctx.rpc_url(args.rpc_url.as_deref(), config), &address).await?;
+
+    let check = compare_with_deployed(&path, args.profile.as_deref(), &contract, &onchain, args.forge_timeout).await?;
+    Ok(report_code_check(name, &address, check)?)
+}
+
+/// Reports what `verify-match` found, failing with `NoCode` or `Differs` unless the build
+/// matches the deployed code.
+fn report_code_check(name: &str, address: &str, check: CodeCheck) -> Result<(), TokenFetchError> {
+    let (local_len, onchain_len, ranges) = match check {
+        CodeCheck::NoCode => return Err(TokenFetchError::NoCode(address.to_string())),
+        CodeCheck::Matches(len) => {
+            info!("{} matches the deployed bytecode ({} bytes, metadata excluded)", name, len);
+            return Ok(());
+        }
+        CodeCheck::Differs { local_len, onchain_len, ranges } => (local_len, onchain_len, ranges),
+    };
+
+    error!("{} does not match the deployed bytecode", name);
+    if local_len != onchain_len {
+        error!("  local code is {} bytes, deployed code is {} bytes", local_len, onchain_len);
+    }
+    for range in ranges.iter().take(MAX_REPORTED_RANGES) {
+        error!("  bytes 0x{:04x}..0x{:04x} differ ({} bytes)", range.start, range.end, range.len());
+    }
+    if ranges.len() > MAX_REPORTED_RANGES {
+        error!("  ...and {} more differing ranges", ranges.len() - MAX_REPORTED_RANGES);
+    }
+    Err(TokenFetchError::Differs("Bytecode mismatch".into()))
+}
+
+/// How a contract compiled from its verified sources compares to the deployed code.
+enum CodeCheck {
+    /// Nothing is deployed at the address (any more)
+    NoCode,
+    /// The code matches, this many bytes of it with the metadata excluded
+    Matches(usize),
+    Differs {
+        local_len: usize,
+        onchain_len: usize,
+        ranges: Vec<std::ops::Range<usize>>,
+    },
+}
+
+/// Compiles `contract` in the forge project at `root` with its verified settings and
+/// compares the result with the deployed runtime code `onchain`, given as hex.
+///
+/// With a `profile`, the contract is taken to live under `src/<profile>` as batch clones
+/// lay it out. The CBOR metadata trailer and immutable variables are ignored.
+async fn compare_with_deployed(
+    root: &Path,
+    profile: Option<&str>,
+    contract: &ContractSource,
+    onchain: &str,
+    forge_timeout: Duration,
+) -> Result<CodeCheck, Box<dyn std::error::Error>> {
+    let mut onchain = verify::decode_hex(onchain)?;
+    if onchain.is_empty() {
+        return Ok(CodeCheck::NoCode);
+    }
+    let name = contract.contract_name.as_deref().ok_or("The explorer reports no contract name")?;
+
+    info!("Compiling {} in {:?}", name, root);
+    foundry::run(&mut forge_build(root, profile, contract), forge_timeout).await?;
+
+    let artifact = verify::find_artifact(root, name)?;
+    info!("Using artifact {:?}", artifact.path);
+
+    let mut local = artifact.deployed_bytecode;
+    verify::mask(&mut local, &artifact.immutables);
+    verify::mask(&mut onchain, &artifact.immutables);
+    let local = verify::strip_metadata(&local);
+    let onchain = verify::strip_metadata(&onchain);
+
+    let ranges = verify::diff_ranges(local, onchain);
+    if ranges.is_empty() {
+        Ok(CodeCheck::Matches(local.len()))
+    } else {
+        Ok(CodeCheck::Differs {
+            local_len: local.len(),
+            onchain_len: onchain.len(),
+            ranges,
+        })
+    }
+}
+
+/// The default chain to use when the chain positional `first` is really the address,
+/// i.e. the chain was left out. Errors when there's no default to fall back on.
+fn omitted_chain(first: &str, ctx: &AppContext) -> Result<Option<String>, TokenFetchError> {
+    let is_target = address::looks_like_target(first) || ens::looks_like_name(first) || first.contains(',');
+    if get_chain_config(first).is_some() || !is_target {
+        return Ok(None);
+    }
+    match &ctx.user_config.default_chain {
+        Some(chain) => Ok(Some(chain.clone())),
+        None => Err(TokenFetchError::InvalidInput(format!(
+            "No chain given for {}: pass it first (e.g. `eth {}`), or set a default with `default_chain` in the config file or the TOKENFETCH_CHAIN environment variable",
+            first, first
+        ))),
+    }
+}
+
+/// The chain and address `subcommand` was pointed at, with the chain left out when there's
+/// a default, the address resolved from an ENS name or normalized, and the chain's API keys.
+async fn resolve_target(
+    mut chain: String,
+    mut address: Option<String>,
+    subcommand: &str,
+    rpc_flag: Option<&str>,
+    ctx: &AppContext,
+) -> Result<(&'static ChainConfig, String, KeyPool), Box<dyn std::error::Error>> {
+    if let Some(default_chain) = omitted_chain(&chain, ctx)? {
+        if let Some(extra) = &address {
+            return Err(TokenFetchError::InvalidInput(format!("Unexpected argument {}", extra)).into());
+        }
+        address = Some(std::mem::replace(&mut chain, default_chain));
+    }
+    let config = ctx.chain(&chain)?;
+    let address = address.ok_or_else(|| missing_arguments(subcommand, "<ADDRESS>"))?;
+    let address = if ens::looks_like_name(&address) {
+        resolve_ens_name(&address, config, rpc_flag, ctx).await?
+    } else {
+        address::normalize(&address, ctx.fetch.strict)?
+    };
+    let keys = load_api_keys(config, ctx)?;
+    Ok((config, address, keys))
+}
+
+/// Clap's usage error for positionals of `subcommand` that weren't given.
+fn missing_arguments(subcommand: &str, names: &str) -> TokenFetchError {
+    let mut command = Args::command();
+    command.build();
+    usage_error(
+        command.find_subcommand_mut(subcommand).expect("a known subcommand").clone().error(
+            clap::error::ErrorKind::MissingRequiredArgument,
+            format!("the following required arguments were not provided:\n  {}", names),
+        ),
+    )
+}
+
+/// A clap usage error as an invalid-input error, reported like any other failure and in
+/// the `--json` document too. The text keeps clap's usage line.
+fn usage_error(e: clap::Error) -> TokenFetchError {
+    let rendered = e.render().to_string();
+    let message = rendered.trim_end();
+    TokenFetchError::InvalidInput(message.strip_prefix("error: ").unwrap_or(message).to_string())
+}
+
+/// How many differing byte ranges `verify-match` lists before summarizing the rest.
+const MAX_REPORTED_RANGES: usize = 20;
+
+async fn clone_contract(mut args: CloneArgs, ctx: &AppContext) -> Result<(), Box<dyn std::error::Error>> {
+    // An explorer link names the chain and the address, shifting the path into the address slot
+    if let Some((host, address)) = address::parse_explorer_url(&args.chain) {
+        let config = chains().find(|config| config.explorer_host == host).ok_or_else(|| {
+            let hosts: Vec<&str> = chains().map(|config| config.explorer_host.as_ref()).collect();
+            TokenFetchError::InvalidInput(format!("Unknown explorer {}, supported explorers are: {}", host, hosts.join(", ")))
+        })?;
+        if args.path.is_some() {
+            return Err(TokenFetchError::InvalidInput("The explorer link already names the address, pass only a path after it".into()).into());
+        }
+        args.path = args.address.take();
+        args.address = Some(address);
+        args.chain = config.name.to_string();
+    }
+
+    // With a default chain the chain may be left out, shifting the other positionals
+    if let Some(default_chain) = omitted_chain(&args.chain, ctx)? {
+        args.path = args.address.take();
+        args.address = Some(std::mem::replace(&mut args.chain, default_chain));
+    }
+
+    // Get chain configuration
+    let config = ctx.chain(&args.chain)?;
+
+    // With --archive the clone is made in a temporary directory, removed once it's packed
+    let staging = match &args.archive {
+        Some(archive) => {
+            let format = archive::Format::of(archive)?;
+            if args.path.is_some() {
+                return Err(TokenFetchError::InvalidInput("--archive packs the clone into the archive, pass no path".into()).into());
+            }
+            let staging = tempfile::Builder::new().prefix("tokenfetch-").tempdir()?;
+            args.path = Some(staging.path().join(format.stem(archive)).display().to_string());
+            Some(staging)
+        }
+        None => None,
+    };
+
+    let mut name_from_contract = args.name_from_contract;
+    let mut implicit_path = false;
+    let (address_arg, target) = match (&args.address, &args.path) {
+        (address, Some(path)) => (address.as_deref(), path.clone()),
+        // With --addresses-file the address positional may be left out, shifting the path into its slot
+        (Some(path), None) if args.addresses_file.is_some() => (None, path.clone()),
+        (None, None) if args.addresses_file.is_none() => return Err(missing_arguments("clone", "<ADDRESS>").into()),
+        // Without a path, clone into <default_output_dir>/<ContractName>, or ./<ContractName>
+        (address, None) => {
+            name_from_contract = true;
+            implicit_path = true;
+            let dir = ctx.user_config.default_output_dir.as_deref().unwrap_or(Path::new("."));
+            (address.as_deref(), dir.display().to_string())
+        }
+    };
+
+    let mut addresses = collect_addresses(address_arg, args.addresses_file.as_deref(), ctx.fetch.strict)?;
+    if addresses.is_empty() {
+        return Err(TokenFetchError::InvalidInput("No contract address given".into()).into());
+    }
+    let rpc_url = ctx.rpc_url(args.rpc_url.as_deref(), config);
+    resolve_ens_names(&mut addresses, config, args.rpc_url.as_deref(), ctx).await?;
+    if args.check_onchain && ctx.fetch.offline {
+        return Err(TokenFetchError::InvalidInput("--check-onchain reads the deployed code, which --offline doesn't".into()).into());
+    }
+    if args.install_solc && ctx.fetch.offline {
+        return Err(TokenFetchError::InvalidInput("--install-solc downloads the compiler, which --offline doesn't".into()).into());
+    }
+    let onchain_rpc_url = match args.check_onchain {
+        true => Some(ctx.required_rpc_url(args.rpc_url.as_deref(), config, "--check-onchain")?),
+        false => None,
+    };
+    if args.hardhat() {
+        let forge_only = [
+            (args.merge, "--merge"),
+            (args.tests, "--tests"),
+            (args.gen_compliance_tests, "--gen-compliance-tests"),
+            (args.install_solc, "--install-solc"),
+            (args.check_onchain, "--check-onchain"),
+            (args.link_deps.is_some(), "--link-deps"),
+            (args.src_dir.is_some(), "--src-dir"),
+            (args.follow_proxy, "--follow-proxy"),
+        ];
+        if let Some((_, flag)) = forge_only.iter().find(|(given, _)| *given) {
+            return Err(TokenFetchError::InvalidInput(format!("{} works on forge projects and can't be combined with --format hardhat", flag)).into());
+        }
+        if addresses.len() > 1 {
+            return Err(TokenFetchError::InvalidInput("--format hardhat clones a single contract per project".into()).into());
+        }
+    }
+    if addresses.len() > 1 && args.follow_proxy {
+        return Err(TokenFetchError::InvalidInput("--follow-proxy follows a single proxy, add the implementations to the batch instead".into()).into());
+    }
+    if addresses.len() > 1 && args.from_file.is_some() {
+        return Err(TokenFetchError::InvalidInput("--from-file replays a single response and can't be combined with several addresses".into()).into());
+    }
+
+    // Get API key from environment, unless replaying a saved response
+    let keys = match args.from_file {
+        Some(_) => None,
+        None => Some(load_api_keys(config, ctx)?),
+    };
+    resolve_tx_hashes(&mut addresses, rpc_url, keys.as_ref(), config, ctx).await?;
+
+    // Printed and done, before any directory is created
+    if args.stdout {
+        if args.path.is_some() || addresses.len() > 1 {
+            return Err(TokenFetchError::InvalidInput("--stdout prints the source of a single contract, pass one address and no path".into()).into());
+        }
+        let throttle = ctx.throttle(1, config);
+        let loaded = load_contract(&args, ctx, &throttle, config, keys.as_ref(), &addresses[0]).await?;
+        return print_source(&args, ContractSource::from_entry(loaded.entry)?, &addresses[0]);
+    }
+
+    // With --name-from-contract the path is a parent directory, and the contract has to
+    // be fetched before the project directory can be named after it
+    let mut prefetched = None;
+    // An archived clone is rooted at a folder named after its contract, or after the
+    // archive for a batch
+    let name_from_contract = name_from_contract || (staging.is_some() && addresses.len() == 1);
+    let path = if name_from_contract {
+        if addresses.len() > 1 {
+            return Err(TokenFetchError::InvalidInput("Naming the project after the contract (no path given, or --name-from-contract) needs a single address; pass a path for batch clones".into()).into());
+        }
+        let throttle = ctx.throttle(1, config);
+        let loaded = load_contract(&args, ctx, &throttle, config, keys.as_ref(), &addresses[0]).await?;
+        if loaded.entry.source_code.is_empty() {
+            return Err(TokenFetchError::NotVerified.into());
+        }
+        let contract_name = non_empty(&loaded.entry.contract_name).filter(|name| !safe_name(name).is_empty()).map(str::to_string);
+        prefetched = Some(loaded);
+
+        ensure_not_a_file(Path::new(&target))?;
+        std::fs::create_dir_all(&target)?;
+        let path = match &contract_name {
+            Some(name) => Path::new(&target).join(safe_name(name)),
+            None => Path::new(&target).join(addresses[0].to_lowercase()),
+        };
+        match &contract_name {
+            // A path picked for the user shouldn't fail on a clash, e.g. with another
+            // deployment of the same contract, so add the address to tell them apart
+            Some(name) if implicit_path && path.exists() && !args.force && !args.merge => {
+                let renamed = Path::new(&target).join(namespace(name, &addresses[0]));
+                info!("{} already exists, cloning into {} instead", path.display(), renamed.display());
+                renamed
+            }
+            _ => path,
+        }
+    } else {
+        PathBuf::from(&target)
+    };
+    summary::target(&config.name, args.archive.as_deref().unwrap_or(&path));
+    let target = path.display().to_string();
+    let target = target.as_str();
+    // Where the clone ends up, as told to the user
+    let destination = args.archive.as_deref().unwrap_or(&path).display().to_string();
+
+    // Checked before anything is written, so a missing entry doesn't leave a partial clone
+    if ctx.fetch.offline && args.from_file.is_none() && !args.keep_going {
+        if let Some(address) = addresses.iter().find(|address| !cache::contains(config.chain_id, address, ctx.fetch.max_age)) {
+            return Err(TokenFetchError::NotCached(format!("The source of {} on {}", address, config.name)).into());
+        }
+    }
+    let readme_template = match &args.readme_template {
+        Some(path) => readme::read_template(path)?,
+        None => readme::DEFAULT_TEMPLATE.to_string(),
+    };
+    let (existed, preserved) = prepare_target(&args, &path)?;
+    // Looked for before forge init writes its own, which is replaced
+    let had_readme = readme::exists(&path);
+
+    info!("Chain id: {}", config.chain_id);
+    info!("Cloning contract at address {} to path {}", addresses.join(", "), destination);
+
+    // Initialize forge project, unless merging into one or scaffolding a Hardhat one
+    if !args.merge && !args.hardhat() {
+        let mut forge = tokio::process::Command::new("forge");
+        forge.arg("init").arg(target).arg("--no-commit");
+        if ctx.fetch.offline {
+            // Skips cloning forge-std
+            forge.arg("--offline");
+        }
+        if existed {
+            forge.arg("--force");
+        }
+        foundry::run(&mut forge, args.forge_timeout).await?;
+        info!("Initialized forge project");
+        preserved.restore(&path)?;
+    }
+
+    let keep_config = preserved.keeps_config();
+    let configured = foundry::configured_src(&path);
+    let src_dir = match (&args.src_dir, configured) {
+        _ if args.hardhat() => hardhat::SOURCES_DIR.to_string(),
+        (Some(dir), configured) => {
+            let dir = dir.trim_end_matches('/').to_string();
+            if keep_config && configured.as_deref().unwrap_or("src") != dir {
+                warn!("The existing foundry.toml builds {}, forge won't find the sources in {} until it points there", configured.as_deref().unwrap_or("src"), dir);
+            } else if !keep_config && configured.as_deref() != Some(&dir) {
+                foundry::set_src(&path, &dir)?;
+            }
+            dir
+        }
+        (None, Some(configured)) => configured,
+        (None, None) => "src".to_string(),
+    };
+    if src_dir != "src" {
+        info!("Writing the sources to {}", path.join(&src_dir).display());
+    }
+    let project = Project {
+        root: path.clone(),
+        keep_config,
+        src_dir,
+    };
+
+    if !args.keep_template && !args.merge && !args.hardhat() {
+        foundry::remove_template(&path, &project.src_dir)?;
+    }
+
+    if addresses.len() == 1 {
+        let throttle = ctx.throttle(1, config);
+        let loaded = match prefetched {
+            Some(loaded) => loaded,
+            None => load_contract(&args, ctx, &throttle, config, keys.as_ref(), &addresses[0]).await?,
+        };
+        let ((written, contract), followed) = match (followed_implementation(&args, &loaded.entry, &addresses[0]), keys.as_ref()) {
+            (Some(implementation), Some(keys)) => {
+                info!("{} is a proxy, cloning its implementation {} too", addresses[0], implementation);
+                let write = {
+                    let (args, project, address) = (args.clone(), project.clone(), addresses[0].clone());
+                    move || write_contract(&args, config, &loaded, &address, &project, None).map_err(TokenFetchError::from)
+                };
+                let (fetched, proxy) = fetch_while_writing(load_contract(&args, ctx, &throttle, config, Some(keys), &implementation), write).await?;
+                let (entry, source) = write_contract(&args, config, &fetched, &implementation, &project, Some(&implementation))?;
+                (proxy, Some((implementation, entry, source)))
+            }
+            (Some(implementation), None) => {
+                warn!("Not following {} to its implementation {}, which --from-file can't fetch", addresses[0], implementation);
+                (write_contract(&args, config, &loaded, &addresses[0], &project, None)?, None)
+            }
+            (None, _) => (write_contract(&args, config, &loaded, &addresses[0], &project, None)?, None),
+        };
+        let name = written.name.clone().unwrap_or_default();
+        let implementation = followed.as_ref().map(|(_, _, source)| &source.entry);
+        let standard = classify(ctx, rpc_url, &addresses[0], &contract.entry, implementation).await;
+        summary::standard(&addresses[0], standard.to_json());
+        if args.gen_compliance_tests {
+            write_compliance_tests(&args, config, &project, &contract, &addresses[0], &standard, None)?;
+        }
+        finish_project(&project, args.hardhat())?;
+        if args.env_example {
+            write_env_example(&args, config, &project)?;
+        }
+        if args.install_solc {
+            install_solc(&project, &contract, None, args.forge_timeout).await;
+        }
+        if let Some(rpc_url) = onchain_rpc_url {
+            check_onchain(ctx, rpc_url, &project, &addresses[0], &contract, None, args.forge_timeout).await;
+        }
+        if let (false, Some(keys)) = (args.no_token_info, keys.as_ref()) {
+            write_token_info(ctx, keys, &throttle, config, &project.root.join("token.json"), &addresses[0], &contract.entry).await;
+        }
+        let mut entries = vec![written];
+        let mut described = vec![(addresses[0].as_str(), &contract)];
+        if let Some((address, entry, source)) = &followed {
+            entries.push(entry.clone());
+            described.push((address, source));
+        }
+        let manifest = manifest::write(&project.root, &config.name, config.chain_id, &config.api_url, entries, manifest::fetched_at(args.archive.is_some()))?;
+        if !had_readme {
+            write_readme(&project, &readme_template, &manifest, config, &described)?;
+        }
+        if let Some(archive) = &args.archive {
+            archive::pack(&project.root, archive)?;
+        }
+        let about = match standard.standard {
+            Some(_) => format!("{}, {}", addresses[0], standard.describe()),
+            None => addresses[0].clone(),
+        };
+        match name.as_str() {
+            "" => info!("Contract cloning completed successfully!"),
+            name => info!("Cloned {} ({}) to {} successfully!", name, about, destination),
+        }
+        if let Some((address, entry, _)) = &followed {
+            info!("Its implementation {} is in {}", address, entry.source_dir);
+        }
+        return Ok(());
+    }
+
+    // Batch mode: every contract shares the one forge project, namespaced under src/.
+    // Fetches run concurrently, files are written one contract at a time as they arrive.
+    let started = std::time::Instant::now();
+    let concurrency = args
+        .concurrency
+        .or(ctx.user_config.concurrency)
+        .unwrap_or(ctx.fetch.tier.unwrap_or(Tier::Free).concurrency());
+    let throttle = ctx.throttle(concurrency as usize, config);
+    let mut fetches = stream::iter(addresses.iter().enumerate())
+        .map(|(idx, address)| {
+            let (args, throttle, keys) = (&args, &throttle, keys.as_ref());
+            async move { (idx, address, load_contract(args, ctx, throttle, config, keys, address).await) }
+        })
+        .buffer_unordered(addresses.len());
+
+    let overall = progress::bar(addresses.len(), "Cloning", "contracts");
+    let mut outcomes = Vec::new();
+    let mut written = Vec::new();
+    while let Some((idx, address, fetched)) = fetches.next().await {
+        let outcome = fetched.and_then(|loaded| {
+            let (entry, contract) = write_contract(&args, config, &loaded, address, &project, Some(address))?;
+            let name = entry.name.clone().unwrap_or_default();
+            written.push((address, namespace(&name, address), entry, contract));
+            Ok(name)
+        });
+        info!("[{}/{}] {} done", outcomes.len() + 1, addresses.len(), address);
+        overall.inc();
+
+        if let Err(e) = &outcome {
+            error!("Failed to clone {}: {}", address, e);
+            summary::contract_failed(address, e.as_ref());
+            if args.fail_fast {
+                return Err(format!("Aborting batch after failure on {}: {}", address, e).into());
+            }
+        }
+        outcomes.push((idx, address, outcome));
+    }
+    outcomes.sort_by_key(|(idx, _, _)| *idx);
+
+    let rows: Vec<(&str, Result<&str, String>)> = outcomes
+        .iter()
+        .map(|(_, address, outcome)| (address.as_str(), outcome.as_deref().map_err(|e| e.to_string())))
+        .collect();
+    let failed = rows.iter().filter(|(_, outcome)| outcome.is_err()).count();
+    info!("Batch summary: {} succeeded, {} failed", rows.len() - failed, failed);
+    for line in batch_table(&rows) {
+        info!("  {}", line);
+    }
+    info!(
+        "Processed {} contracts in {:.1}s with {} explorer requests",
+        outcomes.len(),
+        started.elapsed().as_secs_f64(),
+        throttle.requests()
+    );
+    if let Some(keys) = keys.as_ref().filter(|keys| keys.len() > 1) {
+        for (idx, served) in keys.served().iter().enumerate() {
+            info!("  API key {}: {} requests", idx + 1, served);
+        }
+    }
+
+    finish_project(&project, args.hardhat())?;
+    if args.env_example {
+        write_env_example(&args, config, &project)?;
+    }
+    for (address, profile, _, contract) in &written {
+        let standard = classify(ctx, rpc_url, address, &contract.entry, None).await;
+        if standard.standard.is_some() {
+            info!("{} is {}", address, standard.describe());
+        }
+        summary::standard(address, standard.to_json());
+        if args.gen_compliance_tests {
+            write_compliance_tests(&args, config, &project, contract, address, &standard, Some(profile))?;
+        }
+        if args.install_solc {
+            install_solc(&project, contract, Some(profile), args.forge_timeout).await;
+        }
+        if let Some(rpc_url) = onchain_rpc_url {
+            check_onchain(ctx, rpc_url, &project, address, contract, Some(profile), args.forge_timeout).await;
+        }
+        if let (false, Some(keys)) = (args.no_token_info, keys.as_ref()) {
+            let path = project.root.join("token").join(format!("{}.json", profile));
+            write_token_info(ctx, keys, &throttle, config, &path, address, &contract.entry).await;
+        }
+    }
+    let entries = written.iter().map(|(_, _, entry, _)| entry.clone()).collect();
+    let manifest = manifest::write(&project.root, &config.name, config.chain_id, &config.api_url, entries, manifest::fetched_at(args.archive.is_some()))?;
+    if !had_readme {
+        let contracts: Vec<(&str, &ContractSource)> = written.iter().map(|(address, _, _, contract)| (address.as_str(), contract)).collect();
+        write_readme(&project, &readme_template, &manifest, config, &contracts)?;
+    }
+    if let (Some(archive), false) = (&args.archive, failed > 0 && !args.keep_going) {
+        archive::pack(&project.root, archive)?;
+    }
+    if failed > 0 {
+        let message = format!("{} of {} contracts failed to clone", failed, outcomes.len());
+        if !args.keep_going {
+            return Err(message.into());
+        }
+        warn!("{}", message);
+        return Ok(());
+    }
+    info!("Contract cloning completed successfully!");
+    Ok(())
+}
+
+/// Writes the project's README.md from `template`, with a section for each contract in
+/// `responses`, skipped when `--merge` or `--force` found one there already.
+fn write_readme(
+    project: &Project,
+    template: &str,
+    manifest: &manifest::Manifest,
+    config: &ChainConfig,
+    contracts: &[(&str, &ContractSource)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let sections: Vec<_> = contracts
+        .iter()
+        .filter_map(|(address, source)| {
+            let contract = manifest.contracts.iter().find(|contract| contract.address.eq_ignore_ascii_case(address))?;
+            let meta = contract_meta(&config.name, address, Some(source));
+            Some(readme::values(manifest, contract, &meta, &config.explorer_host))
+        })
+        .collect();
+    Ok(readme::write(&project.root, template, &sections)?)
+}
+
+/// The batch summary as aligned rows of address, contract name and result, failures
+/// with their reason.
+fn batch_table(rows: &[(&str, Result<&str, String>)]) -> Vec<String> {
+    let name = |outcome: &Result<&str, String>| match outcome {
+        Ok(name) if !name.is_empty() => name.to_string(),
+        _ => "-".to_string(),
+    };
+    let address_width = rows.iter().map(|(address, _)| address.len()).max().unwrap_or(0).max("ADDRESS".len());
+    let name_width = rows.iter().map(|(_, outcome)| name(outcome).len()).max().unwrap_or(0).max("CONTRACT".len());
+
+    let mut lines = vec![format!("{:address_width$}  {:name_width$}  RESULT", "ADDRESS", "CONTRACT")];
+    for (address, outcome) in rows {
+        let result = match outcome {
+            Ok(_) => "ok".to_string(),
+            Err(reason) => format!("failed: {}", reason),
+        };
+        lines.push(format!("{:address_width$}  {:name_width$}  {}", address, name(outcome), result));
+    }
+    lines
+}
+
+/// Warns when a freshly cloned contract doesn't reflect the code deployed at `address`
+/// now, e.g. because it self-destructed or the deployment doesn't match the verified
+/// source. This is only advisory, so failing to check is a warning too.
+async fn check_onchain(
+    ctx: &AppContext,
+    rpc_url: &str,
+    project: &Project,
+    address: &str,
+    contract: &ContractSource,
+    profile: Option<&str>,
+    forge_timeout: Duration,
+) {
+    let checked = async {
+        let onchain = rpc::get_code(ctx.fetcher.client.reqwest(), rpc_url, address).await?;
+        compare_with_deployed(&project.root, profile, contract, &onchain, forge_timeout).await
+    }
+    .await;
+
+    match checked {
+        Ok(CodeCheck::Matches(_)) => info!("{} matches the code deployed at its address", address),
+        Ok(CodeCheck::NoCode) => warn!(
+            "No code is deployed at {} any more, the contract may have self-destructed; the cloned source doesn't reflect on-chain state",
+            address
+        ),
+        Ok(CodeCheck::Differs { .. }) => warn!(
+            "{} doesn't compile to the code deployed at its address, it may have been reinitialized; run verify-match for details",
+            address
+        ),
+        Err(e) => warn!("Couldn't check {} against its deployed code: {}", address, e),
+    }
+}
+
+/// Classifies the contract at `address` as a token standard by its ABI, or with an
+/// `rpc_url` also by asking it over ERC-165, which catches proxies whose own ABI doesn't
+/// have the token's functions. A failed ERC-165 call only leaves the ABI's answer.
+///
+/// A proxy followed to its `implementation` is classified by the implementation's ABI,
+/// since that's the interface callers get through it.
+async fn classify(
+    ctx: &AppContext,
+    rpc_url: Option<&str>,
+    address: &str,
+    entry: &SourceCodeResult,
+    implementation: Option<&SourceCodeResult>,
+) -> standard::Classification {
+    let abi = extract_abi(implementation.unwrap_or(entry)).unwrap_or_default();
+    let classification = standard::from_abi(&abi);
+    let Some(rpc_url) = rpc_url.filter(|_| entry.is_proxy() || standard::has_supports_interface(&abi)) else {
+        return classification;
+    };
+
+    let supports = |id: &'static str| async move {
+        match rpc::supports_interface(ctx.fetcher.client.reqwest(), rpc_url, address, id).await {
+            Ok(supported) => supported,
+            Err(e) => {
+                debug!("supportsInterface({}) failed on {}: {}", id, address, e);
+                false
+            }
+        }
+    };
+    for (standard, id) in standard::standard_ids() {
+        if supports(id).await {
+            let mut extensions = Vec::new();
+            for (extension, id) in standard::extension_ids(standard) {
+                if supports(id).await {
+                    extensions.push(extension);
+                }
+            }
+            return classification.with_supported(standard, extensions);
+        }
+    }
+    classification
+}
+
+/// Writes what the explorer knows of the token at `address` to `path`. Contracts that
+/// aren't tokens are skipped quietly, and like `check_onchain` this is advisory, so
+/// failing to look the token up is a warning.
+async fn write_token_info(
+    ctx: &AppContext,
+    keys: &KeyPool,
+    throttle: &Throttle,
+    config: &ChainConfig,
+    path: &Path,
+    address: &str,
+    entry: &SourceCodeResult,
+) {
+    if ctx.fetch.offline {
+        debug!("Not writing token info for {} with --offline", address);
+        return;
+    }
+    // A proxy's own ABI doesn't have the token's functions
+    if !entry.is_proxy() && !extract_abi(entry).is_ok_and(|abi| token::looks_like_token(&abi)) {
+        debug!("{} doesn't look like a token, not writing token info", address);
+        return;
+    }
+
+    let written = async {
+        let info = token_info(ctx, keys, throttle, config, address).await?;
+        if info.is_empty() {
+            debug!("The explorer has no token info for {}", address);
+            return Ok(false);
+        }
+        std::fs::create_dir_all(path.parent().expect("token info path has a parent"))?;
+        std::fs::write(path, serde_json::to_string_pretty(&info.to_json(address))? + "\n")?;
+        summary::file_written(path);
+        Ok::<_, Box<dyn std::error::Error>>(true)
+    }
+    .await;
+
+    match written {
+        Ok(true) => info!("Wrote token info to {:?}", path),
+        Ok(false) => {}
+        Err(e) => warn!("Skipping token info for {}: {}", address, e),
+    }
+}
+
+/// Looks up the token at `address` with the explorer's `tokeninfo` endpoint, or where it
+/// doesn't have one (it's also limited to paid plans on some), with `tokensupply` and
+/// calls to the token's `name()`, `symbol()` and `decimals()` getters.
+async fn token_info(
+    ctx: &AppContext,
+    keys: &KeyPool,
+    throttle: &Throttle,
+    config: &ChainConfig,
+    address: &str,
+) -> Result<token::TokenInfo, Box<dyn std::error::Error>> {
+    let explorer = |url_for: Box<dyn Fn(&str) -> String>| async move {
+        ctx.fetcher.query(throttle, keys, url_for).await
+    };
+
+    let refused = &ctx.tokeninfo_refused;
+    if !refused.load(Ordering::Relaxed) {
+        match explorer(Box::new(|key| build_token_info_url(config, address, key))).await {
+            Ok(json) if json["result"][0].is_object() => return Ok(token::TokenInfo::from_tokeninfo(&json["result"][0])),
+            Ok(_) => debug!("No tokeninfo result for {}", address),
+            // Etherscan keeps the endpoint for API Pro keys, and says so the same way every time
+            Err(TokenFetchError::ExplorerApi { message }) if message.contains("API Pro") => {
+                debug!("tokeninfo needs an API Pro key, calling the tokens instead from now on");
+                refused.store(true, Ordering::Relaxed);
+            }
+            Err(e) => debug!("No tokeninfo endpoint for {} ({}), calling the token instead", address, e),
+        }
+    }
+
+    let total_supply = match explorer(Box::new(|key| build_token_supply_url(config, address, key))).await {
+        Ok(json) => json["result"].as_str().map(str::to_string),
+        Err(e) => {
+            debug!("No token supply for {}: {}", address, e);
+            None
+        }
+    };
+    let mut results = Vec::new();
+    for data in [token::NAME, token::SYMBOL, token::DECIMALS] {
+        let json = explorer(Box::new(move |key| build_call_url(config, address, data, key))).await?;
+        // A contract without the getter reverts, which the proxy reports as an error
+        results.push(proxy_result(&json).ok().and_then(|result| result.as_str()).map(str::to_string));
+    }
+    let [name, symbol, decimals] = results.try_into().expect("three getters were called");
+    let name = name.as_deref().and_then(token::decode_string);
+    let decimals = decimals.as_deref().and_then(token::decode_decimals);
+
+    Ok(token::TokenInfo {
+        // Only a contract with the getters is a token, the supply endpoint answers 0 for
+        // anything else
+        total_supply: total_supply.filter(|_| name.is_some() || decimals.is_some()),
+        name,
+        symbol: symbol.as_deref().and_then(token::decode_string),
+        decimals,
+        token_type: None,
+    })
+}
+
+/// `forge build` of `contract` with its verified settings. Batch clones keep each
+/// contract under <src>/<profile>, `src` being the directory foundry.toml builds.
+fn forge_build(root: &Path, profile: Option<&str>, contract: &ContractSource) -> tokio::process::Command {
+    let base = foundry::configured_src(root).unwrap_or_else(|| "src".to_string());
+    let src_dir = match profile {
+        Some(profile) => format!("{}/{}", base, profile),
+        None => base,
+    };
+    let mut forge = tokio::process::Command::new("forge");
+    forge
+        .arg("build")
+        .arg("--root")
+        .arg(root)
+        .args(foundry::build_args(&src_dir, contract.compiler_version.as_deref(), contract.settings.as_ref()));
+    if let Some(profile) = profile {
+        forge.env("FOUNDRY_PROFILE", profile);
+    }
+    forge
+}
+
+/// Has forge download the contract's solc now rather than on the first build, by
+/// building it once. Like `check_onchain` this is advisory, the clone is already done.
+async fn install_solc(project: &Project, contract: &ContractSource, profile: Option<&str>, forge_timeout: Duration) {
+    let installed = async {
+        // The version pinned from the pragmas when the explorer's isn't a release
+        let version = contract.compiler_version.as_deref().and_then(foundry::solc_version).or_else(|| {
+            lint::solc_from_pragmas(&contract.sources).ok().flatten().map(|version| version.to_string())
+        });
+        let version = match version {
+            Some(version) if !foundry::solc_installed(&version) => version,
+            _ => return Ok(None),
+        };
+        info!("Installing solc {} with a first forge build", version);
+        foundry::run(&mut forge_build(&project.root, profile, contract), forge_timeout).await?;
+        Ok::<_, Box<dyn std::error::Error>>(Some(version))
+    }
+    .await;
+
+    match installed {
+        Ok(Some(version)) => info!("Installed solc {}", version),
+        Ok(None) => {}
+        Err(e) => warn!("Couldn't install solc: {}", e),
+    }
+}
+
+/// The forge project contracts are cloned into.
+#[derive(Clone)]
+struct Project {
+    root: PathBuf,
+    /// The user's `foundry.toml` predates this run and must not be modified
+    keep_config: bool,
+    /// Where the sources go, relative to `root`: `--src-dir`, or what `foundry.toml` sets
+    src_dir: String,
+}
+
+/// The environment variable the generated fork tests read their RPC endpoint from, named
+/// after the chain whatever alias it was given by, e.g. `ETH_RPC_URL`.
+fn fork_rpc_env(config: &ChainConfig) -> String {
+    format!("{}_RPC_URL", config.name.to_uppercase().replace('-', "_"))
+}
+
+/// Writes `.env.example` with what the project needs from the environment, keeping the
+/// real `.env` out of git.
+fn write_env_example(args: &CloneArgs, config: &ChainConfig, project: &Project) -> Result<(), Box<dyn std::error::Error>> {
+    let mut vars = vec![envfile::Variable {
+        name: config.api_key_env.to_string(),
+        comment: format!("API key of the {} explorer, for `tokenfetch refresh` and `forge verify-contract`", config.explorer_host),
+        placeholder: "your-api-key".to_string(),
+    }];
+    let mut uses = Vec::new();
+    if args.tests || args.gen_compliance_tests {
+        uses.push("the fork tests in test/");
+    }
+    if args.check_onchain || args.rpc_url.is_some() {
+        uses.push("RPC checks such as --check-onchain (pass it as --rpc-url)");
+    }
+    if !uses.is_empty() {
+        vars.push(envfile::Variable {
+            name: fork_rpc_env(config),
+            comment: format!("JSON-RPC endpoint of {}, for {}", config.name, uses.join(" and ")),
+            placeholder: "https://your-rpc-endpoint".to_string(),
+        });
+    }
+    let header = format!(
+        "The environment this project needs, from a clone of {} (chain id {}).\nCopy it to .env and fill in the values, forge and tokenfetch both read it.",
+        config.name, config.chain_id
+    );
+    envfile::write_example(&project.root, &header, &vars)?;
+    foundry::ensure_gitignore(&project.root, &[".env"])?;
+    Ok(())
+}
+
+/// Final touches once every contract has been written.
+fn finish_project(project: &Project, hardhat: bool) -> Result<(), Box<dyn std::error::Error>> {
+    match hardhat {
+        true => foundry::ensure_gitignore(&project.root, hardhat::GITIGNORE)?,
+        false => foundry::ensure_gitignore(&project.root, &["cache/", "out/"])?,
+    }
+    Ok(())
+}
+
+/// Gathers the addresses to clone from the positional argument and `--addresses-file`,
+/// dropping duplicates. A `-` in either place reads the list from stdin.
+fn collect_addresses(
+    positional: Option<&str>,
+    list_file: Option<&str>,
+    strict: bool,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut addresses = Vec::new();
+
+    if let Some(positional) = positional {
+        if positional.trim() == "-" {
+            addresses.extend(address::parse_address_list(std::io::stdin().lock(), "<stdin>")?);
+        } else {
+            addresses.extend(
+                positional
+                    .split(',')
+                    .map(|a| a.trim().to_string())
+                    .filter(|a| !a.is_empty()),
+            );
+        }
+    }
+
+    match list_file {
+        Some("-") if positional.is_some_and(|p| p.trim() == "-") => {}
+        Some("-") => addresses.extend(address::parse_address_list(std::io::stdin().lock(), "<stdin>")?),
+        Some(file) => {
+            let reader = std::fs::File::open(file)
+                .map_err(|e| format!("Failed to read address list {}: {}", file, e))?;
+            addresses.extend(address::parse_address_list(std::io::BufReader::new(reader), file)?);
+        }
+        None => {}
+    }
+
+    // ENS names are resolved later, see resolve_ens_names
+    let mut addresses = addresses
+        .iter()
+        .map(|address| {
+            if ens::looks_like_name(address) {
+                Ok(address.clone())
+            } else {
+                address::normalize(address, strict)
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut seen = std::collections::HashSet::new();
+    addresses.retain(|address| {
+        let fresh = seen.insert(address.to_lowercase());
+        if !fresh {
+            info!("Skipping duplicate address {}", address);
+        }
+        fresh
+    });
+
+    Ok(addresses)
+}
+
+/// Replaces ENS names in `addresses` with the addresses they resolve to, through
+/// `rpc_url` or else a public mainnet endpoint.
+async fn resolve_ens_names(
+    addresses: &mut [String],
+    config: &ChainConfig,
+    rpc_flag: Option<&str>,
+    ctx: &AppContext,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for entry in addresses.iter_mut() {
+        if ens::looks_like_name(entry) {
+            *entry = resolve_ens_name(entry, config, rpc_flag, ctx).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn resolve_ens_name(
+    name: &str,
+    config: &ChainConfig,
+    rpc_flag: Option<&str>,
+    ctx: &AppContext,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if config.chain_id != 1 {
+        return Err(TokenFetchError::InvalidInput(format!(
+            "ENS names are only resolved on eth, pass the address of {} on {} instead",
+            name, config.name
+        ))
+        .into());
+    }
+    let rpc_url = ctx.required_rpc_url(rpc_flag, config, &format!("Resolving {}", name))?;
+    Ok(ens::resolve(ctx.fetcher.client.reqwest(), rpc_url, name).await?)
+}
+
+/// Replaces deployment transaction hashes in `addresses` with the contracts they created,
+/// looking up their receipts through `rpc_url` when given and otherwise the explorer.
+async fn resolve_tx_hashes(
+    addresses: &mut [String],
+    rpc_url: Option<&str>,
+    keys: Option<&KeyPool>,
+    config: &ChainConfig,
+    ctx: &AppContext,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !addresses.iter().any(|a| address::looks_like_tx_hash(a)) {
+        return Ok(());
+    }
+    let throttle = ctx.throttle(1, config);
+    for entry in addresses.iter_mut().filter(|a| address::looks_like_tx_hash(a)) {
+        *entry = match (rpc_url, keys) {
+            (Some(rpc_url), _) => rpc::deployed_address(ctx.fetcher.client.reqwest(), rpc_url, entry).await?,
+            (None, Some(keys)) => {
+                let json = ctx.fetcher.query(&throttle, keys, |key| build_receipt_url(config, entry, key)).await?;
+                rpc::created_contract(entry, proxy_result(&json)?)?
+            }
+            // Replaying a saved response, so there's no API key to ask the explorer with
+            (None, None) => {
+                return Err(TokenFetchError::InvalidInput(
+                    "Resolving a transaction hash with --from-file requires --rpc-url".into(),
+                )
+                .into())
+            }
+        };
+    }
+
+    Ok(())
+}
+
+/// Narrows the sources of `contract` down to what `--include-deps-only` or
+/// `--contract-name` asks for, and normalizes their whitespace as requested.
+fn select_sources(options: &manifest::SourceOptions, contract: &mut ContractSource, address: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if options.include_deps_only {
+        contract.sources.retain(|key, _| deps::is_dependency(key));
+        if contract.sources.is_empty() {
+            warn!("{} has no dependency files to write", address);
+        } else {
+            info!("Writing {} dependency files only", contract.sources.len());
+        }
+    }
+    if let Some(selected) = &options.contract_name {
+        let declared = imports::declared_contracts(&contract.sources);
+        let Some(file) = declared.get(selected) else {
+            let available: Vec<&str> = declared.keys().map(String::as_str).collect();
+            return Err(TokenFetchError::InvalidInput(format!(
+                "No contract named {} in the sources of {}, available contracts are: {}",
+                selected,
+                address,
+                available.join(", ")
+            ))
+            .into());
+        };
+        let remappings = imports::remappings(contract.settings.as_ref());
+        let included = imports::closure(file, &contract.sources, &remappings);
+        contract.sources.retain(|key, _| included.contains(key));
+        info!("Writing {} and the {} files it imports", file, included.len() - 1);
+    }
+
+    if options.normalize_eol || options.trim_trailing_whitespace {
+        for source in contract.sources.values_mut() {
+            if let Some(content) = source["content"].as_str() {
+                source["content"] = normalize_whitespace(content, options.normalize_eol, options.trim_trailing_whitespace).into();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Drops the dependency packages from `sources` for `--link-deps`, remapping each in the
+/// project's remappings.txt onto where `dir` installs it.
+fn link_dependencies(sources: &mut serde_json::Map<String, serde_json::Value>, root: &Path, dir: deps::LinkDir) -> Result<(), Box<dyn std::error::Error>> {
+    let mut packages: std::collections::BTreeMap<String, (deps::Package, usize)> = std::collections::BTreeMap::new();
+    sources.retain(|key, _| match deps::package(key) {
+        Some(package) => {
+            packages.entry(package.prefix.clone()).or_insert((package, 0)).1 += 1;
+            false
+        }
+        None => true,
+    });
+    let mut versions: std::collections::BTreeMap<&str, std::collections::BTreeSet<&str>> = std::collections::BTreeMap::new();
+    for (package, _) in packages.values() {
+        versions.entry(&package.name).or_default().extend(package.version.as_deref());
+    }
+    for (name, versions) in versions.iter().filter(|(_, versions)| versions.len() > 1) {
+        let versions: Vec<&str> = versions.iter().copied().collect();
+        warn!("The sources use {} at versions {}, which all link to the one installed", name, versions.join(", "));
+    }
+
+    let mut remappings = Vec::new();
+    for (package, files) in packages.values() {
+        let version = package.version.as_deref().map(|version| format!(" {}", version)).unwrap_or_default();
+        match deps::remapping(package, dir) {
+            Some(remapping) => {
+                info!("Linking {}{} ({} files) as {}, install it there", package.name, version, files, remapping);
+                remappings.push(remapping);
+            }
+            None => info!("Linking {}{} ({} files) where it's imported from, install it at {}", package.name, version, files, package.prefix),
+        }
+    }
+    foundry::add_remappings(root, &remappings)?;
+    Ok(())
+}
+
+/// Prints the source of the contract at `address` for `--stdout`, without touching the disk.
+fn print_source(args: &CloneArgs, mut contract: ContractSource, address: &str) -> Result<(), Box<dyn std::error::Error>> {
+    select_sources(&args.source_options(), &mut contract, address)?;
+    let source = match contract.sources.values().next() {
+        Some(only) if contract.sources.len() == 1 => only["content"].as_str().unwrap_or_default().to_string(),
+        _ if args.flatten => flatten::flatten(&contract.sources, &imports::remappings(contract.settings.as_ref())),
+        _ => flatten::concatenate(&contract.sources),
+    };
+    let mut stdout = std::io::stdout().lock();
+    match stdout.write_all(source.as_bytes()).and_then(|()| stdout.flush()) {
+        // Whatever reads the source, such as `grep -m1` or `head`, may stop early
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+/// Fails when `path`, or whichever of its parents is the nearest that exists, is a file
+/// rather than a directory, which creating the directory would fail on less clearly.
+fn ensure_not_a_file(path: &Path) -> Result<(), TokenFetchError> {
+    let existing = path.ancestors().filter(|ancestor| !ancestor.as_os_str().is_empty()).find(|ancestor| ancestor.exists());
+    match existing {
+        Some(file) if !file.is_dir() => Err(TokenFetchError::InvalidInput(match file == path {
+            true => format!("Target path {} is a file, expected a directory (or new path)", path.display()),
+            false => format!("Target path {} is inside {}, which is a file, expected a directory (or new path)", path.display(), file.display()),
+        })),
+        _ => Ok(()),
+    }
+}
+
+/// Gets the project directory `path` ready for `forge init`, creating it along with any
+/// missing parents. A directory that's already there, unless empty or holding nothing but
+/// dotfiles (as after `git init`), needs `--force` or `--merge`; returns whether it had
+/// anything in it, with the files of it that are kept.
+fn prepare_target(args: &CloneArgs, path: &Path) -> Result<(bool, foundry::PreservedFiles), Box<dyn std::error::Error>> {
+    let target = path.display();
+    ensure_not_a_file(path)?;
+    if args.merge && !path.join("foundry.toml").is_file() {
+        return Err(TokenFetchError::InvalidInput(format!(
+            "{} is not a foundry project (no foundry.toml), drop --merge to create one",
+            target
+        ))
+        .into());
+    }
+    let existed = path.exists() && (!path.is_dir() || std::fs::read_dir(path)?.next().is_some());
+    let only_dotfiles = existed && std::fs::read_dir(path)?.flatten().all(|entry| entry.file_name().to_string_lossy().starts_with('.'));
+    if existed && !only_dotfiles && !args.force && !args.merge {
+        return Err(TokenFetchError::PathExists(path.to_path_buf()).into());
+    }
+
+    if existed {
+        if args.merge {
+            info!("Merging into existing foundry project: {}", target);
+        } else if only_dotfiles && !args.force {
+            info!("Cloning into {}, which only holds dotfiles", target);
+        } else {
+            info!("Cloning into existing directory: {}", target);
+        }
+        Ok((true, foundry::PreservedFiles::capture(path, args.overwrite_config)?))
+    } else {
+        interrupt::create_dir_all(path)?;
+        info!("Created directory: {}", target);
+        Ok((false, foundry::PreservedFiles::default()))
+    }
+}
+
+/// Fetches one contract's getsourcecode response, or replays it from `--from-file`.
+async fn load_contract(
+    args: &CloneArgs,
+    ctx: &AppContext,
+    throttle: &Throttle,
+    config: &ChainConfig,
+    keys: Option<&KeyPool>,
+    address: &str,
+) -> Result<Loaded, Box<dyn std::error::Error>> {
+    match (&args.from_file, keys) {
+        (Some(file), _) => {
+            info!("Reading contract from {:?}...", file);
+            Ok(Loaded::read(load_response_file(file)?)?)
+        }
+        (None, Some(keys)) => Ok(ctx.fetcher.load_contract(config, keys, throttle, ctx.rpc_url(args.rpc_url.as_deref(), config), address).await?),
+        (None, None) => unreachable!("API key is loaded whenever no response file is given"),
+    }
+}
+
+/// Writes on the blocking pool while `fetch` is polled here, so the fetch's request is out
+/// while the files are written rather than after. The write is waited for even when the
+/// fetch fails, so nothing is still writing once the error is reported.
+async fn fetch_while_writing<F, W, T>(
+    fetch: impl std::future::Future<Output = Result<F, Box<dyn std::error::Error>>>,
+    write: W,
+) -> Result<(F, T), Box<dyn std::error::Error>>
+where
+    W: FnOnce() -> Result<T, TokenFetchError> + Send + 'static,
+    T: Send + 'static,
+{
+    let write = tokio::task::spawn_blocking(write);
+    let fetched = fetch.await;
+    let written = write.await.map_err(|e| TokenFetchError::Other(format!("Writing the files failed: {}", e)))?;
+    Ok((fetched?, written?))
+}
+
+/// The implementation to clone along with the proxy at `address`, with `--follow-proxy`
+/// and when the explorer names one.
+fn followed_implementation(args: &CloneArgs, entry: &SourceCodeResult, address: &str) -> Option<String> {
+    if !args.follow_proxy || !entry.is_proxy() {
+        return None;
+    }
+    let implementation = entry.implementation.trim();
+    if !address::looks_like_address(implementation) || implementation.eq_ignore_ascii_case(address) {
+        warn!("{} is a proxy, but the explorer names no implementation to follow", address);
+        return None;
+    }
+    Some(implementation.to_string())
+}
+
+/// Lists the files a write left in place in the report, and the manifest when one is kept.
+fn record_written(written: &WrittenFiles) {
+    for (path, changed) in written.take() {
+        match changed {
+            true => summary::file_written(&path),
+            false => manifest::record(&path),
+        }
+    }
+}
+
+/// Writes one fetched contract into the project, returning its entry in the manifest
+/// along with the sources as written.
+///
+/// With a `namespace_for` address the sources go to `src/<ContractName>_<addr-prefix>/`
+/// and the compiler settings to a matching foundry profile, so several contracts can
+/// share one project without colliding.
+fn write_contract(
+    args: &CloneArgs,
+    config: &ChainConfig,
+    loaded: &Loaded,
+    address: &str,
+    project: &Project,
+    namespace_for: Option<&str>,
+) -> Result<(manifest::Contract, ContractSource), Box<dyn std::error::Error>> {
+    // Saved before the sources are read from it, so an unverified response is kept too
+    if args.format.contains(&OutputFormat::Json) {
+        let path = match namespace_for {
+            Some(address) => project.root.join("explorer-response").join(format!("{}.json", address.to_lowercase())),
+            None => project.root.join("explorer-response.json"),
+        };
+        write_response(&loaded.json, &path)?;
+    }
+
+    let mut contract = ContractSource::from_entry(loaded.entry.clone())?;
+    let name = contract.contract_name.clone().unwrap_or_default();
+    contract.log_verification_info();
+
+    select_sources(&args.source_options(), &mut contract, address)?;
+
+    let (profile, src_dir) = match namespace_for {
+        Some(address) => {
+            let namespace = namespace(&name, address);
+            (namespace.clone(), format!("{}/{}", project.src_dir, namespace))
+        }
+        None => ("default".to_string(), project.src_dir.clone()),
+    };
+
+    // Looked up before the sources are moved out to be written
+    let declared_in = if args.tests { declaring_file(&contract.sources, "contract", &name) } else { None };
+    let solc = match contract.compiler_version.as_deref().and_then(foundry::solc_version) {
+        Some(version) => Some(version),
+        // Checked before anything is written, so conflicting pragmas don't leave half a clone
+        None if !project.keep_config => {
+            let picked = lint::solc_from_pragmas(&contract.sources)?.map(|version| version.to_string());
+            if let Some(version) = &picked {
+                info!(
+                    "The explorer's compiler version {:?} isn't a solc release, pinning solc {}, the newest every pragma solidity allows",
+                    contract.compiler_version.as_deref().unwrap_or_default(),
+                    version
+                );
+            }
+            picked
+        }
+        None => None,
+    };
+
+    if let Some(dir) = args.link_deps {
+        link_dependencies(&mut contract.sources, &project.root, dir)?;
+    }
+    let src_path = project.root.join(&src_dir);
+    let written: Vec<PathBuf> = match args.check {
+        true => contract.sources.keys().map(|key| src_path.join(key)).collect(),
+        false => Vec::new(),
+    };
+    let noted = WrittenFiles::new();
+    let result = write_sources(&src_path, &contract.sources, Some(&noted));
+    record_written(&noted);
+    result?;
+    if args.check {
+        lint::check_sources(&written, &project.root, solc.as_deref())?;
+    }
+    let file_name = match namespace_for {
+        Some(_) => profile.clone(),
+        None if !safe_name(&name).is_empty() => safe_name(&name),
+        None => "Contract".to_string(),
+    };
+    if args.hardhat() {
+        write_abi(&contract.entry, &project.root.join("artifacts-external").join(format!("{}.json", file_name)));
+    } else if args.abi() {
+        write_abi(&contract.entry, &project.root.join("abi").join(format!("{}.json", file_name)));
+    }
+    // A project of its own gets the license at its root, alongside the sources otherwise
+    let license_dir = if namespace_for.is_none() && !args.merge { project.root.clone() } else { src_path.clone() };
+    let license = license::write(&license_dir, contract.license.as_deref(), address)?;
+    if args.tests {
+        match declared_in {
+            Some(file) => {
+                let test = foundry::fork_test(
+                    &name,
+                    &format!("../{}/{}", src_dir, file),
+                    address,
+                    contract.compiler_version.as_deref(),
+                    &fork_rpc_env(config),
+                    args.fork_block,
+                )?;
+                write_fork_test(&project.root.join("test").join(format!("{}.fork.t.sol", file_name)), &test)?;
+            }
+            None => warn!("No source declares contract {}, skipping the fork test", name),
+        }
+    }
+
+    // Reproduce the verified compiler configuration
+    if args.hardhat() {
+        let solc = solc.as_deref().ok_or("No solc version to compile with, neither from the explorer nor from the pragmas")?;
+        let scaffold = hardhat::Project {
+            name: if name.is_empty() { "Contract" } else { &name },
+            address,
+            chain: &args.chain,
+            solc,
+            settings: contract.settings.as_ref(),
+            optimizer: contract.optimizer,
+        };
+        hardhat::scaffold(&project.root, &scaffold)?;
+    } else if project.keep_config {
+        info!("Leaving the existing foundry.toml untouched, pass --overwrite-config to apply the verified compiler settings");
+    } else {
+        foundry::apply_compiler_settings(
+            &project.root,
+            &profile,
+            &src_dir,
+            solc.as_deref(),
+            contract.settings.as_ref(),
+        )?;
+    }
+    if namespace_for.is_some() {
+        info!("Build {} with FOUNDRY_PROFILE={} forge build", address, profile);
+    }
+    if let Some(version) = solc.as_ref().filter(|_| !args.hardhat()) {
+        if !args.install_solc && !foundry::solc_installed(version) {
+            info!("solc {} will be downloaded on the first forge build, pass --install-solc to fetch it now", version);
+        }
+    }
+
+    summary::contract(address, &contract);
+
+    let entry = manifest::Contract {
+        address: address.to_string(),
+        source_address: loaded.source_address.clone(),
+        name: Some(name).filter(|name| !name.is_empty()),
+        compiler_version: contract.compiler_version.clone(),
+        settings: contract.settings.clone(),
+        license,
+        source_dir: src_dir,
+        options: args.source_options(),
+    };
+    Ok((entry, contract))
+}
+
+/// Writes the compliance suite of a contract classified as ERC-20 or ERC-721, named after
+/// the contract or, in a batch, its `namespace`.
+///
+/// A project pinned to a solc older than forge-std supports gets the suite under
+/// `compliance/` instead, with a foundry profile of its own to build it with.
+fn write_compliance_tests(
+    args: &CloneArgs,
+    config: &ChainConfig,
+    project: &Project,
+    contract: &ContractSource,
+    address: &str,
+    classification: &standard::Classification,
+    namespace: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let standard = match classification.standard {
+        Some(standard @ (standard::Standard::Erc20 | standard::Standard::Erc721)) => standard,
+        _ => {
+            warn!("{} is {}, only ERC-20 and ERC-721 tokens get compliance tests", address, classification.describe());
+            return Ok(());
+        }
+    };
+    let name = match namespace {
+        Some(namespace) => namespace.to_string(),
+        None => Some(safe_name(contract.contract_name.as_deref().unwrap_or_default()))
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| "Contract".to_string()),
+    };
+
+    let mut holders = args
+        .holder
+        .iter()
+        .map(|holder| address::normalize(holder, true))
+        .collect::<Result<Vec<_>, _>>()?;
+    holders.extend(compliance::constructor_addresses(&contract.entry.constructor_arguments));
+    let rpc_env = fork_rpc_env(config);
+    let test = compliance::render(&compliance::Suite {
+        name: &name,
+        address,
+        standard,
+        extensions: &classification.extensions,
+        holders: &holders,
+        token_id: args.token_id,
+        rpc_env: &rpc_env,
+        fork_block: args.fork_block,
+    })?;
+
+    let file_name = format!("{}.compliance.t.sol", name);
+    let solc = contract.compiler_version.as_deref().and_then(foundry::solc_version);
+    if project.keep_config || compliance::builds_with(solc.as_deref()) {
+        let path = project.root.join("test").join(&file_name);
+        write_fork_test(&path, &test)?;
+        info!("Run the compliance tests with {}=... forge test --match-path test/{}", rpc_env, file_name);
+    } else {
+        let path = project.root.join("compliance").join(&file_name);
+        write_fork_test(&path, &test)?;
+        foundry::add_compliance_profile(&project.root, compliance::PROFILE_SOLC)?;
+        info!(
+            "solc {} is too old for forge-std, run the compliance tests with {}=... FOUNDRY_PROFILE=compliance forge test",
+            solc.unwrap_or_default(),
+            rpc_env
+        );
+    }
+    Ok(())
+}
+
+/// Writes the contract's ABI next to its sources. A missing ABI is only a warning, as the
+/// sources are what the clone is for.
+fn write_abi(entry: &SourceCodeResult, path: &Path) {
+    let written = extract_abi(entry).and_then(|abi| {
+        std::fs::create_dir_all(path.parent().expect("ABI path has a parent"))?;
+        if path.exists() {
+            warn!("Overwriting existing file: {:?}", path);
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&abi)? + "\n")?;
+        summary::file_written(path);
+        Ok(())
+    });
+    match written {
+        Ok(()) => info!("Wrote ABI to {:?}", path),
+        Err(e) => warn!("Skipping ABI: {}", e),
+    }
+}
+
+/// Saves a getsourcecode response as the explorer sent it. It never holds the API key,
+/// which is only part of the request URL.
+fn write_response(json: &serde_json::Value, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(path.parent().expect("response path has a parent"))?;
+    std::fs::write(path, serde_json::to_string_pretty(json)? + "\n")?;
+    summary::file_written(path);
+    info!("Saved the explorer response to {:?}", path);
+    Ok(())
+}
+
+fn write_fork_test(path: &Path, test: &str) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(path.parent().expect("test path has a parent"))?;
+    if path.exists() {
+        warn!("Overwriting existing file: {:?}", path);
+    }
+    std::fs::write(path, test)?;
+    summary::file_written(path);
+    info!("Wrote fork test to {:?}", path);
+    Ok(())
+}
+
+/// Builds a filesystem-safe directory name such as `Token_0x1234ab`.
+fn namespace(contract_name: &str, address: &str) -> String {
+    let name = safe_name(contract_name);
+    let name = if name.is_empty() { "Contract".to_string() } else { name };
+    let prefix: String = address.chars().take(8).collect();
+
+    format!("{}_{}", name, prefix.to_lowercase())
+}
+
+/// Parses the `ABI` field of a getsourcecode entry, which the explorer returns as a string.
+fn extract_abi(entry: &SourceCodeResult) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    serde_json::from_str(&entry.abi).map_err(|_| {
+        error!("Contract ABI is unavailable: {}", entry.abi);
+        "Contract ABI is unavailable. The contract might not be verified.".into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, method, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn fetch_args() -> FetchArgs {
+        FetchArgs {
+            no_cache: true,
+            refresh: false,
+            offline: false,
+            max_age: None,
+            proxy: None,
+            timeout: None,
+            retries: 2,
+            retry_delay: Duration::from_millis(1),
+            rate_limit_retries: 0,
+            rps: None,
+            tier: None,
+            api_key: None,
+            api_key_file: None,
+            strict: false,
+            custom_chain: Vec::new(),
+            chain_id: None,
+            deadline: None,
+        }
+    }
+
+    fn response(source_code: &str) -> serde_json::Value {
+        serde_json::json!({
+            "status": "1",
+            "message": "OK",
+            "result": [{
+                "SourceCode": source_code,
+                "ABI": "[]",
+                "ContractName": "Token",
+                "CompilerVersion": "v0.8.19+commit.7dd6d404",
+            }]
+        })
+    }
+
+    /// Parses a command line the way `run` does, legacy invocations included.
+    fn parse<'a>(args: impl IntoIterator<Item = &'a std::ffi::OsStr>) -> Result<Args, clap::Error> {
+        let raw_args = args.into_iter().map(std::ffi::OsStr::to_os_string).collect();
+        Args::try_parse_from(with_default_subcommand(&Args::command(), raw_args))
+    }
+
+    fn context(fetch_args: FetchArgs) -> AppContext {
+        AppContext::new(fetch_args, config::Config::default()).unwrap()
+    }
+
+    #[test]
+    fn rpc_features_fall_back_to_the_chains_public_endpoint() {
+        let ctx = context(fetch_args());
+        let eth = chain_config("eth").unwrap();
+        assert_eq!(ctx.required_rpc_url(Some("http://127.0.0.1:8545"), eth, "--check-onchain").unwrap(), "http://127.0.0.1:8545");
+        assert_eq!(ctx.required_rpc_url(None, eth, "--check-onchain").unwrap(), eth.rpc_url.unwrap());
+        // Features with another way to get their answer only use a configured endpoint
+        assert_eq!(ctx.rpc_url(None, eth), None);
+
+        let custom = ChainConfig::custom("norpc", "http://127.0.0.1:9/api", 424243, None, None, None).unwrap();
+        let err = ctx.required_rpc_url(None, &custom, "--check-onchain").unwrap_err();
+        assert!(err.to_string().contains("--rpc-url"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn offline_runs_fail_on_anything_uncached() {
+        let ctx = context(FetchArgs { no_cache: false, offline: true, ..fetch_args() });
+        let config = ChainConfig::custom("offline", "http://127.0.0.1:9/api", 424242, None, None, None).unwrap();
+        let keys = load_api_keys(&config, &ctx).unwrap();
+
+        let address = "0x0000000000000000000000000000000000000bad";
+        let err = ctx.fetcher.fetch_cached(&config, address, &keys, &ctx.throttle(1, &config)).await.unwrap_err();
+        assert_eq!(err.class(), "not_cached");
+        assert_eq!(err.exit_code(), 9);
+        assert!(err.to_string().contains(address), "{}", err);
+        assert!(ctx.rpc_url(Some("http://127.0.0.1:8545"), &config).is_none());
+
+        let err = fetch(&ctx, "http://127.0.0.1:9/api").await.unwrap_err();
+        assert!(matches!(err, TokenFetchError::NotCached(_)), "{:?}", err);
+    }
+
+    /// Queries `url` as is, with a single API key.
+    async fn fetch(ctx: &AppContext, url: &str) -> Result<serde_json::Value, TokenFetchError> {
+        ctx.fetcher.query(&Throttle::new(1, 1000), &KeyPool::new(vec!["key".to_string()]), |_| url.to_string()).await
+    }
+
+    fn standard_json() -> serde_json::Value {
+        serde_json::json!({
+            "language": "Solidity",
+            "sources": {
+                "contracts/Token.sol": { "content": "import \"@openzeppelin/contracts/token/ERC20/ERC20.sol\";\ncontract Token {}" },
+                "@openzeppelin/contracts/token/ERC20/ERC20.sol": { "content": "contract ERC20 {}" }
+            },
+            "settings": { "optimizer": { "enabled": true, "runs": 200 } }
+        })
+    }
+
+    #[test]
+    fn unusable_compiler_versions_pin_solc_from_the_pragmas() {
+        let address = "0x1111111111111111111111111111111111111111";
+        let args = parse(["tokenfetch", "eth", address, "./out"].map(std::ffi::OsStr::new)).unwrap();
+        let Command::Clone(args) = args.command else { unreachable!() };
+        let clone = |sources: serde_json::Value| {
+            let dir = tempfile::tempdir().unwrap();
+            let project = Project { root: dir.path().to_path_buf(), keep_config: false, src_dir: "src".to_string() };
+            let mut json = response(&serde_json::json!({ "language": "Solidity", "sources": sources, "settings": {} }).to_string());
+            json["result"][0]["CompilerVersion"] = "v0.8.20-nightly.2023.3.1+commit.ab12cd34".into();
+            write_contract(&args, chain_config("eth").unwrap(), &Loaded::read(json.clone()).unwrap(), address, &project, None).map(|_| dir)
+        };
+
+        let dir = clone(serde_json::json!({
+            "Token.sol": { "content": "pragma solidity ^0.8.0;\ncontract Token {}" },
+            "Math.sol": { "content": "pragma solidity >=0.6.0 <0.8.20;\nlibrary Math {}" }
+        }))
+        .unwrap();
+        let config: toml::Table = std::fs::read_to_string(dir.path().join("foundry.toml")).unwrap().parse().unwrap();
+        assert_eq!(config["profile"]["default"]["solc_version"].as_str(), Some("0.8.19"));
+
+        let err = clone(serde_json::json!({
+            "Token.sol": { "content": "pragma solidity ^0.8.0;\ncontract Token {}" },
+            "Old.sol": { "content": "pragma solidity ^0.6.0;\ncontract Old {}" }
+        }))
+        .unwrap_err();
+        assert!(err.to_string().contains("No solc release satisfies every pragma solidity"), "{}", err);
+    }
+
+    #[test]
+    fn linked_dependencies_are_remapped_instead_of_written() {
+        let address = "0x1111111111111111111111111111111111111111";
+        let args = parse(["tokenfetch", "eth", address, "./out", "--link-deps"].map(std::ffi::OsStr::new)).unwrap();
+        let Command::Clone(args) = args.command else { unreachable!() };
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("remappings.txt"), "forge-std/=lib/forge-std/src/").unwrap();
+        let project = Project { root: dir.path().to_path_buf(), keep_config: false, src_dir: "src".to_string() };
+        write_contract(&args, chain_config("eth").unwrap(), &Loaded::read(response(&standard_json().to_string())).unwrap(), address, &project, None).unwrap();
+
+        assert!(dir.path().join("src/contracts/Token.sol").is_file());
+        assert!(!dir.path().join("src/@openzeppelin").exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("remappings.txt")).unwrap(),
+            "forge-std/=lib/forge-std/src/\n@openzeppelin/contracts/=lib/openzeppelin-contracts/contracts/\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn hardhat_format_writes_a_hardhat_project() {
+        let address = "0x1111111111111111111111111111111111111111";
+        let args = parse(["tokenfetch", "eth", address, "./out", "--format", "hardhat,json"].map(std::ffi::OsStr::new)).unwrap();
+        let Command::Clone(args) = args.command else { unreachable!() };
+        let dir = tempfile::tempdir().unwrap();
+        let project = Project { root: dir.path().to_path_buf(), keep_config: false, src_dir: hardhat::SOURCES_DIR.to_string() };
+        let json = response(&standard_json().to_string());
+        write_contract(&args, chain_config("eth").unwrap(), &Loaded::read(json.clone()).unwrap(), address, &project, None).unwrap();
+
+        assert!(dir.path().join("contracts/contracts/Token.sol").is_file());
+        assert!(dir.path().join("artifacts-external/Token.json").is_file());
+        assert!(dir.path().join("explorer-response.json").is_file());
+        assert!(!dir.path().join("foundry.toml").exists() && !dir.path().join("src").exists());
+        let config = std::fs::read_to_string(dir.path().join("hardhat.config.ts")).unwrap();
+        assert!(config.contains("version: \"0.8.19\""), "{}", config);
+        let package: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.path().join("package.json")).unwrap()).unwrap();
+        assert_eq!(package["devDependencies"]["hardhat"], hardhat::HARDHAT_VERSION);
+
+        let args = parse(["tokenfetch", "eth", address, "./out", "--format", "hardhat", "--tests"].map(std::ffi::OsStr::new)).unwrap();
+        let Command::Clone(args) = args.command else { unreachable!() };
+        let err = clone_contract(*args, &context(fetch_args())).await.unwrap_err();
+        assert!(err.to_string().contains("--tests works on forge projects"), "{}", err);
+    }
+
+    #[test]
+    fn namespaces_are_a_safe_name_and_an_address_prefix() {
+        assert_eq!(namespace("", "0xABCDEF0123"), "Contract_0xabcdef");
+    }
+
+    #[test]
+    fn batch_summary_is_an_aligned_table() {
+        let rows = [
+            ("0x1111111111111111111111111111111111111111", Ok("Token")),
+            ("0x2222222222222222222222222222222222222222", Err("Contract not verified or does not exist".to_string())),
+        ];
+        assert_eq!(
+            batch_table(&rows),
+            [
+                "ADDRESS                                     CONTRACT  RESULT",
+                "0x1111111111111111111111111111111111111111  Token     ok",
+                "0x2222222222222222222222222222222222222222  -         failed: Contract not verified or does not exist",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn merges_into_an_existing_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("project");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("foundry.toml"), "[profile.default]\n").unwrap();
+        std::fs::write(root.join("src/Mine.sol"), "contract Mine {}").unwrap();
+        let response_file = dir.path().join("response.json");
+        std::fs::write(&response_file, response("contract Token {}").to_string()).unwrap();
+
+        let args = parse([
+            "tokenfetch".as_ref(),
+            "eth".as_ref(),
+            "0x1111111111111111111111111111111111111111".as_ref(),
+            root.as_os_str(),
+            "--merge".as_ref(),
+            "--from-file".as_ref(),
+            response_file.as_os_str(),
+        ])
+        .unwrap();
+        let Command::Clone(clone_args) = args.command else { panic!("not a clone: {:?}", args.command) };
+        clone_contract(*clone_args, &context(fetch_args())).await.unwrap();
+
+        assert_eq!(std::fs::read_to_string(root.join("src/Mine.sol")).unwrap(), "contract Mine {}");
+        assert_eq!(std::fs::read_to_string(root.join("src/Token.sol")).unwrap(), "contract Token {}");
+        assert_eq!(std::fs::read_to_string(root.join("foundry.toml")).unwrap(), "[profile.default]\n");
+    }
+
+    #[tokio::test]
+    async fn merges_follow_the_configured_source_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("project");
+        std::fs::create_dir_all(root.join("contracts")).unwrap();
+        std::fs::write(root.join("foundry.toml"), "[profile.default]\nsrc = \"contracts\"\n").unwrap();
+        let response_file = dir.path().join("response.json");
+        std::fs::write(&response_file, response("contract Token {}").to_string()).unwrap();
+
+        let args = parse([
+            "tokenfetch".as_ref(),
+            "eth".as_ref(),
+            "0x1111111111111111111111111111111111111111".as_ref(),
+            root.as_os_str(),
+            "--merge".as_ref(),
+            "--from-file".as_ref(),
+            response_file.as_os_str(),
+        ])
+        .unwrap();
+        let Command::Clone(clone_args) = args.command else { panic!("not a clone: {:?}", args.command) };
+        clone_contract(*clone_args, &context(fetch_args())).await.unwrap();
+
+        assert_eq!(std::fs::read_to_string(root.join("contracts/Token.sol")).unwrap(), "contract Token {}");
+        assert!(!root.join("src").exists());
+        assert_eq!(manifest::Manifest::read(&root).unwrap().contracts[0].source_dir, "contracts");
+    }
+
+    #[test]
+    fn fork_tests_read_the_chains_rpc_variable() {
+        assert_eq!(fork_rpc_env(chain_config("Ethereum").unwrap()), "ETH_RPC_URL");
+        let custom = ChainConfig::custom("my-chain", "https://api.example.org/api", 7, None, None, None).unwrap();
+        assert_eq!(fork_rpc_env(&custom), "MY_CHAIN_RPC_URL");
+    }
+
+    #[test]
+    fn chain_id_must_match_the_chain() {
+        let mut fetch = fetch_args();
+        fetch.chain_id = Some(8453);
+        let ctx = context(fetch);
+        assert_eq!(ctx.chain("base").unwrap().name, "base");
+        let err = ctx.chain("mainnet").unwrap_err();
+        assert_eq!(err.exit_code(), 2);
+        assert_eq!(err.to_string(), "--chain-id 8453 doesn't match chain eth, whose chain id is 1");
+    }
+
+    #[test]
+    fn chain_may_be_left_out_with_a_default() {
+        let address = "0x1111111111111111111111111111111111111111";
+        let ctx = context(fetch_args());
+        assert_eq!(omitted_chain("base", &ctx).unwrap(), None);
+        let err = omitted_chain(address, &ctx).unwrap_err();
+        assert!(err.to_string().contains("TOKENFETCH_CHAIN"), "{}", err);
+
+        let user_config = config::Config { default_chain: Some("base".into()), ..Default::default() };
+        let ctx = AppContext::new(fetch_args(), user_config).unwrap();
+        assert_eq!(omitted_chain(address, &ctx).unwrap().as_deref(), Some("base"));
+        assert_eq!(omitted_chain("eth", &ctx).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn targets_take_the_default_chain_and_a_normalized_address() {
+        let address = "0x1111111111111111111111111111111111111111";
+        let user_config = config::Config { default_chain: Some("base".into()), ..Default::default() };
+        let ctx = AppContext::new(FetchArgs { api_key: Some("KEY".into()), ..fetch_args() }, user_config).unwrap();
+
+        let (config, resolved, _) = resolve_target(address.to_uppercase().replace("0X", "0x"), None, "abi", None, &ctx).await.unwrap();
+        assert_eq!((config.name.as_ref(), resolved.as_str()), ("base", address));
+        let err = resolve_target(address.into(), Some("extra".into()), "abi", None, &ctx).await.map(drop).unwrap_err();
+        assert_eq!(err.to_string(), "Unexpected argument extra");
+        let err = resolve_target("eth".into(), None, "abi", None, &ctx).await.map(drop).unwrap_err();
+        assert!(err.to_string().contains("<ADDRESS>"), "{}", err);
+    }
+
+    #[test]
+    fn tier_and_rate_prefer_the_command_line_to_the_config_file() {
+        let user_config = || config::Config { tier: Some("advanced".into()), rps: Some(12), concurrency: Some(4), ..Default::default() };
+        let resolve = |flags: &[&str]| {
+            let address = "0x1111111111111111111111111111111111111111";
+            let args = parse(["tokenfetch", "clone", "eth", address].iter().chain(flags).map(std::ffi::OsStr::new)).unwrap();
+            let ctx = AppContext::new(args.fetch, user_config()).unwrap();
+            (ctx.fetch.tier, ctx.fetch.rps, ctx.user_config.concurrency)
+        };
+
+        assert_eq!(resolve(&[]), (Some(Tier::Advanced), Some(12), Some(4)));
+        assert_eq!(resolve(&["--tier", "professional"]), (Some(Tier::Professional), None, None));
+        assert_eq!(resolve(&["--rps", "2"]), (Some(Tier::Advanced), Some(2), Some(4)));
+        assert_eq!((Tier::Professional.rps(), Tier::Professional.concurrency()), (30, 15));
+
+        let user_config = config::Config { tier: Some("gold".into()), ..Default::default() };
+        let err = AppContext::new(fetch_args(), user_config).err().unwrap();
+        assert_eq!(err.to_string(), "Unknown tier \"gold\" in the config file");
+    }
+
+    #[test]
+    fn durations_take_a_unit_and_must_fit() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("2d"), Ok(Duration::from_secs(2 * 24 * 60 * 60)));
+        assert_eq!(parse_duration("10w"), Err("invalid duration unit in 10w, expected ms, s, m, h or d".to_string()));
+        assert_eq!(parse_duration("18446744073709551615d"), Err("duration 18446744073709551615d is too long".to_string()));
+    }
+
+    #[test]
+    fn no_abi_opts_out_of_the_config_default() {
+        let user_config = config::Config { always_fetch_abi: true, ..Default::default() };
+        let address = "0x1111111111111111111111111111111111111111";
+        let cases: [(&[&str], bool); 4] = [
+            (&[], true),
+            (&["--no-abi"], false),
+            (&["--no-abi", "--abi"], true),
+            (&["--abi", "--no-abi"], false),
+        ];
+        for (flags, abi) in cases {
+            let raw_args = ["tokenfetch", "clone", "eth", address, "./out"].into_iter().chain(flags.iter().copied());
+            let matches = with_config_defaults(Args::command(), &user_config).try_get_matches_from(raw_args).unwrap();
+            let Command::Clone(clone_args) = Args::from_arg_matches(&matches).unwrap().command else { panic!("not a clone") };
+            assert_eq!(clone_args.abi(), abi, "{:?}", flags);
+        }
+    }
+
+    #[test]
+    fn clone_is_the_default_subcommand() {
+        let address = "0x1111111111111111111111111111111111111111";
+        let cases: [&[&str]; 4] = [
+            &["clone", "eth", address, "./out"],
+            &["eth", address, "./out"],
+            &["-v", "--config", "tokenfetch.toml", "eth", address, "./out", "--merge"],
+            &["--retries=0", "--force", "eth", address, "./out"],
+        ];
+        for case in cases {
+            let args = parse(["tokenfetch"].iter().chain(case).map(std::ffi::OsStr::new)).unwrap();
+            let Command::Clone(clone_args) = args.command else { panic!("not a clone: {:?}", case) };
+            assert_eq!((clone_args.chain.as_str(), clone_args.address.as_deref()), ("eth", Some(address)), "{:?}", case);
+        }
+
+        let args = parse(["tokenfetch", "--no-cache", "abi", "eth", address].map(std::ffi::OsStr::new)).unwrap();
+        assert!(matches!(args.command, Command::Abi(_)));
+        // The refresh subcommand, not the --refresh flag
+        let args = parse(["tokenfetch", "refresh", "./vendored", "--apply"].map(std::ffi::OsStr::new)).unwrap();
+        assert!(matches!(args.command, Command::Refresh(RefreshArgs { apply: true, force: false, .. })));
+        assert!(parse(["tokenfetch", "refresh", "--force"].map(std::ffi::OsStr::new)).is_err());
+        let help = parse(["tokenfetch", "--help"].map(std::ffi::OsStr::new)).unwrap_err();
+        assert_eq!(help.kind(), clap::error::ErrorKind::DisplayHelp);
+        assert!(help.to_string().contains("deprecated"));
+    }
+
+    #[test]
+    fn target_parents_are_created() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested/new/dir");
+        let clone_args = |flags: &[&str]| {
+            let args = ["tokenfetch", "eth", "0x1111111111111111111111111111111111111111", "./out"];
+            let args = parse(args.iter().chain(flags).map(std::ffi::OsStr::new)).unwrap();
+            let Command::Clone(clone_args) = args.command else { unreachable!() };
+            clone_args
+        };
+
+        let (existed, _) = prepare_target(&clone_args(&[]), &path).unwrap();
+        assert!(!existed && path.is_dir());
+        // Still empty, so it can be cloned into
+        assert!(!prepare_target(&clone_args(&[]), &path).unwrap().0);
+
+        // As after `git init`, which needs forge init --force but nothing from the user
+        std::fs::create_dir_all(path.join(".git")).unwrap();
+        std::fs::write(path.join(".gitignore"), ".env\n").unwrap();
+        let (existed, preserved) = prepare_target(&clone_args(&[]), &path).unwrap();
+        assert!(existed);
+        preserved.restore(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(path.join(".gitignore")).unwrap(), ".env\n");
+
+        std::fs::write(path.join("foundry.toml"), "").unwrap();
+        let err = TokenFetchError::from(prepare_target(&clone_args(&[]), &path).unwrap_err());
+        assert!(matches!(err, TokenFetchError::PathExists(_)), "{:?}", err);
+        assert!(prepare_target(&clone_args(&["--force"]), &path).unwrap().0);
+    }
+
+    #[test]
+    fn target_paths_that_are_files_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("Token.sol");
+        std::fs::write(&file, "contract Token {}").unwrap();
+        let args = parse(["tokenfetch", "eth", "0x1111111111111111111111111111111111111111", "./out", "--force"].map(std::ffi::OsStr::new)).unwrap();
+        let Command::Clone(args) = args.command else { unreachable!() };
+
+        for path in [file.clone(), file.join("nested/out")] {
+            let err = TokenFetchError::from(prepare_target(&args, &path).unwrap_err());
+            assert!(matches!(&err, TokenFetchError::InvalidInput(message) if message.contains("is a file, expected a directory (or new path)")), "{:?}", err);
+        }
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "contract Token {}");
+        assert!(ensure_not_a_file(&dir.path().join("new/out")).is_ok());
+    }
+
+    #[test]
+    fn sources_go_to_the_project_source_directory() {
+        let address = "0x1111111111111111111111111111111111111111";
+        let args = parse(["tokenfetch", "eth", address, "./out", "--src-dir", "contracts"].map(std::ffi::OsStr::new)).unwrap();
+        let Command::Clone(args) = args.command else { unreachable!() };
+        let dir = tempfile::tempdir().unwrap();
+        let project = Project { root: dir.path().to_path_buf(), keep_config: false, src_dir: "contracts".to_string() };
+        let json = response(&standard_json().to_string());
+
+        let (single, _) = write_contract(&args, chain_config("eth").unwrap(), &Loaded::read(json.clone()).unwrap(), address, &project, None).unwrap();
+        assert_eq!(single.source_dir, "contracts");
+        assert!(dir.path().join("contracts/contracts/Token.sol").is_file());
+        let (batched, _) = write_contract(&args, chain_config("eth").unwrap(), &Loaded::read(json.clone()).unwrap(), address, &project, Some(address)).unwrap();
+        assert!(batched.source_dir.starts_with("contracts/Token_"), "{}", batched.source_dir);
+        assert!(dir.path().join(&batched.source_dir).join("contracts/Token.sol").is_file());
+        assert!(!dir.path().join("src").exists());
+        let config: toml::Table = std::fs::read_to_string(dir.path().join("foundry.toml")).unwrap().parse().unwrap();
+        let profile = batched.source_dir.trim_start_matches("contracts/");
+        assert_eq!(config["profile"][profile]["src"].as_str(), Some(batched.source_dir.as_str()));
+    }
+
+    #[test]
+    fn response_is_saved_before_it_is_parsed() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = Project { root: dir.path().to_path_buf(), keep_config: false, src_dir: "src".to_string() };
+        let address = "0x1111111111111111111111111111111111111111";
+        let args = parse(["tokenfetch", "eth", address, "./out", "--format", "json"].map(std::ffi::OsStr::new)).unwrap();
+        let Command::Clone(args) = args.command else { unreachable!() };
+
+        let json = response("");
+        assert!(write_contract(&args, chain_config("eth").unwrap(), &Loaded::read(json.clone()).unwrap(), address, &project, None).is_err());
+        let saved = std::fs::read_to_string(dir.path().join("explorer-response.json")).unwrap();
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&saved).unwrap(), json);
+    }
+
+    #[test]
+    fn contract_name_selects_its_imports() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = Project { root: dir.path().to_path_buf(), keep_config: false, src_dir: "src".to_string() };
+        let address = "0x1111111111111111111111111111111111111111";
+        let source = serde_json::json!({
+            "language": "Solidity",
+            "sources": {
+                "contracts/Router.sol": { "content": "import \"./Vault.sol\";\ncontract Router {}" },
+                "contracts/Vault.sol": { "content": "import {Math} from \"./Math.sol\";\ncontract Vault {}" },
+                "contracts/Math.sol": { "content": "library Math {}" },
+                "contracts/Unused.sol": { "content": "contract Unused {}" }
+            },
+            "settings": {}
+        });
+        let json = response(&source.to_string());
+
+        let args = parse(["tokenfetch", "eth", address, "./out", "--contract-name", "Router"].map(std::ffi::OsStr::new)).unwrap();
+        let Command::Clone(args) = args.command else { unreachable!() };
+        write_contract(&args, chain_config("eth").unwrap(), &Loaded::read(json.clone()).unwrap(), address, &project, None).unwrap();
+        for file in ["Router.sol", "Vault.sol", "Math.sol"] {
+            assert!(dir.path().join("src/contracts").join(file).is_file(), "{}", file);
+        }
+        assert!(!dir.path().join("src/contracts/Unused.sol").exists());
+
+        let args = parse(["tokenfetch", "eth", address, "./out", "--contract-name", "Pool"].map(std::ffi::OsStr::new)).unwrap();
+        let Command::Clone(args) = args.command else { unreachable!() };
+        let err = write_contract(&args, chain_config("eth").unwrap(), &Loaded::read(json.clone()).unwrap(), address, &project, None).unwrap_err().to_string();
+        assert!(err.contains("available contracts are: Math, Router, Unused, Vault"), "{}", err);
+    }
+
+    #[test]
+    fn compliance_tests_get_their_own_profile_on_old_solc() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = Project { root: dir.path().to_path_buf(), keep_config: false, src_dir: "src".to_string() };
+        let address = "0x1111111111111111111111111111111111111111";
+        let args = parse(["tokenfetch", "eth", address, "./out", "--gen-compliance-tests"].map(std::ffi::OsStr::new)).unwrap();
+        let Command::Clone(args) = args.command else { unreachable!() };
+        let erc20 = standard::Classification { standard: Some(standard::Standard::Erc20), extensions: vec![] };
+
+        let mut json = response("contract Token {}");
+        write_compliance_tests(&args, chain_config("eth").unwrap(), &project, &extract_sources(&json).unwrap(), address, &erc20, None).unwrap();
+        assert!(dir.path().join("test/Token.compliance.t.sol").is_file());
+
+        json["result"][0]["CompilerVersion"] = "v0.5.16+commit.9c3226ce".into();
+        write_compliance_tests(&args, chain_config("eth").unwrap(), &project, &extract_sources(&json).unwrap(), address, &erc20, Some("Token_0x1111")).unwrap();
+        assert!(dir.path().join("compliance/Token_0x1111.compliance.t.sol").is_file());
+        let config: toml::Table = std::fs::read_to_string(dir.path().join("foundry.toml")).unwrap().parse().unwrap();
+        assert_eq!(config["profile"]["compliance"]["src"].as_str(), Some("compliance"));
+        assert_eq!(config["profile"]["compliance"]["solc_version"].as_str(), Some(compliance::PROFILE_SOLC));
+    }
+
+    #[test]
+    fn meta_lists_verification_details() {
+        let address = "0x1111111111111111111111111111111111111111";
+        let mut json = response(&standard_json().to_string());
+        json["result"][0]["EVMVersion"] = "Default".into();
+        json["result"][0]["LicenseType"] = "MIT".into();
+        json["result"][0]["Proxy"] = "1".into();
+        json["result"][0]["Implementation"] = "0x2222222222222222222222222222222222222222".into();
+
+        let meta = contract_meta("eth", address, Some(&extract_sources(&json).unwrap()));
+        assert_eq!(meta["optimizer"], serde_json::json!({ "enabled": true, "runs": 200 }));
+        assert_eq!(meta["evm_version"], serde_json::Value::Null);
+        assert_eq!(meta["source_files"], 2);
+        let table = meta_table(&meta);
+        assert!(table.contains(&"Optimizer     enabled, 200 runs".to_string()), "{:?}", table);
+        assert!(table.contains(&"License       MIT".to_string()), "{:?}", table);
+        assert!(table.contains(&"Proxy         yes, implementation 0x2222222222222222222222222222222222222222".to_string()));
+        let mut meta = meta;
+        meta["standard"] = serde_json::json!({ "standard": "ERC-20", "extensions": ["metadata", "permit"] });
+        assert_eq!(meta_table(&meta).last().unwrap(), "Standard      ERC-20 (metadata, permit)");
+
+        let meta = contract_meta("eth", address, None);
+        assert_eq!(meta, serde_json::json!({ "chain": "eth", "address": address, "verified": false }));
+        assert_eq!(meta_table(&meta).last().unwrap(), "Verified  no");
+    }
+
+    #[test]
+    fn completions_list_chain_names() {
+        let mut script = Vec::new();
+        write_completions(clap_complete::Shell::Bash, &mut script);
+
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("eth base"));
+        assert!(script.contains("--addresses-file"));
+        assert!(script.contains("--custom-chain"));
+
+        // Nested subcommands complete chain names too
+        let set = script.split("TokenFetch__subcmd__key__subcmd__set)").nth(1).unwrap();
+        let set = &set[..set.find(";;").unwrap()];
+        assert!(set.contains("eth base"), "{}", set);
+    }
+
+    fn first_key(keys: &KeyPool) -> String {
+        match keys.pick() {
+            keys::Pick::Key(_, key) => key,
+            _ => panic!("no key available"),
+        }
+    }
+
+    #[test]
+    fn api_key_flag_wins_over_environment() {
+        let config = get_chain_config("eth").unwrap();
+        let flag_args = FetchArgs { api_key: Some(" from-flag\n".to_string()), ..fetch_args() };
+        assert_eq!(first_key(&load_api_keys(config, &context(flag_args)).unwrap()), "from-flag");
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "from-file\nsecond\n").unwrap();
+        let file_args = FetchArgs { api_key_file: Some(file.path().to_path_buf()), ..fetch_args() };
+        let keys = load_api_keys(config, &context(file_args)).unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(first_key(&keys), "from-file");
+    }
+
+    #[tokio::test]
+    async fn creation_code_is_the_deployment_input() {
+        let server = MockServer::start().await;
+        let config = ChainConfig::custom("creation", &format!("{}/api", server.uri()), 1, None, None, None).unwrap();
+        Mock::given(query_param("action", "getcontractcreation"))
+            .and(query_param("contractaddresses", "0x1111111111111111111111111111111111111111"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "status": "1",
+                "message": "OK",
+                "result": [{ "contractAddress": "0x1111111111111111111111111111111111111111", "txHash": "0xdeploy" }]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(query_param("action", "eth_getTransactionByHash"))
+            .and(query_param("txhash", "0xdeploy"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": { "hash": "0xdeploy", "to": null, "input": "0x6080604052" }
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(query_param("action", "getcontractcreation"))
+            .and(query_param("contractaddresses", "0x2222222222222222222222222222222222222222"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "status": "0",
+                "message": "No data found",
+                "result": []
+            })))
+            .mount(&server)
+            .await;
+
+        let (ctx, keys) = (context(fetch_args()), KeyPool::new(vec!["key".to_string()]));
+        let throttle = ctx.throttle(1, &config);
+        let code = creation_code(&ctx, &keys, &throttle, &config, "0x1111111111111111111111111111111111111111").await.unwrap();
+        assert_eq!(code, "0x6080604052");
+
+        let err = creation_code(&ctx, &keys, &throttle, &config, "0x2222222222222222222222222222222222222222").await.unwrap_err();
+        let err = TokenFetchError::from(err);
+        assert_eq!(err.exit_code(), 8);
+        assert_eq!(err.to_string(), "No code at address 0x2222222222222222222222222222222222222222 (EOA or selfdestructed)");
+    }
+
+    #[tokio::test]
+    async fn the_library_fetches_as_clone_does() {
+        let server = MockServer::start().await;
+        let config = ChainConfig::custom("library", &format!("{}/api", server.uri()), 1, None, None, None).unwrap();
+        let address = "0x1111111111111111111111111111111111111111";
+        // Each fetch first gets a 503, which both retry
+        let flaky = || async {
+            server.reset().await;
+            Mock::given(method("GET")).respond_with(ResponseTemplate::new(503)).up_to_n_times(1).mount(&server).await;
+            Mock::given(method("GET")).respond_with(ResponseTemplate::new(200).set_body_json(response("contract Token {}"))).mount(&server).await;
+        };
+
+        let args = parse(["tokenfetch", "library", address, "./out"].map(std::ffi::OsStr::new)).unwrap();
+        let Command::Clone(args) = args.command else { unreachable!() };
+        let ctx = context(fetch_args());
+        flaky().await;
+        let loaded = load_contract(&args, &ctx, &ctx.throttle(1, &config), &config, Some(&KeyPool::new(vec!["key".to_string()])), address).await.unwrap();
+        let cloned = extract_sources(&loaded.json).unwrap();
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+
+        flaky().await;
+        let fetched = tokenfetch::fetch_contract_source(&ctx.fetcher, &config, address, "key").await.unwrap();
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+        assert_eq!(fetched.sources(), cloned.sources());
+        assert_eq!(fetched.contract_name, cloned.contract_name);
+    }
+
+    #[tokio::test]
+    async fn minimal_proxies_clone_their_target() {
+        let server = MockServer::start().await;
+        let config = ChainConfig::custom("clones", &format!("{}/api", server.uri()), 1, None, None, None).unwrap();
+        let (proxy, target) = ("0x1111111111111111111111111111111111111111", "0x2222222222222222222222222222222222222222");
+        let source = |address: &str, source: &str| {
+            Mock::given(query_param("action", "getsourcecode"))
+                .and(query_param("address", address))
+                .respond_with(ResponseTemplate::new(200).set_body_json(response(source)))
+        };
+        source(proxy, "").mount(&server).await;
+        source(target, "contract Implementation {}").mount(&server).await;
+        Mock::given(query_param("action", "eth_getCode"))
+            .and(query_param("address", proxy))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": format!("0x363d3d373d3d3d363d73{}5af43d82803e903d91602b57fd5bf3", &target[2..])
+            })))
+            .mount(&server)
+            .await;
+
+        let args = parse(["tokenfetch", "clones", proxy, "./out"].map(std::ffi::OsStr::new)).unwrap();
+        let Command::Clone(args) = args.command else { unreachable!() };
+        let (ctx, keys) = (context(fetch_args()), KeyPool::new(vec!["key".to_string()]));
+        let loaded = load_contract(&args, &ctx, &ctx.throttle(1, &config), &config, Some(&keys), proxy).await.unwrap();
+        assert_eq!(loaded.json["result"][0]["SourceCode"], "contract Implementation {}");
+    }
+
+    #[test]
+    fn follow_proxy_picks_the_named_implementation() {
+        let (proxy, implementation) = ("0x1111111111111111111111111111111111111111", "0x2222222222222222222222222222222222222222");
+        let mut json = response("contract Proxy {}");
+        json["result"][0]["Proxy"] = "1".into();
+        json["result"][0]["Implementation"] = implementation.into();
+        let clone_args = |extra: &[&str]| {
+            let args = parse(["tokenfetch", "eth", proxy, "./out"].iter().chain(extra).map(std::ffi::OsStr::new)).unwrap();
+            let Command::Clone(args) = args.command else { unreachable!() };
+            args
+        };
+        let entry = |json: &serde_json::Value| Loaded::read(json.clone()).unwrap().entry;
+
+        assert_eq!(followed_implementation(&clone_args(&["--follow-proxy"]), &entry(&json), proxy).as_deref(), Some(implementation));
+        assert_eq!(followed_implementation(&clone_args(&[]), &entry(&json), proxy), None);
+        json["result"][0]["Implementation"] = proxy.into();
+        assert_eq!(followed_implementation(&clone_args(&["--follow-proxy"]), &entry(&json), proxy), None);
+    }
+
+    #[tokio::test]
+    async fn the_proxy_is_written_while_its_implementation_is_fetched() {
+        let server = MockServer::start().await;
+        let (sent, received) = std::sync::mpsc::channel();
+        Mock::given(method("GET"))
+            .respond_with(move |_: &wiremock::Request| {
+                sent.send(()).unwrap();
+                ResponseTemplate::new(200).set_body_string("{}").set_delay(Duration::from_millis(500))
+            })
+            .mount(&server)
+            .await;
+        let url = server.uri();
+        let fetch = async move { Ok::<_, Box<dyn std::error::Error>>(reqwest::get(url).await?.text().await?) };
+        // The write only returns once the request is out, which it never is if the fetch waits for it
+        let write = move || {
+            received.recv_timeout(Duration::from_secs(5)).map_err(|_| TokenFetchError::Other("the fetch waited for the write".into()))?;
+            Ok(std::time::Instant::now())
+        };
+        let (body, written) = fetch_while_writing(fetch, write).await.unwrap();
+        assert_eq!(body, "{}");
+        assert!(written.elapsed() >= Duration::from_millis(400), "the write finished as the response arrived");
+    }
+
+    #[test]
+    fn verify_match_failures_have_their_own_exit_codes() {
+        let exit_code = |check| report_code_check("Token", "0x1111111111111111111111111111111111111111", check).map_err(|e| e.exit_code());
+        assert_eq!(exit_code(CodeCheck::Matches(100)), Ok(()));
+        assert_eq!(exit_code(CodeCheck::NoCode), Err(8));
+        assert_eq!(exit_code(CodeCheck::Differs { local_len: 100, onchain_len: 90, ranges: vec![0..4, 80..90] }), Err(10));
+    }
+
+    #[tokio::test]
+    async fn tx_hashes_resolve_through_the_explorer() {
+        let server = MockServer::start().await;
+        let config = ChainConfig::custom("receipts", &format!("{}/api", server.uri()), 1, None, None, None).unwrap();
+        let (deploy, factory) = (format!("0x{}", "a".repeat(64)), format!("0x{}", "b".repeat(64)));
+        let receipt = |tx: &str, contract_address: serde_json::Value| {
+            Mock::given(query_param("action", "eth_getTransactionReceipt"))
+                .and(query_param("txhash", tx))
+                .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "result": { "transactionHash": tx, "status": "0x1", "contractAddress": contract_address }
+                })))
+        };
+        receipt(&deploy, "0x1111111111111111111111111111111111111111".into()).mount(&server).await;
+        receipt(&factory, serde_json::Value::Null).mount(&server).await;
+
+        let (ctx, keys) = (context(fetch_args()), KeyPool::new(vec!["key".to_string()]));
+        let mut addresses = [deploy, "0x2222222222222222222222222222222222222222".to_string()];
+        resolve_tx_hashes(&mut addresses, None, Some(&keys), &config, &ctx).await.unwrap();
+        assert_eq!(addresses, ["0x1111111111111111111111111111111111111111", "0x2222222222222222222222222222222222222222"]);
+
+        let err = resolve_tx_hashes(&mut [factory], None, Some(&keys), &config, &ctx).await.unwrap_err();
+        assert!(err.to_string().contains("if a factory deployed it, pass the contract's address instead"));
+    }
+
+    #[tokio::test]
+    async fn token_info_falls_back_to_calling_the_token() {
+        let server = MockServer::start().await;
+        let config = ChainConfig::custom("tokens", &format!("{}/api", server.uri()), 1, None, None, None).unwrap();
+        let address = "0x1111111111111111111111111111111111111111";
+        Mock::given(query_param("action", "tokeninfo"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "status": "0",
+                "message": "NOTOK",
+                "result": "Sorry, it looks like you are trying to access an API Pro endpoint"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(query_param("action", "tokensupply"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "status": "1", "message": "OK", "result": "1000000" })))
+            .mount(&server)
+            .await;
+        let call = |data: &str, result: serde_json::Value| {
+            Mock::given(query_param("action", "eth_call"))
+                .and(query_param("data", data))
+                .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": result })))
+        };
+        call(token::NAME, "0x4d4b520000000000000000000000000000000000000000000000000000000000".into()).mount(&server).await;
+        call(token::DECIMALS, "0x0000000000000000000000000000000000000000000000000000000000000012".into()).mount(&server).await;
+        Mock::given(query_param("data", token::SYMBOL))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "error": { "code": -32000, "message": "execution reverted" }
+            })))
+            .mount(&server)
+            .await;
+
+        let (ctx, keys) = (context(fetch_args()), KeyPool::new(vec!["key".to_string()]));
+        let throttle = ctx.throttle(1, &config);
+        let info = token_info(&ctx, &keys, &throttle, &config, address).await.unwrap();
+        assert_eq!(
+            info,
+            token::TokenInfo {
+                name: Some("MKR".into()),
+                symbol: None,
+                decimals: Some(18),
+                total_supply: Some("1000000".into()),
+                token_type: None,
+            }
+        );
+        // Refused once, tokeninfo isn't asked again
+        assert_eq!(token_info(&ctx, &keys, &throttle, &config, address).await.unwrap(), info);
+    }
+
+    #[tokio::test]
+    async fn proxies_are_classified_over_erc165() {
+        let server = MockServer::start().await;
+        let address = "0x1111111111111111111111111111111111111111";
+        for (id, supported) in [("80ac58cd", true), ("5b5e139f", true), ("d9b67a26", false), ("780e9d63", false)] {
+            let data = standard::supports_interface_call(id);
+            Mock::given(method("POST"))
+                .and(body_partial_json(serde_json::json!({ "method": "eth_call", "params": [{ "to": address, "data": data }] })))
+                .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "result": format!("0x{:064x}", supported as u8)
+                })))
+                .mount(&server)
+                .await;
+        }
+
+        let mut json = response("contract Proxy {}");
+        json["result"][0]["Proxy"] = "1".into();
+        let (ctx, entry) = (context(fetch_args()), SourceCodeResult::from_response(&json).unwrap());
+        assert_eq!(classify(&ctx, None, address, &entry, None).await, standard::Classification::default());
+        let standard = classify(&ctx, Some(&server.uri()), address, &entry, None).await;
+        assert_eq!(standard.describe(), "ERC-721 with metadata");
+    }
+
+    #[tokio::test]
+    async fn followed_proxies_are_classified_by_their_implementation() {
+        let functions = ["totalSupply()", "balanceOf(address)", "transfer(address,uint256)", "transferFrom(address,address,uint256)", "approve(address,uint256)", "allowance(address,address)"];
+        let abi: Vec<serde_json::Value> = functions
+            .iter()
+            .map(|signature| {
+                let (name, params) = signature.trim_end_matches(')').split_once('(').unwrap();
+                let inputs: Vec<_> = params.split(',').filter(|ty| !ty.is_empty()).map(|ty| serde_json::json!({ "name": "", "type": ty })).collect();
+                serde_json::json!({ "type": "function", "name": name, "inputs": inputs })
+            })
+            .collect();
+        let mut json = response("contract Token {}");
+        json["result"][0]["ABI"] = serde_json::to_string(&abi).unwrap().into();
+        let implementation = SourceCodeResult::from_response(&json).unwrap();
+        let mut json = response("contract Proxy {}");
+        json["result"][0]["Proxy"] = "1".into();
+        json["result"][0]["ABI"] = "[]".into();
+        let proxy = SourceCodeResult::from_response(&json).unwrap();
+
+        let (ctx, address) = (context(fetch_args()), "0x1111111111111111111111111111111111111111");
+        assert_eq!(classify(&ctx, None, address, &proxy, None).await, standard::Classification::default());
+        assert_eq!(classify(&ctx, None, address, &proxy, Some(&implementation)).await.describe(), "ERC-20");
+    }
+}
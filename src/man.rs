@@ -0,0 +1,131 @@
+use tokenfetch::chain::chains;
+use tokenfetch::error::TokenFetchError;
+use roff::{bold, italic, roman, Roff};
+use std::io::Write;
+
+const EXAMPLES: &[(&str, &str)] = &[
+    ("Clone a contract into a new forge project:", "TokenFetch eth 0x1f9840a85d5af5bf1d1762f925bdaddc4201f984 ./uni"),
+    ("Clone several contracts into one project, continuing past failures:", "TokenFetch base 0xabc...,0xdef... ./protocol --keep-going"),
+    ("Print only the verified ABI:", "TokenFetch abi eth 0x1f9840a85d5af5bf1d1762f925bdaddc4201f984 -o abi.json"),
+    ("Check a cloned project against the deployed code:", "TokenFetch verify-match eth 0x1f9840a85d5af5bf1d1762f925bdaddc4201f984 ./uni"),
+];
+
+/// Writes the `tokenfetch(1)` man page for `command`. Options and subcommands come from
+/// clap; the chain list, which `--help` keeps in its after-help text, gets a section of
+/// its own alongside the environment, exit status and examples.
+pub fn write(command: clap::Command, out: &mut dyn Write) -> std::io::Result<()> {
+    let man = clap_mangen::Man::new(command.clone()).title("tokenfetch");
+    man.render_title(out)?;
+    man.render_name_section(out)?;
+    man.render_synopsis_section(out)?;
+    man.render_description_section(out)?;
+    man.render_options_section(out)?;
+    man.render_subcommands_section(out)?;
+    write_subcommand_options(&command, "", out)?;
+
+    let mut page = Roff::new();
+    page.control("SH", ["CHAINS"]);
+    page.text([roman("The chain is given by name, or by one of its aliases:")]);
+    for config in chains() {
+        let mut names = vec![config.name.as_ref()];
+        names.extend(config.aliases);
+        page.control("TP", []);
+        page.text([bold(names.join(", "))]);
+        page.text([roman(format!(
+            "Chain id {}, explorer {}. The API key is read from {}.",
+            config.chain_id, config.explorer_host, config.api_key_env
+        ))]);
+    }
+
+    page.control("SH", ["ENVIRONMENT"]);
+    page.text([roman(
+        "Variables are also loaded from the nearest .env file and a .env.local next to it; variables already set in the environment win.",
+    )]);
+    let mut variable = |name: String, description: &str| {
+        page.control("TP", []);
+        page.text([bold(name)]);
+        page.text([roman(description)]);
+    };
+    for config in chains() {
+        variable(
+            format!("{}, {}S", config.api_key_env, config.api_key_env),
+            &format!("API key for {}. Several keys can be given comma-separated, and are rotated between.", config.name),
+        );
+    }
+    variable("TOKENFETCH_CHAIN".into(), "The chain to use when none is given, overriding default_chain in the config file.");
+    variable("<CHAIN>_RPC_URL".into(), "The RPC URL a fork test written with --tests forks from, e.g. ETH_RPC_URL.");
+    variable(
+        "HTTPS_PROXY, HTTP_PROXY, ALL_PROXY, NO_PROXY".into(),
+        "Proxy for explorer requests, in either case. --proxy overrides them.",
+    );
+    variable(
+        "RUST_LOG".into(),
+        "Log filter, e.g. reqwest=debug for the HTTP client's own logs. -v and -q take precedence over its default level.",
+    );
+
+    page.control("SH", ["EXIT STATUS"]);
+    let success = (0, "Success.");
+    for (code, meaning) in std::iter::once(success).chain(TokenFetchError::exit_codes()) {
+        page.control("TP", []);
+        page.text([bold(code.to_string())]);
+        page.text([roman(meaning)]);
+    }
+
+    page.control("SH", ["EXAMPLES"]);
+    for (description, example) in EXAMPLES {
+        page.text([roman(*description)]);
+        page.control("PP", []);
+        page.control("RS", ["4"]);
+        page.text([italic(*example)]);
+        page.control("RE", []);
+        page.control("PP", []);
+    }
+    page.to_writer(out)?;
+
+    man.render_version_section(out)?;
+    man.render_authors_section(out)
+}
+
+/// Renders the options of every visible subcommand of `command`, nested ones included,
+/// each under a heading naming it such as `KEY SET OPTIONS`, so the one page covers them.
+fn write_subcommand_options(command: &clap::Command, prefix: &str, out: &mut dyn Write) -> std::io::Result<()> {
+    for subcommand in command.get_subcommands().filter(|subcommand| !subcommand.is_hide_set()) {
+        let name = format!("{}{} ", prefix, subcommand.get_name());
+        let heading = format!("{}options", name);
+        // Rendered on its own, so the global flags aren't repeated for every subcommand
+        let rendered = subcommand
+            .clone()
+            .disable_help_flag(true)
+            .mut_args(|arg| arg.help_heading(heading.clone()));
+        clap_mangen::Man::new(rendered).render_options_section(out)?;
+        write_subcommand_options(subcommand, &name, out)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn man_page_covers_flags_chains_and_exit_codes() {
+        let mut out = Vec::new();
+        write(crate::Args::command(), &mut out).unwrap();
+        let page = String::from_utf8(out).unwrap();
+
+        assert!(page.contains(".TH tokenfetch 1"));
+        assert!(page.contains(".SH \"CLONE OPTIONS\""));
+        assert!(page.contains("\\-\\-fail\\-fast"));
+        assert!(page.contains(".SH \"KEY SET OPTIONS\""));
+        assert!(page.contains("verify\\-match"));
+        assert!(page.contains(".SH CHAINS"));
+        assert!(page.contains("eth, ethereum, mainnet"));
+        assert!(page.contains("ETHERSCAN_API_KEY, ETHERSCAN_API_KEYS"));
+        assert!(page.contains("RUST_LOG"));
+        assert!(page.contains(".SH \"EXIT STATUS\""));
+        assert!(page.contains(".SH EXAMPLES"));
+        // The hidden subcommands stay out of it
+        assert!(!page.contains("completions"));
+    }
+}
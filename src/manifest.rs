@@ -0,0 +1,334 @@
+use crate::error::TokenFetchError;
+use log::{debug, warn};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Written at the project root, recording where the clone came from and what it wrote.
+pub const FILE: &str = ".tokenfetch.json";
+
+/// Every file this run wrote, or found already there with the contents it would have
+/// written. Process-wide like the `--json` report, since files are written all over, and
+/// like it only collected once [`track`] is called, so writing sources through the library
+/// doesn't pile paths up.
+static WRITTEN: Mutex<Option<BTreeSet<PathBuf>>> = Mutex::new(None);
+
+/// Starts recording the files written, for [`write`] to hash into the manifest.
+pub fn track() {
+    WRITTEN.lock().unwrap_or_else(|e| e.into_inner()).get_or_insert_with(BTreeSet::new);
+}
+
+pub fn record(path: &Path) {
+    if let Some(written) = WRITTEN.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        written.insert(path.to_path_buf());
+    }
+}
+
+/// Takes the recorded files under `root`, as paths relative to it.
+fn take_under(root: &Path) -> Vec<String> {
+    let mut guard = WRITTEN.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(written) = guard.as_mut() else { return Vec::new() };
+    let under: Vec<PathBuf> = written.iter().filter(|path| path.starts_with(root)).cloned().collect();
+    under
+        .into_iter()
+        .filter_map(|path| {
+            written.remove(&path);
+            let relative = path.strip_prefix(root).ok()?;
+            Some(relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/"))
+        })
+        .collect()
+}
+
+pub fn sha256(bytes: &[u8]) -> String {
+    alloy_primitives::hex::encode(Sha256::digest(bytes))
+}
+
+/// `time` in RFC 3339 at second precision, in UTC.
+fn timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (days, rem) = ((secs / 86400) as i64, secs % 86400);
+    // Converts days since the epoch to a civil date, after Howard Hinnant's algorithm
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, rem / 3600, rem % 3600 / 60, rem % 60)
+}
+
+/// When the clone was made: `SOURCE_DATE_EPOCH` if it's set, as in reproducible builds,
+/// so that cloning the same sources again can give the same files byte for byte. A
+/// `pinned` clone, one packed into an archive, otherwise records the archive entries'
+/// fixed time instead of the clock, so the archive comes out the same on every run.
+pub fn fetched_at(pinned: bool) -> SystemTime {
+    match std::env::var("SOURCE_DATE_EPOCH").ok().and_then(|secs| secs.trim().parse().ok()) {
+        Some(secs) => UNIX_EPOCH + std::time::Duration::from_secs(secs),
+        None if pinned => UNIX_EPOCH + std::time::Duration::from_secs(crate::archive::MTIME),
+        None => SystemTime::now(),
+    }
+}
+
+/// How a clone picked and rewrote the sources before writing them, which `refresh`
+/// repeats so it compares the re-fetched sources with what was written.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceOptions {
+    /// Only the file declaring this contract and the files it imports
+    pub contract_name: Option<String>,
+    pub include_deps_only: bool,
+    /// Dependency packages were left out, to be installed and remapped
+    pub link_deps: bool,
+    pub normalize_eol: bool,
+    pub trim_trailing_whitespace: bool,
+}
+
+impl SourceOptions {
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "contractName": self.contract_name,
+            "includeDepsOnly": self.include_deps_only,
+            "linkDeps": self.link_deps,
+            "normalizeEol": self.normalize_eol,
+            "trimTrailingWhitespace": self.trim_trailing_whitespace,
+        })
+    }
+
+    fn from_json(json: &serde_json::Value) -> Self {
+        let flag = |key: &str| json[key].as_bool().unwrap_or_default();
+        SourceOptions {
+            contract_name: json["contractName"].as_str().map(str::to_string),
+            include_deps_only: flag("includeDepsOnly"),
+            link_deps: flag("linkDeps"),
+            normalize_eol: flag("normalizeEol"),
+            trim_trailing_whitespace: flag("trimTrailingWhitespace"),
+        }
+    }
+}
+
+/// One contract written into the project.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contract {
+    pub address: String,
+    /// The address the sources were fetched from, when it isn't `address`: the contract
+    /// a minimal proxy delegates to
+    pub source_address: Option<String>,
+    pub name: Option<String>,
+    pub compiler_version: Option<String>,
+    /// The standard-JSON settings it was verified with
+    pub settings: Option<serde_json::Value>,
+    /// The SPDX identifier of the license it was verified under
+    pub license: Option<String>,
+    /// Where its sources were written, relative to the project root
+    pub source_dir: String,
+    pub options: SourceOptions,
+}
+
+impl Contract {
+    fn to_json(&self) -> serde_json::Value {
+        let mut json = serde_json::json!({
+            "address": self.address,
+            "name": self.name,
+            "compilerVersion": self.compiler_version,
+            "settings": self.settings,
+            "license": self.license,
+            "sourceDir": self.source_dir,
+        });
+        // Left out when there's nothing to say, as for most clones
+        if let Some(address) = &self.source_address {
+            json["sourceAddress"] = address.as_str().into();
+        }
+        if self.options != SourceOptions::default() {
+            json["options"] = self.options.to_json();
+        }
+        json
+    }
+
+    fn from_json(json: &serde_json::Value) -> Option<Self> {
+        let text = |key: &str| json[key].as_str().map(str::to_string);
+        Some(Contract {
+            address: text("address")?,
+            source_address: text("sourceAddress"),
+            name: text("name"),
+            compiler_version: text("compilerVersion"),
+            settings: Some(json["settings"].clone()).filter(|settings| !settings.is_null()),
+            license: text("license"),
+            source_dir: text("sourceDir")?,
+            options: SourceOptions::from_json(&json["options"]),
+        })
+    }
+}
+
+/// The contents of `.tokenfetch.json`. Keys are sorted and paths relative, so the same
+/// clone made on another machine differs only in `fetchedAt`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifest {
+    pub chain: String,
+    pub chain_id: i32,
+    /// The explorer API the sources were fetched from
+    pub explorer: String,
+    pub fetched_at: String,
+    pub tool_version: String,
+    pub contracts: Vec<Contract>,
+    /// SHA-256 of every file written, keyed by its path relative to the project root
+    pub files: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "chain": self.chain,
+            "chainId": self.chain_id,
+            "explorer": self.explorer,
+            "fetchedAt": self.fetched_at,
+            "tool": { "name": env!("CARGO_PKG_NAME"), "version": self.tool_version },
+            "contracts": self.contracts.iter().map(Contract::to_json).collect::<Vec<_>>(),
+            "files": self.files,
+        })
+    }
+
+    pub fn from_json(json: &serde_json::Value) -> Option<Self> {
+        Some(Manifest {
+            chain: json["chain"].as_str()?.to_string(),
+            chain_id: json["chainId"].as_i64().and_then(|id| i32::try_from(id).ok())?,
+            explorer: json["explorer"].as_str().unwrap_or_default().to_string(),
+            fetched_at: json["fetchedAt"].as_str().unwrap_or_default().to_string(),
+            tool_version: json["tool"]["version"].as_str().unwrap_or_default().to_string(),
+            contracts: json["contracts"].as_array()?.iter().map(Contract::from_json).collect::<Option<_>>()?,
+            files: json["files"]
+                .as_object()?
+                .iter()
+                .map(|(path, hash)| Some((path.clone(), hash.as_str()?.to_string())))
+                .collect::<Option<_>>()?,
+        })
+    }
+
+    /// Reads the manifest of the project at `root`, if it has one that parses.
+    pub fn read(root: &Path) -> Option<Self> {
+        let text = std::fs::read_to_string(root.join(FILE)).ok()?;
+        let manifest = serde_json::from_str(&text).ok().and_then(|json| Manifest::from_json(&json));
+        if manifest.is_none() {
+            warn!("Ignoring {}, which isn't a manifest this version can read", root.join(FILE).display());
+        }
+        manifest
+    }
+
+    /// Keeps what an earlier clone into the same project recorded, for contracts and
+    /// files this one didn't rewrite. One from another chain is replaced outright.
+    fn merge_previous(&mut self, previous: Manifest, root: &Path) {
+        if (previous.chain_id, &previous.chain) != (self.chain_id, &self.chain) {
+            warn!("Replacing the manifest of the {} clone in {}", previous.chain, root.display());
+            return;
+        }
+        for contract in previous.contracts {
+            if !self.contracts.iter().any(|c| c.address.eq_ignore_ascii_case(&contract.address)) {
+                self.contracts.push(contract);
+            }
+        }
+        self.contracts.sort_by_key(|contract| contract.address.to_lowercase());
+        for (path, hash) in previous.files {
+            if root.join(&path).is_file() {
+                self.files.entry(path).or_insert(hash);
+            }
+        }
+    }
+}
+
+/// Writes `.tokenfetch.json` for the contracts cloned into `root` at `fetched_at`, hashing
+/// the files this run wrote there, and returns what it wrote. An earlier manifest in the
+/// project is merged in.
+pub fn write(
+    root: &Path,
+    chain: &str,
+    chain_id: i32,
+    explorer: &str,
+    mut contracts: Vec<Contract>,
+    fetched_at: SystemTime,
+) -> Result<Manifest, TokenFetchError> {
+    contracts.sort_by_key(|contract| contract.address.to_lowercase());
+    let mut files = BTreeMap::new();
+    for path in take_under(root).into_iter().filter(|path| path != FILE) {
+        match std::fs::read(root.join(&path)) {
+            Ok(bytes) => {
+                files.insert(path, sha256(&bytes));
+            }
+            Err(e) => debug!("Not hashing {}: {}", path, e),
+        }
+    }
+    let mut manifest = Manifest {
+        chain: chain.to_string(),
+        chain_id,
+        explorer: explorer.to_string(),
+        fetched_at: timestamp(fetched_at),
+        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        contracts,
+        files,
+    };
+    if let Some(previous) = Manifest::read(root) {
+        manifest.merge_previous(previous, root);
+    }
+    std::fs::write(root.join(FILE), serde_json::to_string_pretty(&manifest.to_json())? + "\n")?;
+    crate::summary::file_written(&root.join(FILE));
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn formats_utc_timestamps() {
+        assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(timestamp(UNIX_EPOCH + Duration::from_secs(951_827_696)), "2000-02-29T12:34:56Z");
+        assert_eq!(timestamp(UNIX_EPOCH + Duration::from_secs(1_792_022_399)), "2026-10-14T23:59:59Z");
+    }
+
+    #[test]
+    fn hashes_written_files_and_merges_earlier_clones() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src/A_0xaaaa")).unwrap();
+        std::fs::write(root.join("src/A_0xaaaa/A.sol"), "contract A {}").unwrap();
+        track();
+        record(&root.join("src/A_0xaaaa/A.sol"));
+        let contract = |address: &str, dir: &str| Contract {
+            address: address.to_string(),
+            source_address: None,
+            name: Some("A".to_string()),
+            compiler_version: Some("v0.8.19+commit.7dd6d404".to_string()),
+            settings: Some(serde_json::json!({ "optimizer": { "enabled": true, "runs": 200 } })),
+            license: Some("MIT".to_string()),
+            source_dir: dir.to_string(),
+            options: SourceOptions::default(),
+        };
+        write(root, "eth", 1, "https://api.etherscan.io/api", vec![contract("0xaaaa", "src/A_0xaaaa")], UNIX_EPOCH).unwrap();
+
+        let first = Manifest::read(root).unwrap();
+        assert_eq!(first.files["src/A_0xaaaa/A.sol"], sha256(b"contract A {}"));
+        assert_eq!(first.contracts, [contract("0xaaaa", "src/A_0xaaaa")]);
+        assert_eq!(Manifest::from_json(&first.to_json()).unwrap(), first);
+
+        std::fs::write(root.join("B.sol"), "contract B {}").unwrap();
+        record(&root.join("B.sol"));
+        let replayed = Contract {
+            source_address: Some("0x0ccc".to_string()),
+            options: SourceOptions { contract_name: Some("B".to_string()), normalize_eol: true, ..SourceOptions::default() },
+            ..contract("0x0bbb", "src")
+        };
+        write(root, "eth", 1, "https://api.etherscan.io/api", vec![replayed.clone()], UNIX_EPOCH).unwrap();
+        let merged = Manifest::read(root).unwrap();
+        assert_eq!(merged.contracts.iter().map(|c| c.address.as_str()).collect::<Vec<_>>(), ["0x0bbb", "0xaaaa"]);
+        assert_eq!(merged.contracts[0], replayed);
+        assert_eq!(merged.contracts[1].to_json().get("options"), None);
+        assert_eq!(merged.files.keys().collect::<Vec<_>>(), ["B.sol", "src/A_0xaaaa/A.sol"]);
+        assert_eq!(
+            std::fs::read_to_string(root.join(FILE)).unwrap(),
+            serde_json::to_string_pretty(&merged.to_json()).unwrap() + "\n"
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// Whether progress is drawn at all. Off until [`init`] turns it on, so tests and
+/// non-interactive runs never see it.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Every bar and spinner is drawn through this, which lets log lines be printed above
+/// them instead of through them.
+static BARS: LazyLock<MultiProgress> = LazyLock::new(MultiProgress::new);
+
+pub fn init(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Runs `f` with the bars hidden, for writing log lines.
+pub fn suspend<R>(f: impl FnOnce() -> R) -> R {
+    if ENABLED.load(Ordering::Relaxed) {
+        BARS.suspend(f)
+    } else {
+        f()
+    }
+}
+
+/// A spinner or bar, cleared when dropped. Does nothing while progress is disabled.
+pub struct Progress(Option<ProgressBar>);
+
+impl Progress {
+    pub fn inc(&self) {
+        if let Some(bar) = &self.0 {
+            bar.inc(1);
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        if let Some(bar) = &self.0 {
+            bar.finish_and_clear();
+            BARS.remove(bar);
+        }
+    }
+}
+
+/// A spinner showing `message` for a step of unknown length.
+pub fn spinner(message: impl Into<String>) -> Progress {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return Progress(None);
+    }
+    let bar = BARS.add(ProgressBar::new_spinner().with_message(message.into()));
+    bar.set_style(ProgressStyle::with_template("{spinner} {msg} ({elapsed})").expect("valid template"));
+    bar.enable_steady_tick(Duration::from_millis(100));
+    Progress(Some(bar))
+}
+
+/// A bar counting `len` `unit`s, e.g. "writing 37/214 files".
+pub fn bar(len: usize, message: &str, unit: &str) -> Progress {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return Progress(None);
+    }
+    let template = format!("{{msg}} {{pos}}/{{len}} {} [{{bar:30}}]", unit);
+    let bar = BARS.add(ProgressBar::new(len as u64).with_message(message.to_string()));
+    bar.set_style(ProgressStyle::with_template(&template).expect("valid template").progress_chars("=> "));
+    Progress(Some(bar))
+}
@@ -0,0 +1,195 @@
+use crate::error::TokenFetchError;
+use crate::manifest::{self, Manifest};
+use log::{info, warn};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// The README written when no `--readme-template` is given.
+pub const DEFAULT_TEMPLATE: &str = "# {{contract_name}}
+
+Verified source of [{{address}}]({{explorer_url}}) on {{chain}} (chain id {{chain_id}}), fetched with TokenFetch.
+
+- Compiler: {{compiler_version}}
+- Optimizer: {{optimizer}}
+- Proxy: {{proxy}}
+- License: {{license}}
+- Fetched: {{fetched_at}} with tokenfetch {{tool_version}}
+
+## Files
+
+{{file_count}} files in `{{source_dir}}`:
+
+```text
+{{file_tree}}
+```
+";
+
+/// Every placeholder a template may use.
+pub const PLACEHOLDERS: &[&str] = &[
+    "address",
+    "chain",
+    "chain_id",
+    "compiler_version",
+    "contract_name",
+    "explorer_url",
+    "fetched_at",
+    "file_count",
+    "file_tree",
+    "implementation",
+    "license",
+    "optimizer",
+    "proxy",
+    "source_dir",
+    "tool_version",
+];
+
+/// Beyond this many lines the file tree is cut short.
+const TREE_LINES: usize = 40;
+
+/// The `{{placeholders}}` in `template` that aren't among [`PLACEHOLDERS`].
+pub fn unknown_placeholders(template: &str) -> Vec<&str> {
+    template
+        .split("{{")
+        .skip(1)
+        .filter_map(|rest| rest.split_once("}}"))
+        .map(|(name, _)| name.trim())
+        .filter(|name| !PLACEHOLDERS.contains(name))
+        .collect()
+}
+
+/// `template` with each `{{placeholder}}` replaced by its value. Unknown ones are left as
+/// they are.
+pub fn render(template: &str, values: &BTreeMap<&str, String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}") else { break };
+        let name = rest[start + 2..start + len].trim();
+        out.push_str(&rest[..start]);
+        match values.get(name) {
+            Some(value) => out.push_str(value),
+            None => out.push_str(&rest[start..start + len + 2]),
+        }
+        rest = &rest[start + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `paths` as an indented tree, each directory with its files before its subdirectories.
+fn file_tree(paths: &[&str]) -> String {
+    let mut lines = Vec::new();
+    let mut open: Vec<&str> = Vec::new();
+    let mut sorted = paths.to_vec();
+    // By directory components, so that `a-b/` doesn't split `a/` from `a/c/`
+    sorted.sort_by_key(|path| {
+        let (dir, file) = path.rsplit_once('/').unwrap_or(("", path));
+        (dir.split('/').filter(|part| !part.is_empty()).collect::<Vec<_>>(), file)
+    });
+    for path in sorted {
+        let parts: Vec<&str> = path.split('/').collect();
+        let (dirs, file) = parts.split_at(parts.len() - 1);
+        let common = open.iter().zip(dirs).take_while(|(a, b)| a == b).count();
+        open.truncate(common);
+        for dir in &dirs[common..] {
+            lines.push(format!("{}{}/", "  ".repeat(open.len()), dir));
+            open.push(dir);
+        }
+        lines.push(format!("{}{}", "  ".repeat(open.len()), file[0]));
+    }
+    if lines.len() > TREE_LINES {
+        let more = lines.len() - TREE_LINES;
+        lines.truncate(TREE_LINES);
+        lines.push(format!("… and {} more", more));
+    }
+    lines.join("\n")
+}
+
+/// The placeholder values for one contract of `manifest`, with `meta` as `info` reports it.
+pub fn values(manifest: &Manifest, contract: &manifest::Contract, meta: &serde_json::Value, explorer_host: &str) -> BTreeMap<&'static str, String> {
+    let text = |key: &str| meta[key].as_str().unwrap_or("unknown").to_string();
+    let prefix = format!("{}/", contract.source_dir);
+    let files: Vec<&str> = manifest.files.keys().filter_map(|path| path.strip_prefix(&prefix)).collect();
+    let optimizer = &meta["optimizer"];
+    let optimizer = match (optimizer["enabled"].as_bool(), optimizer["runs"].as_u64()) {
+        (Some(true), Some(runs)) => format!("enabled, {} runs", runs),
+        (Some(true), None) => "enabled".to_string(),
+        (Some(false), _) => "disabled".to_string(),
+        (None, _) => "unknown".to_string(),
+    };
+    let implementation = meta["implementation"].as_str().unwrap_or_default().to_string();
+    let proxy = match (meta["proxy"].as_bool(), implementation.as_str()) {
+        (Some(true), "") => "yes".to_string(),
+        (Some(true), implementation) => format!("yes, implementation {}", implementation),
+        _ => "no".to_string(),
+    };
+    let license = match (meta["license"].as_str(), contract.license.as_deref()) {
+        (Some(license), Some(spdx)) if license != spdx => format!("{} ({})", license, spdx),
+        (Some(license), _) => license.to_string(),
+        (None, _) => "not specified".to_string(),
+    };
+    BTreeMap::from([
+        ("address", contract.address.clone()),
+        ("chain", manifest.chain.clone()),
+        ("chain_id", manifest.chain_id.to_string()),
+        ("compiler_version", text("compiler_version")),
+        ("contract_name", contract.name.clone().unwrap_or_else(|| contract.address.clone())),
+        ("explorer_url", format!("https://{}/address/{}#code", explorer_host, contract.address)),
+        ("fetched_at", manifest.fetched_at.clone()),
+        ("file_count", files.len().to_string()),
+        ("file_tree", file_tree(&files)),
+        ("implementation", implementation),
+        ("license", license),
+        ("optimizer", optimizer),
+        ("proxy", proxy),
+        ("source_dir", contract.source_dir.clone()),
+        ("tool_version", manifest.tool_version.clone()),
+    ])
+}
+
+/// Writes `README.md` at `root`, rendering `template` once per contract.
+pub fn write(root: &Path, template: &str, sections: &[BTreeMap<&str, String>]) -> Result<(), TokenFetchError> {
+    let readme: Vec<String> = sections.iter().map(|values| render(template, values)).collect();
+    let path = root.join("README.md");
+    std::fs::write(&path, readme.join("\n"))?;
+    crate::summary::file_written(&path);
+    info!("Wrote {:?}", path);
+    Ok(())
+}
+
+/// Reads a `--readme-template`, warning about placeholders it doesn't know.
+pub fn read_template(path: &Path) -> Result<String, TokenFetchError> {
+    let template = std::fs::read_to_string(path)
+        .map_err(|e| crate::error::TokenFetchError::InvalidInput(format!("Can't read --readme-template {}: {}", path.display(), e)))?;
+    for name in unknown_placeholders(&template) {
+        warn!("Unknown placeholder {{{{{}}}}} in {}, it's left as is", name, path.display());
+    }
+    Ok(template)
+}
+
+/// Whether the project at `root` has a README of its own, in any of the usual spellings.
+pub fn exists(root: &Path) -> bool {
+    std::fs::read_dir(root).into_iter().flatten().flatten().any(|entry| {
+        let name = entry.file_name().to_string_lossy().to_lowercase();
+        name == "readme" || name.starts_with("readme.")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_placeholders_and_a_file_tree() {
+        let values = BTreeMap::from([("contract_name", "Token".to_string()), ("chain", "eth".to_string())]);
+        assert_eq!(render("# {{contract_name}} on {{ chain }}, {{nope}} {{", &values), "# Token on eth, {{nope}} {{");
+        assert_eq!(unknown_placeholders("{{chain}} {{nope}} {{ address }}"), ["nope"]);
+
+        let tree = file_tree(&["contracts/Token.sol", "@openzeppelin/token/ERC20.sol", "contracts/utils/Math.sol", "contracts-v2/A.sol", "Main.sol"]);
+        assert_eq!(tree, "Main.sol\n@openzeppelin/\n  token/\n    ERC20.sol\ncontracts/\n  Token.sol\n  utils/\n    Math.sol\ncontracts-v2/\n  A.sol");
+        let many: Vec<String> = (0..50).map(|i| format!("lib/F{:02}.sol", i)).collect();
+        let tree = file_tree(&many.iter().map(String::as_str).collect::<Vec<_>>());
+        assert_eq!(tree.lines().count(), TREE_LINES + 1);
+        assert!(tree.ends_with("… and 11 more"), "{}", tree);
+    }
+}
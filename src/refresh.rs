@@ -0,0 +1,115 @@
+use crate::manifest::{self, Manifest};
+use serde_json::{Map, Value};
+use std::path::Path;
+
+/// A file of the re-fetched source that differs from the clone on disk.
+#[derive(Debug, PartialEq)]
+pub struct Change {
+    /// Relative to the project root
+    pub path: String,
+    /// What's on disk, `None` for a file the source gained
+    pub old: Option<String>,
+    pub new: String,
+    /// Whether the file on disk was edited since the clone, so overwriting it loses work
+    pub locally_modified: bool,
+}
+
+/// How a contract's re-fetched source compares with its clone.
+#[derive(Debug, Default, PartialEq)]
+pub struct Plan {
+    pub changes: Vec<Change>,
+    /// Files the clone wrote that the source no longer has
+    pub removed: Vec<String>,
+    /// Files the clone wrote that were edited since, whether or not the source changed them
+    pub locally_modified: Vec<String>,
+}
+
+/// Compares the `sources` re-fetched for the contract cloned into `source_dir` with the
+/// files on disk. Whether a file changed upstream is decided against the hash the
+/// manifest recorded, so a file that was only edited locally is not reported as changed.
+pub fn plan(root: &Path, manifest: &Manifest, source_dir: &str, sources: &Map<String, Value>) -> Plan {
+    let prefix = format!("{}/", source_dir);
+    let edited = |path: &str, bytes: &[u8]| manifest.files.get(path).is_some_and(|hash| *hash != manifest::sha256(bytes));
+
+    let mut plan = Plan::default();
+    for (path, hash) in manifest.files.iter().filter(|(path, _)| path.starts_with(&prefix)) {
+        // A merged clone's LICENSE sits among its sources without being one of them
+        let key = &path[prefix.len()..];
+        if !sources.contains_key(key) && key != "LICENSE" {
+            plan.removed.push(path.clone());
+        }
+        if std::fs::read(root.join(path)).is_ok_and(|bytes| manifest::sha256(&bytes) != *hash) {
+            plan.locally_modified.push(path.clone());
+        }
+    }
+    for (key, source) in sources {
+        let Some(new) = source["content"].as_str() else { continue };
+        let path = format!("{}{}", prefix, key);
+        let old = std::fs::read(root.join(&path)).ok();
+        let cloned_as_is = manifest.files.get(&path).is_some_and(|hash| *hash == manifest::sha256(new.as_bytes()));
+        if old.as_deref() == Some(new.as_bytes()) || cloned_as_is {
+            continue;
+        }
+        plan.changes.push(Change {
+            locally_modified: old.as_deref().is_some_and(|old| edited(&path, old)),
+            old: old.map(|old| String::from_utf8_lossy(&old).into_owned()),
+            new: new.to_string(),
+            path,
+        });
+    }
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn tells_upstream_changes_from_local_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src/utils")).unwrap();
+        let files = [
+            ("src/Token.sol", "contract Token {}"),
+            ("src/utils/Math.sol", "library Math {}"),
+            ("src/Old.sol", "contract Old {}"),
+            ("src/Same.sol", "contract Same {}"),
+            ("src/Mine.sol", "contract Mine {}"),
+            ("src/LICENSE", "MIT License"),
+        ];
+        for (path, content) in files {
+            std::fs::write(root.join(path), content).unwrap();
+        }
+        let manifest = Manifest {
+            chain: "eth".to_string(),
+            chain_id: 1,
+            explorer: String::new(),
+            fetched_at: String::new(),
+            tool_version: String::new(),
+            contracts: Vec::new(),
+            files: files.iter().map(|(path, content)| (path.to_string(), manifest::sha256(content.as_bytes()))).collect::<BTreeMap<_, _>>(),
+        };
+        // Edited by hand since the clone
+        std::fs::write(root.join("src/utils/Math.sol"), "library Math { /* mine */ }").unwrap();
+        std::fs::write(root.join("src/Mine.sol"), "contract Mine { /* mine */ }").unwrap();
+
+        let sources: Map<String, Value> = [
+            ("Token.sol", "contract Token { uint v2; }"),
+            ("utils/Math.sol", "library Math { uint constant X = 1; }"),
+            ("Same.sol", "contract Same {}"),
+            ("Mine.sol", "contract Mine {}"),
+            ("New.sol", "contract New {}"),
+        ]
+        .into_iter()
+        .map(|(path, content)| (path.to_string(), serde_json::json!({ "content": content })))
+        .collect();
+
+        let plan = plan(root, &manifest, "src", &sources);
+        let changed: Vec<(&str, bool, bool)> = plan.changes.iter().map(|c| (c.path.as_str(), c.old.is_some(), c.locally_modified)).collect();
+        assert_eq!(changed, [("src/New.sol", false, false), ("src/Token.sol", true, false), ("src/utils/Math.sol", true, true)]);
+        assert_eq!(plan.removed, ["src/Old.sol"]);
+        // Mine.sol is as upstream still has it, only edited here
+        assert_eq!(plan.locally_modified, ["src/Mine.sol", "src/utils/Math.sol"]);
+    }
+}
@@ -0,0 +1,275 @@
+use crate::error::TokenFetchError;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
+
+/// The wrapper around every answer from the explorer's `contract` and `account` modules.
+/// The `proxy` module answers like a JSON-RPC node instead and has no status.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct ExplorerEnvelope {
+    /// `1` on success, `0` on an error
+    #[serde(default, deserialize_with = "optional_text")]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub message: String,
+    /// The entries on success, usually a sentence on an error
+    #[serde(default)]
+    pub result: Value,
+}
+
+impl ExplorerEnvelope {
+    pub fn from_response(json: &Value) -> Result<Self, TokenFetchError> {
+        Ok(ExplorerEnvelope::deserialize(json)?)
+    }
+
+    /// The error the explorer reported, if the status says there was one.
+    pub fn error(&self) -> Option<TokenFetchError> {
+        if self.status.as_deref().is_none_or(|status| status == "1") {
+            return None;
+        }
+        let message = non_empty(&self.message).unwrap_or("Unknown error");
+        let result = self.result.as_str().unwrap_or("No additional info");
+        // getsourcecode says "No records found", getcontractcreation "No data found"
+        let nothing = |text: &&str| text.eq_ignore_ascii_case("No records found") || text.eq_ignore_ascii_case("No data found");
+        if [message, result].iter().any(nothing) {
+            return Some(TokenFetchError::NotFound);
+        }
+        Some(TokenFetchError::ExplorerApi {
+            message: format!("{} - {}", message, result),
+        })
+    }
+
+    /// The getsourcecode entries in `result`. An entry with a missing or mistyped field is
+    /// an error naming the entry and the field.
+    pub fn entries(&self) -> Result<Vec<SourceCodeResult>, TokenFetchError> {
+        let entries = self
+            .result
+            .as_array()
+            .ok_or_else(|| TokenFetchError::Other("No result array in response".into()))?;
+        entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                SourceCodeResult::deserialize(entry)
+                    .map_err(|e| TokenFetchError::Parse(serde_json::Error::custom(format!("result[{}]: {}", index, e))))
+            })
+            .collect()
+    }
+}
+
+/// What a `SourceCode` field holds, told apart by its shape. Explorers don't all lay
+/// standard-JSON input out the same: the sources may be under `sources`, one level
+/// further down in a wrapper object, or the whole document may be the sources
+/// themselves, keyed by path. Etherscan also wraps the input in an extra pair of braces.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SourceCode {
+    /// A single file, empty when the contract isn't verified
+    Flat(String),
+    /// Standard-JSON input
+    StandardJson {
+        language: Option<String>,
+        sources: Map<String, Value>,
+        settings: Option<Value>,
+    },
+    /// Source files keyed by path, with nothing around them
+    Files(Map<String, Value>),
+}
+
+impl Default for SourceCode {
+    fn default() -> Self {
+        SourceCode::Flat(String::new())
+    }
+}
+
+impl SourceCode {
+    pub fn parse(code: &str) -> Result<Self, serde_json::Error> {
+        // Some explorers prefix the JSON with a byte-order mark or whitespace
+        let trimmed = code.trim_start_matches('\u{feff}').trim_start();
+        if !trimmed.starts_with('{') {
+            return Ok(SourceCode::Flat(code.to_string()));
+        }
+        // Etherscan wraps standard-JSON input in one extra pair of braces; strip just that
+        // pair, since `{{` and `}}` also turn up inside the input and in source text
+        let trimmed = trimmed.trim_end();
+        let document: Value = match trimmed.starts_with("{{") && trimmed.ends_with("}}") {
+            true => serde_json::from_str(&trimmed[1..trimmed.len() - 1])?,
+            false => serde_json::from_str(trimmed)?,
+        };
+
+        let input = match document["sources"].is_object() {
+            true => Some(&document),
+            false => document.as_object().and_then(|document| document.values().find(|value| value["sources"].is_object())),
+        };
+        if let Some(input) = input {
+            return Ok(SourceCode::StandardJson {
+                language: input["language"].as_str().map(str::to_string),
+                sources: input["sources"].as_object().cloned().unwrap_or_default(),
+                settings: input.get("settings").cloned(),
+            });
+        }
+        match document {
+            Value::Object(files) if !files.is_empty() && files.values().all(|source| source["content"].is_string()) => {
+                Ok(SourceCode::Files(files))
+            }
+            _ => Err(serde_json::Error::custom("no sources object in the standard-JSON input")),
+        }
+    }
+
+    /// Whether the explorer sent no source at all, as for contracts that aren't verified.
+    pub fn is_empty(&self) -> bool {
+        matches!(self, SourceCode::Flat(code) if code.is_empty())
+    }
+}
+
+impl<'de> Deserialize<'de> for SourceCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        SourceCode::parse(&code).map_err(|e| D::Error::custom(format!("malformed `SourceCode`: {}", e)))
+    }
+}
+
+/// The explorer's record of a contract, the first entry of a getsourcecode response's
+/// `result`. Etherscan sends every field as a string, empty where it doesn't apply, so
+/// fields other explorers leave out are read as empty too.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SourceCodeResult {
+    pub source_code: SourceCode,
+    /// The ABI as a JSON string, or a message when the contract isn't verified
+    #[serde(rename = "ABI", default)]
+    pub abi: String,
+    #[serde(default)]
+    pub contract_name: String,
+    #[serde(default)]
+    pub compiler_version: String,
+    /// `1` or `0`
+    #[serde(default, deserialize_with = "text")]
+    pub optimization_used: String,
+    #[serde(default, deserialize_with = "text")]
+    pub runs: String,
+    #[serde(rename = "EVMVersion", default)]
+    pub evm_version: String,
+    /// Linked libraries as `Name:address` pairs separated by `;`
+    #[serde(default)]
+    pub library: String,
+    #[serde(default)]
+    pub license_type: String,
+    /// `1` when the explorer has the contract marked as a proxy
+    #[serde(default, deserialize_with = "text")]
+    pub proxy: String,
+    #[serde(default)]
+    pub implementation: String,
+    /// ABI-encoded, without a `0x` prefix
+    #[serde(default)]
+    pub constructor_arguments: String,
+}
+
+impl SourceCodeResult {
+    /// Reads the first entry of a getsourcecode response. A missing or mistyped field is
+    /// an error naming it.
+    pub fn from_response(json: &serde_json::Value) -> Result<Self, TokenFetchError> {
+        let envelope = ExplorerEnvelope::from_response(json)?;
+        envelope
+            .entries()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| TokenFetchError::Other("No result array in response".into()))
+    }
+
+    pub fn is_proxy(&self) -> bool {
+        self.proxy == "1"
+    }
+}
+
+/// `value`, unless the explorer left it empty.
+pub fn non_empty(value: &str) -> Option<&str> {
+    Some(value).filter(|value| !value.is_empty())
+}
+
+/// A field some explorers send as a number or a boolean rather than the string Etherscan
+/// does, read as text.
+fn text<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(text) => text,
+        Value::Null => String::new(),
+        Value::Bool(flag) => if flag { "1" } else { "0" }.to_string(),
+        other => other.to_string(),
+    })
+}
+
+fn optional_text<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    Ok(Some(text(deserializer)?).filter(|text| !text.is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_first_result() {
+        let json = serde_json::json!({
+            "status": "1",
+            "result": [{ "SourceCode": "contract Token {}", "ContractName": "Token", "Runs": 200, "OptimizationUsed": true, "Proxy": "0" }]
+        });
+        let result = SourceCodeResult::from_response(&json).unwrap();
+        assert_eq!((result.contract_name.as_str(), result.runs.as_str(), result.optimization_used.as_str()), ("Token", "200", "1"));
+        assert!(!result.is_proxy());
+        assert_eq!(non_empty(&result.implementation), None);
+
+        let err = SourceCodeResult::from_response(&serde_json::json!({ "result": [{ "ContractName": "Token" }] })).unwrap_err();
+        assert!(err.to_string().contains("missing field `SourceCode`"), "{}", err);
+        let err = SourceCodeResult::from_response(&serde_json::json!({ "result": [{ "SourceCode": 1 }] })).unwrap_err();
+        assert!(err.to_string().contains("invalid type: integer `1`, expected a string"), "{}", err);
+        assert!(SourceCodeResult::from_response(&serde_json::json!({ "result": "Invalid API Key" })).is_err());
+    }
+
+    #[test]
+    fn tells_source_code_layouts_apart() {
+        let input = serde_json::json!({
+            "language": "Vyper",
+            "sources": { "Token.vy": { "content": "# token" } },
+            "settings": { "optimize": "gas" }
+        });
+        let standard = SourceCode::StandardJson {
+            language: Some("Vyper".into()),
+            sources: input["sources"].as_object().unwrap().clone(),
+            settings: Some(input["settings"].clone()),
+        };
+        assert_eq!(SourceCode::parse(&input.to_string()).unwrap(), standard);
+        assert_eq!(SourceCode::parse(&format!("\u{feff}{{{}}}", serde_json::to_string_pretty(&input).unwrap())).unwrap(), standard);
+        let wrapped = serde_json::json!({ "input": input });
+        assert_eq!(SourceCode::parse(&wrapped.to_string()).unwrap(), standard);
+        assert_eq!(SourceCode::parse(&input["sources"].to_string()).unwrap(), SourceCode::Files(input["sources"].as_object().unwrap().clone()));
+        assert_eq!(SourceCode::parse("contract Token {}").unwrap(), SourceCode::Flat("contract Token {}".into()));
+        assert!(SourceCode::parse("").unwrap().is_empty());
+
+        // Compact input has `}}` of its own, in the settings and in the sources
+        let compact = serde_json::json!({
+            "language": "Solidity",
+            "sources": { "A.sol": { "content": "contract A { function f() public { if (true) { } }}" } },
+            "settings": { "optimizer": { "details": {} }, "libraries": {} }
+        });
+        for code in [compact.to_string(), format!("{{{}}}", compact)] {
+            let SourceCode::StandardJson { sources, settings, .. } = SourceCode::parse(&code).unwrap() else { panic!("{}", code) };
+            assert_eq!(sources["A.sol"], compact["sources"]["A.sol"]);
+            assert_eq!(settings.unwrap(), compact["settings"]);
+        }
+
+        let err = SourceCodeResult::from_response(&serde_json::json!({ "result": [{ "SourceCode": "{\"sources\": " }] })).unwrap_err();
+        assert!(err.to_string().starts_with("Failed to parse JSON: result[0]: malformed `SourceCode`: EOF"), "{}", err);
+    }
+
+    #[test]
+    fn envelopes_report_explorer_errors() {
+        let envelope = ExplorerEnvelope::from_response(&serde_json::json!({ "status": "0", "message": "NOTOK", "result": "Invalid API Key" })).unwrap();
+        assert_eq!(envelope.error().unwrap().to_string(), "API error: NOTOK - Invalid API Key");
+        let envelope = ExplorerEnvelope::from_response(&serde_json::json!({ "status": 0, "message": "No records found", "result": [] })).unwrap();
+        assert!(matches!(envelope.error(), Some(TokenFetchError::NotFound)));
+        let envelope = ExplorerEnvelope::from_response(&serde_json::json!({ "status": "0", "message": "No data found", "result": [] })).unwrap();
+        assert!(matches!(envelope.error(), Some(TokenFetchError::NotFound)));
+        // Answers from the `proxy` module have no status
+        let envelope = ExplorerEnvelope::from_response(&serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": "0x" })).unwrap();
+        assert!(envelope.error().is_none());
+        assert!(ExplorerEnvelope::from_response(&serde_json::json!({ "status": "1", "message": 1 })).is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use crate::error::TokenFetchError;
+use log::{info, trace};
+
+/// Sends a JSON-RPC request and returns its `result`.
+pub async fn call(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    method: &str,
+    params: serde_json::Value,
+) -> Result<serde_json::Value, TokenFetchError> {
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params,
+    });
+
+    // The URL is left out of the logs and errors, as RPC URLs often carry a key in their path
+    trace!("RPC {} {}", method, params);
+    let res = client.post(rpc_url).json(&request).send().await.map_err(reqwest::Error::without_url)?;
+    let status = res.status();
+    trace!("RPC {} answered HTTP {}", method, status);
+    if !status.is_success() {
+        return Err(format!("RPC request {} failed with HTTP {}", method, status).into());
+    }
+
+    let mut json: serde_json::Value = res.json().await.map_err(reqwest::Error::without_url)?;
+    if let Some(error) = json.get("error") {
+        let message = error["message"].as_str().unwrap_or("Unknown error");
+        return Err(format!("RPC error from {}: {}", method, message).into());
+    }
+
+    Ok(json["result"].take())
+}
+
+/// Looks up the address of the contract created by the deployment transaction `tx_hash`.
+pub async fn deployed_address(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    tx_hash: &str,
+) -> Result<String, TokenFetchError> {
+    let receipt = call(client, rpc_url, "eth_getTransactionReceipt", serde_json::json!([tx_hash])).await?;
+    created_contract(tx_hash, &receipt)
+}
+
+/// The address of the contract created by `tx_hash`, from its transaction receipt.
+///
+/// A contract created by a factory, through `CREATE` or `CREATE2` from another contract,
+/// isn't in the receipt, which only has a `contractAddress` for a deployment transaction.
+pub fn created_contract(tx_hash: &str, receipt: &serde_json::Value) -> Result<String, TokenFetchError> {
+    if receipt.is_null() {
+        return Err(format!("Transaction {} not found, it may be pending or on another chain", tx_hash).into());
+    }
+
+    if receipt["status"].as_str() == Some("0x0") {
+        return Err(format!("Transaction {} reverted, so it didn't deploy a contract", tx_hash).into());
+    }
+
+    match receipt["contractAddress"].as_str() {
+        Some(address) => {
+            info!("Transaction {} deployed contract {}", tx_hash, address);
+            Ok(address.to_string())
+        }
+        None => Err(format!(
+            "Transaction {} did not create a contract itself; if a factory deployed it, pass the contract's address instead",
+            tx_hash
+        )
+        .into()),
+    }
+}
+
+/// Whether the contract at `address` reports supporting the ERC-165 interface `id`,
+/// given as `0x`-prefixed hex.
+pub async fn supports_interface(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    address: &str,
+    id: &str,
+) -> Result<bool, TokenFetchError> {
+    let data = crate::standard::supports_interface_call(id);
+    let result = call(client, rpc_url, "eth_call", serde_json::json!([{ "to": address, "data": data }, "latest"])).await?;
+    let result = result.as_str().ok_or_else(|| format!("Unexpected eth_call result: {}", result))?;
+    // A bool is returned as a 32-byte word
+    Ok(result.len() == 66 && result.ends_with('1') && result[2..65].chars().all(|c| c == '0'))
+}
+
+/// Returns the runtime code deployed at `address`, as a `0x`-prefixed hex string.
+pub async fn get_code(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    address: &str,
+) -> Result<String, TokenFetchError> {
+    let code = call(client, rpc_url, "eth_getCode", serde_json::json!([address, "latest"])).await?;
+    code.as_str()
+        .map(str::to_string)
+        .ok_or_else(|| format!("Unexpected eth_getCode result: {}", code).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const TX: &str = "0x1111111111111111111111111111111111111111111111111111111111111111";
+
+    async fn serve_receipt(receipt: serde_json::Value) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({ "method": "eth_getTransactionReceipt", "params": [TX] })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": receipt,
+            })))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn resolves_created_contract() {
+        let server = serve_receipt(serde_json::json!({
+            "status": "0x1",
+            "contractAddress": "0x2222222222222222222222222222222222222222",
+        }))
+        .await;
+
+        let address = deployed_address(&reqwest::Client::new(), &server.uri(), TX).await.unwrap();
+        assert_eq!(address, "0x2222222222222222222222222222222222222222");
+    }
+
+    #[tokio::test]
+    async fn errors_leave_out_the_url() {
+        let err = call(&reqwest::Client::new(), "http://127.0.0.1:1/v2/s3cretkey", "eth_chainId", serde_json::json!([])).await.unwrap_err();
+        assert!(!err.to_string().contains("s3cretkey"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn rejects_non_deployment_transaction() {
+        let server = serve_receipt(serde_json::json!({
+            "status": "0x1",
+            "contractAddress": null,
+        }))
+        .await;
+
+        let err = deployed_address(&reqwest::Client::new(), &server.uri(), TX).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("Transaction {} did not create a contract itself; if a factory deployed it, pass the contract's address instead", TX)
+        );
+    }
+}
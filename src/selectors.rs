@@ -0,0 +1,171 @@
+use crate::error::TokenFetchError;
+use alloy_primitives::{hex, keccak256};
+
+/// A function, event or custom error of an ABI with its selector.
+#[derive(Debug, PartialEq)]
+pub struct Selector {
+    /// `function`, `event` or `error`
+    pub kind: &'static str,
+    /// The first 4 bytes of the signature's hash, or all 32 for an event's topic0
+    pub selector: String,
+    /// Canonical signature, e.g. `transfer(address,uint256)`
+    pub signature: String,
+    /// For functions, `pure`, `view`, `nonpayable` or `payable`
+    pub state_mutability: Option<String>,
+}
+
+impl Selector {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "kind": self.kind,
+            "selector": self.selector,
+            "signature": self.signature,
+            "state_mutability": self.state_mutability,
+        })
+    }
+}
+
+/// The selectors of every function, event and error in `abi`: functions first, then
+/// errors, then events, each sorted by selector. Constructors, fallback and receive
+/// functions have none.
+///
+/// `abi` is the ABI array itself, or a compiler artifact holding it under `abi`.
+pub fn selectors(abi: &serde_json::Value) -> Result<Vec<Selector>, TokenFetchError> {
+    let items = abi
+        .as_array()
+        .or_else(|| abi["abi"].as_array())
+        .ok_or("The ABI is not a JSON array")?;
+
+    let mut selectors = Vec::new();
+    for item in items {
+        let kind = match item["type"].as_str() {
+            Some("function") => "function",
+            Some("event") => "event",
+            Some("error") => "error",
+            _ => continue,
+        };
+        let signature = signature(item).ok_or_else(|| format!("Malformed ABI entry: {}", item))?;
+        let hash = keccak256(signature.as_bytes());
+        let selector = match kind {
+            "event" => hex::encode_prefixed(hash),
+            _ => hex::encode_prefixed(&hash[..4]),
+        };
+        let state_mutability = (kind == "function").then(|| state_mutability(item));
+        selectors.push(Selector { kind, selector, signature, state_mutability });
+    }
+
+    let order = |kind: &str| ["function", "error", "event"].iter().position(|k| *k == kind);
+    selectors.sort_by(|a, b| (order(a.kind), &a.selector).cmp(&(order(b.kind), &b.selector)));
+    Ok(selectors)
+}
+
+/// The canonical signature of a function, event or error, such as
+/// `swap((address,uint256)[],bytes)`, from which its selector is hashed.
+fn signature(item: &serde_json::Value) -> Option<String> {
+    let name = item["name"].as_str()?;
+    let inputs = match item.get("inputs") {
+        Some(inputs) => inputs.as_array()?.iter().map(canonical_type).collect::<Option<Vec<_>>>()?,
+        None => Vec::new(),
+    };
+    Some(format!("{}({})", name, inputs.join(",")))
+}
+
+/// The canonical type of an ABI parameter. Structs are written out as the tuple of their
+/// components, keeping any array suffix (`tuple[2][]` becomes `(address,uint256)[2][]`),
+/// and the `uint`/`int` shorthands get their size.
+fn canonical_type(param: &serde_json::Value) -> Option<String> {
+    let ty = param["type"].as_str()?;
+    if let Some(suffix) = ty.strip_prefix("tuple") {
+        let components = param["components"]
+            .as_array()?
+            .iter()
+            .map(canonical_type)
+            .collect::<Option<Vec<_>>>()?;
+        return Some(format!("({}){}", components.join(","), suffix));
+    }
+
+    let base_len = ty.find('[').unwrap_or(ty.len());
+    let (base, suffix) = ty.split_at(base_len);
+    let base = match base {
+        "uint" => "uint256",
+        "int" => "int256",
+        "fixed" => "fixed128x18",
+        "ufixed" => "ufixed128x18",
+        base => base,
+    };
+    Some(format!("{}{}", base, suffix))
+}
+
+/// A function's state mutability, derived from the `constant` and `payable` flags of
+/// ABIs older than solc 0.4.16 that don't state it.
+fn state_mutability(item: &serde_json::Value) -> String {
+    if let Some(mutability) = item["stateMutability"].as_str() {
+        return mutability.to_string();
+    }
+    let mutability = match (item["constant"].as_bool(), item["payable"].as_bool()) {
+        (Some(true), _) => "view",
+        (_, Some(true)) => "payable",
+        _ => "nonpayable",
+    };
+    mutability.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn param(ty: &str) -> serde_json::Value {
+        serde_json::json!({ "name": "", "type": ty })
+    }
+
+    #[test]
+    fn computes_selectors_of_overloads_and_events() {
+        let abi = serde_json::json!([
+            { "type": "constructor", "inputs": [param("address")] },
+            { "type": "function", "name": "transfer", "inputs": [param("address"), param("uint256")], "stateMutability": "nonpayable" },
+            { "type": "function", "name": "safeTransferFrom", "inputs": [param("address"), param("address"), param("uint256")] },
+            {
+                "type": "function",
+                "name": "safeTransferFrom",
+                "inputs": [param("address"), param("address"), param("uint"), param("bytes")],
+                "constant": false,
+                "payable": true
+            },
+            { "type": "event", "name": "Transfer", "inputs": [param("address"), param("address"), param("uint256")] },
+            { "type": "error", "name": "InsufficientBalance", "inputs": [param("uint256"), param("uint256")] },
+            { "type": "fallback" }
+        ]);
+
+        let selectors = selectors(&abi).unwrap();
+        let lines: Vec<(&str, &str)> = selectors.iter().map(|s| (s.selector.as_str(), s.signature.as_str())).collect();
+        assert_eq!(
+            lines,
+            [
+                ("0x42842e0e", "safeTransferFrom(address,address,uint256)"),
+                ("0xa9059cbb", "transfer(address,uint256)"),
+                ("0xb88d4fde", "safeTransferFrom(address,address,uint256,bytes)"),
+                ("0xcf479181", "InsufficientBalance(uint256,uint256)"),
+                ("0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef", "Transfer(address,address,uint256)"),
+            ]
+        );
+        assert_eq!(selectors[0].state_mutability.as_deref(), Some("nonpayable"));
+        assert_eq!(selectors[2].state_mutability.as_deref(), Some("payable"));
+        assert_eq!(selectors[4].state_mutability, None);
+    }
+
+    #[test]
+    fn structs_become_tuples() {
+        let order = serde_json::json!({
+            "name": "orders",
+            "type": "tuple[]",
+            "components": [
+                param("address"),
+                { "name": "amounts", "type": "tuple[2]", "components": [param("uint128"), param("int")] }
+            ]
+        });
+        let abi = serde_json::json!({ "abi": [{ "type": "function", "name": "fill", "inputs": [order, param("bytes32[]")] }] });
+
+        let selectors = selectors(&abi).unwrap();
+        assert_eq!(selectors[0].signature, "fill((address,(uint128,int256)[2])[],bytes32[])");
+    }
+}
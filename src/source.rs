@@ -0,0 +1,573 @@
+use crate::error::TokenFetchError;
+use crate::response::{non_empty, SourceCode, SourceCodeResult};
+use crate::{license, progress};
+use log::{debug, info, trace, warn};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+/// Keeps only the characters of `name` that are safe in a directory name on every platform.
+pub fn safe_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect()
+}
+
+
+/// The parts of a verified contract needed to rebuild it locally.
+#[derive(Clone, Debug)]
+pub struct ContractSource {
+    /// Source files keyed by their path, each holding a `content` string
+    pub sources: serde_json::Map<String, serde_json::Value>,
+    /// Verified contract name, when the explorer reports one
+    pub contract_name: Option<String>,
+    /// Compiler version as reported by the explorer, e.g. `v0.8.19+commit.7dd6d404`
+    pub compiler_version: Option<String>,
+    /// The standard-JSON `settings` object, when verified that way
+    pub settings: Option<serde_json::Value>,
+    /// License the contract was verified under, as the explorer names it (e.g. `MIT`)
+    pub license: Option<String>,
+    /// Whether the optimizer was enabled, and the number of runs when known
+    pub optimizer: Option<(bool, Option<u64>)>,
+    /// Everything the explorer reported, including the fields not read above
+    pub entry: SourceCodeResult,
+}
+
+/// How [`ContractSource::write_sources`] writes the files.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WriteOptions<'a> {
+    /// Rewrite CRLF line endings as LF
+    pub normalize_eol: bool,
+    /// Strip the spaces and tabs ending each line
+    pub trim_trailing_whitespace: bool,
+    /// Where to note each file the write leaves in place
+    pub written: Option<&'a WrittenFiles>,
+}
+
+/// The files a write left in place, for a caller keeping track of them: the CLI lists
+/// them in its report and hashes them into its manifest.
+#[derive(Debug, Default)]
+pub struct WrittenFiles {
+    files: Mutex<Vec<(PathBuf, bool)>>,
+}
+
+impl WrittenFiles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add(&self, path: &Path, written: bool) {
+        self.files.lock().unwrap_or_else(|e| e.into_inner()).push((path.to_path_buf(), written));
+    }
+
+    /// Takes the files noted so far, each with whether it was written or already held
+    /// the same contents.
+    pub fn take(&self) -> Vec<(PathBuf, bool)> {
+        std::mem::take(&mut *self.files.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+impl ContractSource {
+    /// The source files keyed by their path, each holding a `content` string.
+    pub fn sources(&self) -> &serde_json::Map<String, serde_json::Value> {
+        &self.sources
+    }
+
+    /// Writes the source files under `root` at the paths they were verified with.
+    pub fn write_sources(&self, root: &Path, options: WriteOptions) -> Result<(), TokenFetchError> {
+        if !options.normalize_eol && !options.trim_trailing_whitespace {
+            return write_sources(root, &self.sources, options.written);
+        }
+        let mut sources = self.sources.clone();
+        for source in sources.values_mut() {
+            if let Some(content) = source["content"].as_str() {
+                source["content"] = normalize_whitespace(content, options.normalize_eol, options.trim_trailing_whitespace).into();
+            }
+        }
+        write_sources(root, &sources, options.written)
+    }
+
+    /// Logs the license and optimizer configuration the contract was verified with.
+    pub fn log_verification_info(&self) {
+        info!("License: {}", self.license.as_deref().unwrap_or("not specified"));
+        match self.optimizer {
+            Some((true, Some(runs))) => info!("Optimizer: enabled, {} runs", runs),
+            Some((true, None)) => info!("Optimizer: enabled"),
+            Some((false, _)) => info!("Optimizer: disabled"),
+            None => {}
+        }
+    }
+}
+
+/// Parses the explorer's flat `Library` field, such as `SafeMath:0x5b38...;Math:7a25...`,
+/// into library names and `0x`-prefixed addresses.
+pub fn parse_library_field(value: &str) -> Vec<(String, String)> {
+    value
+        .split([';', ','])
+        .filter_map(|entry| {
+            let (name, address) = entry.trim().split_once(':')?;
+            let address = address.trim();
+            let address = if address.starts_with("0x") { address.to_string() } else { format!("0x{}", address) };
+            Some((name.trim().to_string(), address))
+        })
+        .filter(|(name, address)| !name.is_empty() && address.len() > 2)
+        .collect()
+}
+
+/// Whether the relative source path `key` names a file under the directory it's written to.
+fn stays_inside(key: &str) -> bool {
+    let path = Path::new(key);
+    !key.starts_with(['/', '\\'])
+        && path.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        && key.split(['/', '\\']).all(|part| part != "..")
+}
+
+/// Finds the source file declaring `<keyword> <name>`, e.g. `library SafeMath`.
+pub fn declaring_file(sources: &serde_json::Map<String, serde_json::Value>, keyword: &str, name: &str) -> Option<String> {
+    let declaration = format!("{} {}", keyword, name);
+    sources
+        .iter()
+        .find(|(_, source)| {
+            source["content"].as_str().is_some_and(|content| {
+                content.match_indices(&declaration).any(|(idx, _)| {
+                    content[idx + declaration.len()..]
+                        .chars()
+                        .next()
+                        .is_some_and(|c| c.is_whitespace() || c == '{')
+                })
+            })
+        })
+        .map(|(file, _)| file.clone())
+}
+
+/// The extension for source files in `language`, falling back to the compiler version
+/// (`vyper:0.3.10`) when there is no language field, as for single files.
+fn source_extension(language: Option<&str>, compiler_version: Option<&str>) -> &'static str {
+    let vyper = match language {
+        Some(language) => language.eq_ignore_ascii_case("vyper"),
+        None => compiler_version.is_some_and(|version| version.to_ascii_lowercase().starts_with("vyper")),
+    };
+    if vyper { "vy" } else { "sol" }
+}
+
+/// `sources` with an extension added to any name an explorer left it off, since source
+/// names are paths with one.
+fn with_extensions(sources: &serde_json::Map<String, serde_json::Value>, extension: &str) -> serde_json::Map<String, serde_json::Value> {
+    sources
+        .iter()
+        .map(|(file, source)| match Path::new(file).extension() {
+            Some(_) => (file.clone(), source.clone()),
+            None => (format!("{}.{}", file, extension), source.clone()),
+        })
+        .collect()
+}
+
+/// Pulls the source files out of a getsourcecode response, keyed by their path.
+pub fn extract_sources(json: &serde_json::Value) -> Result<ContractSource, TokenFetchError> {
+    ContractSource::from_entry(SourceCodeResult::from_response(json)?)
+}
+
+impl ContractSource {
+    /// Pulls the source files out of the explorer's already-read record of the contract.
+    pub fn from_entry(result: SourceCodeResult) -> Result<Self, TokenFetchError> {
+        if result.source_code.is_empty() {
+            return Err(TokenFetchError::NotVerified);
+        }
+
+        let contract_name = non_empty(&result.contract_name).map(str::to_string);
+        let compiler_version = non_empty(&result.compiler_version).map(str::to_string);
+        let license = non_empty(&result.license_type).map(str::to_string);
+
+        let (sources, mut settings) = match &result.source_code {
+            SourceCode::StandardJson { language, sources, settings } => {
+                (with_extensions(sources, source_extension(language.as_deref(), compiler_version.as_deref())), settings.clone())
+            }
+            SourceCode::Files(sources) => (with_extensions(sources, source_extension(None, compiler_version.as_deref())), None),
+            SourceCode::Flat(source_code) => {
+                let extension = source_extension(None, compiler_version.as_deref());
+                // Handle single file format, keeping the license visible in the file itself
+                let content = match license.as_deref().and_then(license::spdx_identifier) {
+                    Some(spdx) if !source_code.contains("SPDX-License-Identifier") => {
+                        let comment = if extension == "vy" { "#" } else { "//" };
+                        format!("{} SPDX-License-Identifier: {}\n{}", comment, spdx, source_code)
+                    }
+                    _ => source_code.to_string(),
+                };
+                // Name the file after the contract, as forge and most tooling expect
+                let file_name = match contract_name.as_deref() {
+                    Some(name) if safe_name(name) == name => format!("{}.{}", name, extension),
+                    _ => format!("Single.{}", extension),
+                };
+                let mut map = serde_json::Map::new();
+                map.insert(
+                    file_name,
+                    serde_json::json!({
+                        "content": content
+                    }),
+                );
+                (map, None)
+            }
+        };
+
+        // Linked libraries may only be listed in the flat `Library` field; move them into
+        // the settings so they're applied like standard-JSON `libraries`
+        let linked = parse_library_field(&result.library);
+        let has_libraries = settings
+            .as_ref()
+            .and_then(|s| s["libraries"].as_object())
+            .is_some_and(|libraries| !libraries.is_empty());
+        if !linked.is_empty() && !has_libraries {
+            let settings = settings.get_or_insert_with(|| serde_json::json!({}));
+            if let Some(settings) = settings.as_object_mut() {
+                let mut libraries = serde_json::Map::new();
+                for (name, address) in linked {
+                    match declaring_file(&sources, "library", &name) {
+                        Some(file) => {
+                            let entry = libraries.entry(file).or_insert_with(|| serde_json::json!({}));
+                            entry[name.as_str()] = serde_json::Value::String(address);
+                        }
+                        None => warn!("Linked library {} ({}) isn't declared in the verified sources, link it manually", name, address),
+                    }
+                }
+                settings.insert("libraries".to_string(), serde_json::Value::Object(libraries));
+            }
+        }
+
+        let optimizer = match settings.as_ref().map(|s| &s["optimizer"]) {
+            Some(optimizer) if optimizer["enabled"].is_boolean() => {
+                Some((optimizer["enabled"].as_bool() == Some(true), optimizer["runs"].as_u64()))
+            }
+            _ => non_empty(&result.optimization_used).map(|used| (used == "1", result.runs.parse().ok())),
+        };
+
+        Ok(ContractSource {
+            sources,
+            contract_name,
+            compiler_version,
+            settings,
+            license,
+            optimizer,
+            entry: result,
+        })
+    }
+}
+
+/// Strips the spaces and tabs ending each line with `trim`, then rewrites CRLF line
+/// endings as LF with `eol`. Anything else, including a lone CR, is kept byte for byte.
+pub fn normalize_whitespace(content: &str, eol: bool, trim: bool) -> String {
+    let mut content = content.to_string();
+    if trim {
+        content = content
+            .split('\n')
+            .map(|line| match line.strip_suffix('\r') {
+                Some(text) => format!("{}\r", text.trim_end_matches([' ', '\t'])),
+                None => line.trim_end_matches([' ', '\t']).to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+    if eol {
+        content = content.replace("\r\n", "\n");
+    }
+    content
+}
+
+/// Writes each source file under `src_path`, creating intermediate directories.
+///
+/// The paths come from whoever verified the contract, so one that is absolute or climbs
+/// out with `..` is refused before anything is written. Each file left in place is noted
+/// in `written`, when given, including one that already held the same contents.
+pub fn write_sources(src_path: &Path, sources: &serde_json::Map<String, serde_json::Value>, written: Option<&WrittenFiles>) -> Result<(), TokenFetchError> {
+    if let Some(key) = sources.keys().find(|key| !stays_inside(key)) {
+        return Err(TokenFetchError::InvalidInput(format!("Refusing to write source {:?}, which points outside {:?}", key, src_path)));
+    }
+    std::fs::create_dir_all(src_path)?;
+
+    let progress = progress::bar(sources.len(), "Writing", "files");
+    for (key, value) in sources {
+        progress.inc();
+        let mut file_path = src_path.to_path_buf();
+        
+        let mut parts: Vec<&str> = key.split('/').collect();
+        let file_name = parts.pop().unwrap_or_default();
+        for dir in &parts {
+            file_path.push(dir);
+            std::fs::create_dir_all(&file_path)?;
+            info!("Created directory: {:?}", file_path);
+        }
+        
+        file_path.push(file_name);
+        
+        if let Some(content) = value["content"].as_str() {
+            // Contracts merged into one project often share dependency files, e.g. a proxy
+            // and its implementation built against the same OpenZeppelin release
+            match std::fs::read(&file_path) {
+                Ok(existing) if existing == content.as_bytes() => {
+                    debug!("{:?} is already there with the same contents", file_path);
+                    if let Some(written) = written {
+                        written.add(&file_path, false);
+                    }
+                    continue;
+                }
+                Ok(_) => warn!("Overwriting existing file with different contents: {:?}", file_path),
+                Err(_) => {}
+            }
+            info!("Creating file: {:?}", file_path);
+            let mut file = std::fs::File::create(&file_path)?;
+            file.write_all(content.as_bytes())?;
+            trace!("Wrote {} bytes to {:?}", content.len(), file_path);
+            if let Some(written) = written {
+                written.add(&file_path, true);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_the_sources_at_their_verified_paths() {
+        let json = serde_json::json!({
+            "status": "1",
+            "result": [{
+                "SourceCode": "{\"language\":\"Solidity\",\"sources\":{\"contracts/Token.sol\":{\"content\":\"contract Token {}  \\r\\n\"}}}",
+                "ContractName": "Token",
+                "LicenseType": "MIT"
+            }]
+        });
+        let contract = extract_sources(&json).unwrap();
+        assert_eq!(contract.sources().keys().collect::<Vec<_>>(), ["contracts/Token.sol"]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let written = WrittenFiles::new();
+        let options = WriteOptions { normalize_eol: true, trim_trailing_whitespace: true, written: Some(&written) };
+        contract.write_sources(dir.path(), options).unwrap();
+        let path = dir.path().join("contracts/Token.sol");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "contract Token {}\n");
+        assert_eq!(written.take(), [(path.clone(), true)]);
+        contract.write_sources(dir.path(), options).unwrap();
+        assert_eq!(written.take(), [(path, false)]);
+        assert!(matches!(extract_sources(&serde_json::json!({ "result": [{ "SourceCode": "" }] })), Err(TokenFetchError::NotVerified)));
+    }
+
+    #[test]
+    fn refuses_paths_outside_the_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("src");
+        for key in ["../Evil.sol", "contracts/../../Evil.sol", "/tmp/Evil.sol", "..\\Evil.sol"] {
+            let mut sources = serde_json::Map::new();
+            sources.insert("Token.sol".to_string(), serde_json::json!({ "content": "contract Token {}" }));
+            sources.insert(key.to_string(), serde_json::json!({ "content": "contract Evil {}" }));
+            let err = write_sources(&root, &sources, None).unwrap_err();
+            assert!(matches!(err, TokenFetchError::InvalidInput(_)), "{}: {:?}", key, err);
+            assert!(!root.exists(), "{}", key);
+        }
+    }
+
+    fn response(source_code: &str) -> serde_json::Value {
+        serde_json::json!({
+            "status": "1",
+            "message": "OK",
+            "result": [{ "SourceCode": source_code, "ABI": "[]", "ContractName": "Token", "CompilerVersion": "v0.8.19+commit.7dd6d404" }]
+        })
+    }
+
+    fn standard_json() -> serde_json::Value {
+        serde_json::json!({
+            "language": "Solidity",
+            "sources": {
+                "contracts/Token.sol": { "content": "import \"@openzeppelin/contracts/token/ERC20/ERC20.sol\";\ncontract Token {}" },
+                "@openzeppelin/contracts/token/ERC20/ERC20.sol": { "content": "contract ERC20 {}" }
+            },
+            "settings": { "optimizer": { "enabled": true, "runs": 200 } }
+        })
+    }
+
+    /// Extracts the sources from `json` and writes them into a temp dir.
+    fn write_response(json: serde_json::Value) -> Result<tempfile::TempDir, TokenFetchError> {
+        let contract = extract_sources(&json)?;
+        let dir = tempfile::tempdir()?;
+        contract.write_sources(&dir.path().join("src"), WriteOptions::default())?;
+        Ok(dir)
+    }
+
+    #[test]
+    fn contract_names_become_safe_directory_names() {
+        assert_eq!(safe_name("Token"), "Token");
+        assert_eq!(safe_name("../My Token:v2"), "MyTokenv2");
+    }
+
+    #[test]
+    fn single_file_contract() {
+        let dir = write_response(response("pragma solidity ^0.8.0;\ncontract Token {}")).unwrap();
+
+        let content = std::fs::read_to_string(dir.path().join("src/Token.sol")).unwrap();
+        assert_eq!(content, "pragma solidity ^0.8.0;\ncontract Token {}");
+    }
+
+    #[test]
+    fn single_file_falls_back_to_a_generic_name() {
+        for name in ["", "My Token"] {
+            let mut json = response("contract Token {}");
+            json["result"][0]["ContractName"] = name.into();
+            let contract = extract_sources(&json).unwrap();
+            assert!(contract.sources.contains_key("Single.sol"), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn identical_files_are_written_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let sources = |content: &str| {
+            let mut map = serde_json::Map::new();
+            map.insert("@openzeppelin/Ownable.sol".to_string(), serde_json::json!({ "content": content }));
+            map
+        };
+        let path = dir.path().join("@openzeppelin/Ownable.sol");
+
+        // Backdated well past any filesystem's timestamp granularity, so a rewrite would show
+        let backdated = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        let modified = || std::fs::metadata(&path).unwrap().modified().unwrap();
+        write_sources(dir.path(), &sources("contract Ownable {}"), None).unwrap();
+        std::fs::File::options().write(true).open(&path).unwrap().set_modified(backdated).unwrap();
+        write_sources(dir.path(), &sources("contract Ownable {}"), None).unwrap();
+        assert_eq!(modified(), backdated);
+
+        write_sources(dir.path(), &sources("contract Ownable { address owner; }"), None).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "contract Ownable { address owner; }");
+        assert_ne!(modified(), backdated);
+    }
+
+    #[test]
+    fn standard_json_contract() {
+        let source = serde_json::to_string_pretty(&standard_json()).unwrap();
+        let dir = write_response(response(&source)).unwrap();
+
+        let src = dir.path().join("src");
+        assert_eq!(
+            std::fs::read_to_string(src.join("@openzeppelin/contracts/token/ERC20/ERC20.sol")).unwrap(),
+            "contract ERC20 {}"
+        );
+        assert!(src.join("contracts/Token.sol").is_file());
+        assert!(!src.join("Single.sol").exists());
+    }
+
+    #[test]
+    fn standard_json_layouts_are_recognised() {
+        let settings = serde_json::json!({ "optimizer": { "enabled": true, "runs": 200 } });
+        let files = standard_json()["sources"].clone();
+        let layouts = [
+            ("nested under sources", standard_json(), true),
+            ("wrapped", serde_json::json!({ "input": standard_json() }), true),
+            ("bare sources", files.clone(), false),
+        ];
+        for (layout, source, has_settings) in layouts {
+            let contract = extract_sources(&response(&source.to_string())).unwrap();
+            assert_eq!(serde_json::Value::Object(contract.sources), files, "{}", layout);
+            assert_eq!(contract.settings.as_ref() == Some(&settings), has_settings, "{}", layout);
+        }
+
+        let err = extract_sources(&response(r#"{"language": "Solidity"}"#)).err().unwrap();
+        assert_eq!(err.to_string(), "Failed to parse JSON: result[0]: malformed `SourceCode`: no sources object in the standard-JSON input");
+    }
+
+    #[test]
+    fn vyper_sources_get_a_vy_extension() {
+        let source = serde_json::json!({
+            "language": "Vyper",
+            "sources": { "contracts/Vault": { "content": "# @version 0.3.10" } }
+        });
+        let contract = extract_sources(&response(&source.to_string())).unwrap();
+        assert!(contract.sources.contains_key("contracts/Vault.vy"));
+
+        let mut json = response("# @version 0.3.10");
+        json["result"][0]["CompilerVersion"] = "vyper:0.3.10".into();
+        json["result"][0]["LicenseType"] = "MIT".into();
+        let contract = extract_sources(&json).unwrap();
+        assert_eq!(contract.sources["Token.vy"]["content"], "# SPDX-License-Identifier: MIT\n# @version 0.3.10");
+    }
+
+    #[test]
+    fn double_braced_contract() {
+        // Etherscan wraps standard-JSON input in an extra pair of braces
+        let source = format!("{{{}}}", serde_json::to_string_pretty(&standard_json()).unwrap());
+        let dir = write_response(response(&source)).unwrap();
+
+        let src = dir.path().join("src");
+        assert!(src.join("contracts/Token.sol").is_file());
+        assert!(src.join("@openzeppelin/contracts/token/ERC20/ERC20.sol").is_file());
+    }
+
+    #[test]
+    fn standard_json_after_byte_order_mark() {
+        let source = format!("\u{feff}{}", serde_json::to_string_pretty(&standard_json()).unwrap());
+        let dir = write_response(response(&source)).unwrap();
+
+        let src = dir.path().join("src");
+        assert!(src.join("contracts/Token.sol").is_file());
+        assert!(!src.join("Single.sol").exists());
+    }
+
+    #[test]
+    fn settings_are_extracted_from_standard_json() {
+        let source = serde_json::to_string_pretty(&standard_json()).unwrap();
+        let contract = extract_sources(&response(&source)).unwrap();
+
+        assert_eq!(contract.contract_name.as_deref(), Some("Token"));
+        assert_eq!(contract.compiler_version.as_deref(), Some("v0.8.19+commit.7dd6d404"));
+        assert_eq!(contract.optimizer, Some((true, Some(200))));
+        assert_eq!(contract.settings.unwrap()["optimizer"]["runs"], 200);
+    }
+
+    #[test]
+    fn flat_library_field_becomes_linked_libraries() {
+        let mut json = response("library SafeMath {}\nlibrary SafeMathX {}\ncontract Router {}");
+        json["result"][0]["Library"] = "SafeMathX:0x5b38da6a701c568545dcfcb03fcb875f56beddc4;Missing:0xab".into();
+        let contract = extract_sources(&json).unwrap();
+
+        let settings = contract.settings.unwrap();
+        assert_eq!(
+            settings["libraries"],
+            serde_json::json!({ "Token.sol": { "SafeMathX": "0x5b38da6a701c568545dcfcb03fcb875f56beddc4" } })
+        );
+        assert_eq!(
+            crate::foundry::build_args("src", None, Some(&settings)),
+            ["--libraries", "src/Token.sol:SafeMathX:0x5b38da6a701c568545dcfcb03fcb875f56beddc4"]
+        );
+    }
+
+    #[test]
+    fn single_file_gets_an_spdx_line_from_the_license() {
+        let mut json = response("pragma solidity ^0.8.0;\ncontract Token {}");
+        json["result"][0]["LicenseType"] = "GNU GPLv3".into();
+        let contract = extract_sources(&json).unwrap();
+        assert_eq!(
+            contract.sources["Token.sol"]["content"],
+            "// SPDX-License-Identifier: GPL-3.0\npragma solidity ^0.8.0;\ncontract Token {}"
+        );
+
+        // An existing SPDX line is left alone
+        let mut json = response("// SPDX-License-Identifier: MIT\ncontract Token {}");
+        json["result"][0]["LicenseType"] = "GNU GPLv3".into();
+        let contract = extract_sources(&json).unwrap();
+        assert_eq!(contract.sources["Token.sol"]["content"], "// SPDX-License-Identifier: MIT\ncontract Token {}");
+    }
+
+    #[test]
+    fn unverified_contract() {
+        let err = write_response(response("")).unwrap_err();
+        assert_eq!(err.to_string(), "Contract source code is empty, the contract might not be verified");
+    }
+
+    #[test]
+    fn whitespace_is_normalized_only_when_asked() {
+        let content = "contract A {  \r\n\tuint x;\t\r\n}\r  \n";
+        assert_eq!(normalize_whitespace(content, true, false), "contract A {  \n\tuint x;\t\n}\r  \n");
+        assert_eq!(normalize_whitespace(content, false, true), "contract A {\r\n\tuint x;\r\n}\r\n");
+        assert_eq!(normalize_whitespace(content, true, true), "contract A {\n\tuint x;\n}\n");
+    }
+}
@@ -0,0 +1,277 @@
+use crate::selectors;
+use std::collections::BTreeSet;
+
+/// The token standards a contract is classified as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Standard {
+    Erc20,
+    Erc721,
+    Erc1155,
+}
+
+impl Standard {
+    pub fn name(self) -> &'static str {
+        match self {
+            Standard::Erc20 => "ERC-20",
+            Standard::Erc721 => "ERC-721",
+            Standard::Erc1155 => "ERC-1155",
+        }
+    }
+}
+
+/// Functions a standard, or one of its optional extensions, requires, by their full
+/// signature so that a `transfer(address,uint256,bytes)` isn't taken for ERC-20's.
+struct Interface {
+    standard: Standard,
+    /// `None` for the standard itself
+    extension: Option<&'static str>,
+    functions: &'static [&'static str],
+    /// Its ERC-165 interface id, for the standards that have one
+    id: Option<&'static str>,
+}
+
+const INTERFACES: &[Interface] = &[
+    Interface {
+        standard: Standard::Erc1155,
+        extension: None,
+        functions: &[
+            "balanceOf(address,uint256)",
+            "balanceOfBatch(address[],uint256[])",
+            "setApprovalForAll(address,bool)",
+            "isApprovedForAll(address,address)",
+            "safeTransferFrom(address,address,uint256,uint256,bytes)",
+            "safeBatchTransferFrom(address,address,uint256[],uint256[],bytes)",
+        ],
+        id: Some("0xd9b67a26"),
+    },
+    Interface {
+        standard: Standard::Erc1155,
+        extension: Some("metadata"),
+        functions: &["uri(uint256)"],
+        id: Some("0x0e89341c"),
+    },
+    Interface {
+        standard: Standard::Erc721,
+        extension: None,
+        functions: &[
+            "balanceOf(address)",
+            "ownerOf(uint256)",
+            "safeTransferFrom(address,address,uint256,bytes)",
+            "safeTransferFrom(address,address,uint256)",
+            "transferFrom(address,address,uint256)",
+            "approve(address,uint256)",
+            "setApprovalForAll(address,bool)",
+            "getApproved(uint256)",
+            "isApprovedForAll(address,address)",
+        ],
+        id: Some("0x80ac58cd"),
+    },
+    Interface {
+        standard: Standard::Erc721,
+        extension: Some("metadata"),
+        functions: &["name()", "symbol()", "tokenURI(uint256)"],
+        id: Some("0x5b5e139f"),
+    },
+    Interface {
+        standard: Standard::Erc721,
+        extension: Some("enumerable"),
+        functions: &["totalSupply()", "tokenOfOwnerByIndex(address,uint256)", "tokenByIndex(uint256)"],
+        id: Some("0x780e9d63"),
+    },
+    Interface {
+        standard: Standard::Erc20,
+        extension: None,
+        functions: &[
+            "totalSupply()",
+            "balanceOf(address)",
+            "transfer(address,uint256)",
+            "transferFrom(address,address,uint256)",
+            "approve(address,uint256)",
+            "allowance(address,address)",
+        ],
+        id: None,
+    },
+    Interface {
+        standard: Standard::Erc20,
+        extension: Some("metadata"),
+        functions: &["name()", "symbol()", "decimals()"],
+        id: None,
+    },
+    Interface {
+        standard: Standard::Erc20,
+        extension: Some("permit"),
+        functions: &[
+            "permit(address,address,uint256,uint256,uint8,bytes32,bytes32)",
+            "nonces(address)",
+            "DOMAIN_SEPARATOR()",
+        ],
+        id: None,
+    },
+];
+
+/// Which token standard a contract follows, if any, and which of its optional
+/// extensions it has.
+#[derive(Debug, Default, PartialEq)]
+pub struct Classification {
+    pub standard: Option<Standard>,
+    pub extensions: Vec<&'static str>,
+}
+
+impl Classification {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "standard": self.standard.map(Standard::name),
+            "extensions": self.extensions,
+        })
+    }
+
+    /// Such as `ERC-20 with metadata, permit`.
+    pub fn describe(&self) -> String {
+        match (self.standard, self.extensions.is_empty()) {
+            (None, _) => "not a token".to_string(),
+            (Some(standard), true) => standard.name().to_string(),
+            (Some(standard), false) => format!("{} with {}", standard.name(), self.extensions.join(", ")),
+        }
+    }
+
+    /// Takes what the contract reports through ERC-165 over its ABI, which for a proxy
+    /// doesn't have the token's functions. Extensions found either way are kept.
+    pub fn with_supported(mut self, standard: Standard, extensions: Vec<&'static str>) -> Self {
+        if self.standard != Some(standard) {
+            self.standard = Some(standard);
+            self.extensions.clear();
+        }
+        for extension in extensions {
+            if !self.extensions.contains(&extension) {
+                self.extensions.push(extension);
+            }
+        }
+        self
+    }
+}
+
+fn functions(abi: &serde_json::Value) -> BTreeSet<String> {
+    selectors::selectors(abi)
+        .map(|selectors| {
+            selectors
+                .into_iter()
+                .filter(|selector| selector.kind == "function")
+                .map(|selector| selector.signature)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Classifies a contract by the functions in its ABI. ERC-1155 and ERC-721 are tried
+/// before ERC-20, which shares some of ERC-721's signatures.
+pub fn from_abi(abi: &serde_json::Value) -> Classification {
+    let functions = functions(abi);
+    let has = |interface: &Interface| interface.functions.iter().all(|function| functions.contains(*function));
+    let Some(standard) = INTERFACES.iter().find(|i| i.extension.is_none() && has(i)).map(|i| i.standard) else {
+        return Classification::default();
+    };
+    let extensions = INTERFACES
+        .iter()
+        .filter(|i| i.standard == standard && has(i))
+        .filter_map(|i| i.extension)
+        .collect();
+    Classification { standard: Some(standard), extensions }
+}
+
+/// Whether the ABI has ERC-165's `supportsInterface`, which is worth calling.
+pub fn has_supports_interface(abi: &serde_json::Value) -> bool {
+    functions(abi).contains("supportsInterface(bytes4)")
+}
+
+/// The ERC-165 interface ids of the standards that have one.
+pub fn standard_ids() -> impl Iterator<Item = (Standard, &'static str)> {
+    INTERFACES.iter().filter(|i| i.extension.is_none()).filter_map(|i| Some((i.standard, i.id?)))
+}
+
+/// The ERC-165 interface ids of the extensions of `standard`.
+pub fn extension_ids(standard: Standard) -> impl Iterator<Item = (&'static str, &'static str)> {
+    INTERFACES
+        .iter()
+        .filter(move |i| i.standard == standard)
+        .filter_map(|i| Some((i.extension?, i.id?)))
+}
+
+/// Calldata of `supportsInterface(id)`.
+pub fn supports_interface_call(id: &str) -> String {
+    format!("0x01ffc9a7{:0<64}", id.trim_start_matches("0x"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn abi(signatures: &[&str]) -> serde_json::Value {
+        signatures
+            .iter()
+            .map(|signature| {
+                let (name, params) = signature.trim_end_matches(')').split_once('(').unwrap();
+                let inputs: Vec<serde_json::Value> = params
+                    .split(',')
+                    .filter(|ty| !ty.is_empty())
+                    .map(|ty| serde_json::json!({ "name": "", "type": ty }))
+                    .collect();
+                serde_json::json!({ "type": "function", "name": name, "inputs": inputs })
+            })
+            .collect()
+    }
+
+    const ERC20: &[&str] = &[
+        "totalSupply()",
+        "balanceOf(address)",
+        "transfer(address,uint256)",
+        "transferFrom(address,address,uint256)",
+        "approve(address,uint256)",
+        "allowance(address,address)",
+    ];
+
+    #[test]
+    fn classifies_by_full_signatures() {
+        let mut token = ERC20.to_vec();
+        token.extend(["name()", "symbol()", "decimals()", "permit(address,address,uint256,uint256,uint8,bytes32,bytes32)", "nonces(address)", "DOMAIN_SEPARATOR()"]);
+        let classification = from_abi(&abi(&token));
+        assert_eq!(classification.describe(), "ERC-20 with metadata, permit");
+        assert_eq!(classification.to_json(), serde_json::json!({ "standard": "ERC-20", "extensions": ["metadata", "permit"] }));
+
+        // An ERC-223 style transfer is not ERC-20's
+        let mut not_erc20 = ERC20.to_vec();
+        not_erc20[2] = "transfer(address,uint256,bytes)";
+        assert_eq!(from_abi(&abi(&not_erc20)), Classification::default());
+
+        let nft = abi(&[
+            "balanceOf(address)",
+            "ownerOf(uint256)",
+            "safeTransferFrom(address,address,uint256,bytes)",
+            "safeTransferFrom(address,address,uint256)",
+            "transferFrom(address,address,uint256)",
+            "approve(address,uint256)",
+            "setApprovalForAll(address,bool)",
+            "getApproved(uint256)",
+            "isApprovedForAll(address,address)",
+            "totalSupply()",
+            "tokenOfOwnerByIndex(address,uint256)",
+            "tokenByIndex(uint256)",
+            "supportsInterface(bytes4)",
+        ]);
+        assert_eq!(from_abi(&nft).describe(), "ERC-721 with enumerable");
+        assert!(has_supports_interface(&nft));
+    }
+
+    #[test]
+    fn erc165_answers_win_over_the_abi() {
+        assert_eq!(
+            supports_interface_call("0x80ac58cd"),
+            "0x01ffc9a780ac58cd00000000000000000000000000000000000000000000000000000000"
+        );
+        assert_eq!(standard_ids().collect::<Vec<_>>(), [(Standard::Erc1155, "0xd9b67a26"), (Standard::Erc721, "0x80ac58cd")]);
+
+        let proxy = Classification::default().with_supported(Standard::Erc721, vec!["metadata"]);
+        assert_eq!(proxy.describe(), "ERC-721 with metadata");
+        let enumerable = Classification { standard: Some(Standard::Erc721), extensions: vec!["enumerable"] };
+        assert_eq!(enumerable.with_supported(Standard::Erc721, vec!["metadata"]).extensions, ["enumerable", "metadata"]);
+    }
+}
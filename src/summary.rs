@@ -0,0 +1,203 @@
+use crate::error::TokenFetchError;
+use crate::response::non_empty;
+use crate::source::ContractSource;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// What a `--json` run did, collected as it goes and printed as one document at the end.
+///
+/// This is process-wide like the logger, since files are written and warnings logged
+/// from all over; while `--json` is off every call here is a no-op.
+struct Report {
+    started: Instant,
+    chain: Option<String>,
+    path: Option<PathBuf>,
+    contracts: Vec<serde_json::Value>,
+    files: Vec<PathBuf>,
+    warnings: Vec<String>,
+}
+
+impl Report {
+    fn new() -> Self {
+        Report {
+            started: Instant::now(),
+            chain: None,
+            path: None,
+            contracts: Vec::new(),
+            files: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn add_file(&mut self, path: &Path) {
+        if !self.files.iter().any(|file| file == path) {
+            self.files.push(path.to_path_buf());
+        }
+    }
+
+    fn to_json(&self, result: Result<(), &TokenFetchError>) -> serde_json::Value {
+        let files: Vec<serde_json::Value> = self
+            .files
+            .iter()
+            .map(|path| {
+                let bytes = std::fs::metadata(path).map(|metadata| metadata.len()).ok();
+                serde_json::json!({ "path": path, "bytes": bytes })
+            })
+            .collect();
+        let mut doc = serde_json::json!({
+            "ok": result.is_ok(),
+            "chain": self.chain,
+            "path": self.path,
+            "contracts": self.contracts,
+            "files": files,
+            "warnings": self.warnings,
+            "elapsed_secs": self.started.elapsed().as_secs_f64(),
+        });
+        if let Err(e) = result {
+            doc["error"] = serde_json::json!({
+                "class": e.class(),
+                "exit_code": e.exit_code(),
+                "message": e.to_string(),
+            });
+        }
+        doc
+    }
+}
+
+static REPORT: Mutex<Option<Report>> = Mutex::new(None);
+
+fn with(f: impl FnOnce(&mut Report)) {
+    if let Some(report) = REPORT.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        f(report);
+    }
+}
+
+/// Starts collecting the report.
+pub fn enable() {
+    *REPORT.lock().unwrap_or_else(|e| e.into_inner()) = Some(Report::new());
+}
+
+/// Stops collecting, for commands that don't report this way.
+pub fn disable() {
+    *REPORT.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+pub fn enabled() -> bool {
+    REPORT.lock().unwrap_or_else(|e| e.into_inner()).is_some()
+}
+
+/// Records the chain and the project directory the run clones into.
+pub fn target(chain: &str, path: &Path) {
+    with(|report| {
+        report.chain = Some(chain.to_string());
+        report.path = Some(path.to_path_buf());
+    });
+}
+
+/// Records a contract written into the project.
+pub fn contract(address: &str, contract: &ContractSource) {
+    let entry = contract_entry(address, contract);
+    with(|report| report.contracts.push(entry));
+}
+
+fn contract_entry(address: &str, contract: &ContractSource) -> serde_json::Value {
+    serde_json::json!({
+        "address": address,
+        "ok": true,
+        "contract_name": contract.contract_name,
+        "compiler_version": contract.compiler_version,
+        "license": contract.license,
+        "optimizer": contract.optimizer.map(|(enabled, runs)| serde_json::json!({ "enabled": enabled, "runs": runs })),
+        "proxy": contract.entry.is_proxy(),
+        "implementation": non_empty(&contract.entry.implementation),
+    })
+}
+
+/// Adds the token standard a written contract was classified as to its entry.
+pub fn standard(address: &str, standard: serde_json::Value) {
+    with(|report| {
+        if let Some(entry) = report.contracts.iter_mut().find(|entry| entry["address"] == address) {
+            entry["standard"] = standard;
+        }
+    });
+}
+
+/// Records a contract of a batch that failed to clone.
+pub fn contract_failed(address: &str, error: &dyn std::error::Error) {
+    let entry = serde_json::json!({ "address": address, "ok": false, "error": error.to_string() });
+    with(|report| report.contracts.push(entry));
+}
+
+/// Records a file written into the project, which the manifest hashes whether or not
+/// `--json` is on.
+pub fn file_written(path: &Path) {
+    crate::manifest::record(path);
+    with(|report| report.add_file(path));
+}
+
+/// The report for a run that ended with `result`.
+pub fn finish(result: Result<(), &TokenFetchError>) -> serde_json::Value {
+    let guard = REPORT.lock().unwrap_or_else(|e| e.into_inner());
+    match guard.as_ref() {
+        Some(report) => report.to_json(result),
+        None => serde_json::Value::Null,
+    }
+}
+
+/// Wraps the logger to keep a copy of every warning for the report. Logs still go to
+/// stderr as usual, so stdout only carries the JSON document, and are drawn above any
+/// progress bars.
+pub struct Logger(pub env_logger::Logger);
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.0.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if record.level() == log::Level::Warn && self.0.matches(record) {
+            let message = record.args().to_string();
+            with(|report| report.warnings.push(message));
+        }
+        crate::progress::suspend(|| self.0.log(record));
+    }
+
+    fn flush(&self) {
+        self.0.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_contracts_files_and_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("Token.sol");
+        std::fs::write(&file, "contract Token {}").unwrap();
+
+        let mut report = Report::new();
+        report.chain = Some("eth".to_string());
+        let response = serde_json::json!({
+            "result": [{ "SourceCode": "contract Token {}", "ContractName": "Token", "LicenseType": "MIT", "OptimizationUsed": "1", "Runs": "200", "Proxy": "1", "Implementation": "0x22" }]
+        });
+        let contract = crate::source::extract_sources(&response).unwrap();
+        report.contracts.push(contract_entry("0x1111111111111111111111111111111111111111", &contract));
+        report.add_file(&file);
+        report.add_file(&file);
+
+        let doc = report.to_json(Err(&TokenFetchError::NotVerified));
+        assert_eq!(doc["ok"], false);
+        assert_eq!(doc["contracts"][0]["ok"], true);
+        assert_eq!(doc["contracts"][0]["contract_name"], "Token");
+        assert_eq!(doc["contracts"][0]["compiler_version"], serde_json::Value::Null);
+        assert_eq!(doc["contracts"][0]["license"], "MIT");
+        assert_eq!(doc["contracts"][0]["optimizer"], serde_json::json!({ "enabled": true, "runs": 200 }));
+        assert_eq!(doc["contracts"][0]["proxy"], true);
+        assert_eq!(doc["files"], serde_json::json!([{ "path": file, "bytes": 17 }]));
+        assert_eq!(doc["error"]["class"], "not_verified");
+        assert_eq!(doc["error"]["exit_code"], 4);
+    }
+}
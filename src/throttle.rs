@@ -0,0 +1,94 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::{Mutex, Semaphore, SemaphorePermit};
+use tokio::time::Instant;
+
+/// An explorer API plan, which sets how fast requests may be made. The limits are
+/// Etherscan's, which Basescan and most other Etherscan-style explorers share.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Tier {
+    Free,
+    Standard,
+    Advanced,
+    Professional,
+}
+
+impl Tier {
+    /// Requests per second the plan allows.
+    pub fn rps(self) -> u32 {
+        match self {
+            Tier::Free => 5,
+            Tier::Standard => 10,
+            Tier::Advanced => 20,
+            Tier::Professional => 30,
+        }
+    }
+
+    /// How many contracts a batch fetches at once by default, enough to keep the
+    /// limiter busy without piling up requests that only wait for a slot.
+    pub fn concurrency(self) -> u32 {
+        match self {
+            Tier::Free => 3,
+            Tier::Standard => 5,
+            Tier::Advanced => 10,
+            Tier::Professional => 15,
+        }
+    }
+}
+
+/// Bounds how many explorer requests are in flight and spaces them out to stay within
+/// the explorer's rate limit.
+pub struct Throttle {
+    permits: Semaphore,
+    interval: Duration,
+    next_slot: Mutex<Instant>,
+    requests: AtomicUsize,
+}
+
+impl Throttle {
+    pub fn new(concurrency: usize, requests_per_second: u32) -> Self {
+        Throttle {
+            permits: Semaphore::new(concurrency.max(1)),
+            interval: Duration::from_secs(1) / requests_per_second.max(1),
+            next_slot: Mutex::new(Instant::now()),
+            requests: AtomicUsize::new(0),
+        }
+    }
+
+    /// Waits for a free request slot. Hold the returned permit until the request is done.
+    pub async fn acquire(&self) -> SemaphorePermit<'_> {
+        let permit = self.permits.acquire().await.expect("throttle semaphore is never closed");
+
+        let slot = {
+            let mut next_slot = self.next_slot.lock().await;
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        permit
+    }
+
+    /// Number of requests sent through this throttle so far.
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn spaces_requests_across_tasks() {
+        let throttle = Throttle::new(4, 20);
+        let started = Instant::now();
+        futures::future::join_all((0..5).map(|_| async { drop(throttle.acquire().await) })).await;
+
+        // The first request goes out at once, the other four 50ms apart
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert_eq!(throttle.requests(), 5);
+    }
+}
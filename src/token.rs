@@ -0,0 +1,141 @@
+use alloy_primitives::{hex, U256};
+
+/// Calldata of the ERC-20 getters `token.json` is filled from when the explorer has no
+/// token endpoint for the chain.
+pub const NAME: &str = "0x06fdde03";
+pub const SYMBOL: &str = "0x95d89b41";
+pub const DECIMALS: &str = "0x313ce567";
+
+/// What the explorer knows of a token contract, written to `token.json`.
+#[derive(Debug, Default, PartialEq)]
+pub struct TokenInfo {
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub decimals: Option<u8>,
+    /// In the token's smallest unit, as a decimal string since it overflows a u64
+    pub total_supply: Option<String>,
+    /// Such as `ERC20` or `ERC721`, from explorers that report it
+    pub token_type: Option<String>,
+}
+
+impl TokenInfo {
+    /// Reads a `module=token&action=tokeninfo` result, which has the decimals as `divisor`.
+    pub fn from_tokeninfo(result: &serde_json::Value) -> Self {
+        let text = |key: &str| result[key].as_str().filter(|value| !value.is_empty()).map(str::to_string);
+        TokenInfo {
+            name: text("tokenName"),
+            symbol: text("symbol"),
+            decimals: text("divisor").and_then(|divisor| divisor.parse().ok()),
+            total_supply: text("totalSupply"),
+            token_type: text("tokenType"),
+        }
+    }
+
+    /// Whether none of it is known, as for a contract that isn't a token.
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.symbol.is_none() && self.decimals.is_none() && self.total_supply.is_none()
+    }
+
+    pub fn to_json(&self, address: &str) -> serde_json::Value {
+        let mut json = serde_json::json!({
+            "address": address,
+            "name": self.name,
+            "symbol": self.symbol,
+            "decimals": self.decimals,
+            "totalSupply": self.total_supply,
+        });
+        if let Some(token_type) = &self.token_type {
+            json["tokenType"] = token_type.as_str().into();
+        }
+        json
+    }
+}
+
+/// Whether an ABI has the functions every ERC-20 and ERC-721 token does, so that a clone of
+/// anything else doesn't ask the explorer about a token.
+pub fn looks_like_token(abi: &serde_json::Value) -> bool {
+    let has_function = |name: &str| {
+        abi.as_array()
+            .is_some_and(|items| items.iter().any(|item| item["type"] == "function" && item["name"] == name))
+    };
+    has_function("balanceOf") && (has_function("totalSupply") || has_function("ownerOf"))
+}
+
+fn words(data: &str) -> Option<Vec<u8>> {
+    let bytes = hex::decode(data.strip_prefix("0x").unwrap_or(data)).ok()?;
+    (!bytes.is_empty()).then_some(bytes)
+}
+
+/// Decodes the return data of `name()` or `symbol()`: an ABI-encoded string, or the
+/// `bytes32` some early tokens such as MKR return instead.
+pub fn decode_string(data: &str) -> Option<String> {
+    let bytes = words(data)?;
+    let text = if bytes.len() == 32 {
+        let end = bytes.iter().position(|byte| *byte == 0).unwrap_or(32);
+        bytes[..end].to_vec()
+    } else {
+        let offset = usize::try_from(U256::from_be_slice(bytes.get(..32)?)).ok()?;
+        let len_end = offset.checked_add(32)?;
+        let len = usize::try_from(U256::from_be_slice(bytes.get(offset..len_end)?)).ok()?;
+        bytes.get(len_end..len_end.checked_add(len)?)?.to_vec()
+    };
+    String::from_utf8(text).ok().filter(|text| !text.is_empty())
+}
+
+/// Decodes the return data of `decimals()`.
+pub fn decode_decimals(data: &str) -> Option<u8> {
+    u8::try_from(U256::from_be_slice(words(data)?.get(..32)?)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_getter_results() {
+        let symbol = concat!(
+            "0x0000000000000000000000000000000000000000000000000000000000000020",
+            "0000000000000000000000000000000000000000000000000000000000000004",
+            "5553445400000000000000000000000000000000000000000000000000000000"
+        );
+        assert_eq!(decode_string(symbol).as_deref(), Some("USDT"));
+        // MKR returns its symbol as bytes32
+        assert_eq!(
+            decode_string("0x4d4b520000000000000000000000000000000000000000000000000000000000").as_deref(),
+            Some("MKR")
+        );
+        assert_eq!(decode_string("0x"), None);
+        assert_eq!(decode_decimals("0x0000000000000000000000000000000000000000000000000000000000000012"), Some(18));
+        assert_eq!(decode_decimals("0x"), None);
+    }
+
+    #[test]
+    fn reads_explorer_token_info() {
+        let result = serde_json::json!({
+            "contractAddress": "0xdac17f958d2ee523a2206206994597c13d831ec7",
+            "tokenName": "Tether USD",
+            "symbol": "USDT",
+            "divisor": "6",
+            "tokenType": "ERC20",
+            "totalSupply": "39828710009874796",
+            "website": ""
+        });
+        let info = TokenInfo::from_tokeninfo(&result);
+        assert_eq!(info.decimals, Some(6));
+        assert_eq!(
+            info.to_json("0xdac17f958d2ee523a2206206994597c13d831ec7"),
+            serde_json::json!({
+                "address": "0xdac17f958d2ee523a2206206994597c13d831ec7",
+                "name": "Tether USD",
+                "symbol": "USDT",
+                "decimals": 6,
+                "totalSupply": "39828710009874796",
+                "tokenType": "ERC20"
+            })
+        );
+
+        let abi = |names: &[&str]| serde_json::Value::from_iter(names.iter().map(|name| serde_json::json!({ "type": "function", "name": name })));
+        assert!(looks_like_token(&abi(&["balanceOf", "totalSupply", "transfer"])));
+        assert!(!looks_like_token(&abi(&["deposit", "totalSupply"])));
+    }
+}
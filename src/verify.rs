@@ -0,0 +1,183 @@
+use crate::error::TokenFetchError;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Decodes a `0x`-prefixed hex string into bytes.
+pub fn decode_hex(value: &str) -> Result<Vec<u8>, TokenFetchError> {
+    let hex = value.trim().trim_start_matches("0x");
+    if !hex.is_ascii() || !hex.len().is_multiple_of(2) {
+        return Err("bytecode is not a valid hex string".into());
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map_err(|_| format!("invalid hex in bytecode at offset {}", i / 2).into())
+        })
+        .collect()
+}
+
+/// Drops the CBOR-encoded metadata solc appends to runtime code.
+///
+/// The last two bytes give the length of the CBOR map before them. The metadata holds
+/// source hashes, so it differs for any change in comments or whitespace and tells
+/// nothing about whether the code itself matches. Code without a trailer is returned as is.
+pub fn strip_metadata(code: &[u8]) -> &[u8] {
+    let Some(len_bytes) = code.len().checked_sub(2).map(|i| &code[i..]) else {
+        return code;
+    };
+    let cbor_len = u16::from_be_bytes([len_bytes[0], len_bytes[1]]) as usize;
+
+    match code.len().checked_sub(cbor_len + 2) {
+        // A CBOR map header is 0xa0-0xbf
+        Some(start) if cbor_len > 0 && (0xa0..=0xbf).contains(&code[start]) => &code[..start],
+        _ => code,
+    }
+}
+
+/// The code of an EIP-1167 minimal proxy around the 20-byte address it delegates to.
+const MINIMAL_PROXY_PREFIX: &[u8] = &[0x36, 0x3d, 0x3d, 0x37, 0x3d, 0x3d, 0x3d, 0x36, 0x3d, 0x73];
+const MINIMAL_PROXY_SUFFIX: &[u8] = &[0x5a, 0xf4, 0x3d, 0x82, 0x80, 0x3e, 0x90, 0x3d, 0x91, 0x60, 0x2b, 0x57, 0xfd, 0x5b, 0xf3];
+
+/// The address an EIP-1167 minimal proxy delegates every call to, if `code` is one.
+pub fn minimal_proxy_target(code: &[u8]) -> Option<alloy_primitives::Address> {
+    let target = code.strip_prefix(MINIMAL_PROXY_PREFIX)?.strip_suffix(MINIMAL_PROXY_SUFFIX)?;
+    (target.len() == 20).then(|| alloy_primitives::Address::from_slice(target))
+}
+
+/// Zeroes `ranges` in `code`, ignoring any that run past its end.
+///
+/// Immutable variables are filled in by the constructor, so the deployed code holds
+/// values where the compiler's output only has placeholders.
+pub fn mask(code: &mut [u8], ranges: &[Range<usize>]) {
+    for range in ranges {
+        if let Some(bytes) = code.get_mut(range.clone()) {
+            bytes.fill(0);
+        }
+    }
+}
+
+/// Returns the byte ranges where `local` and `onchain` differ, including any length difference.
+pub fn diff_ranges(local: &[u8], onchain: &[u8]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for offset in 0..local.len().max(onchain.len()) {
+        if local.get(offset) == onchain.get(offset) {
+            continue;
+        }
+        match ranges.last_mut() {
+            Some(last) if last.end == offset => last.end = offset + 1,
+            _ => ranges.push(offset..offset + 1),
+        }
+    }
+    ranges
+}
+
+/// The compiled runtime code of one contract, as forge wrote it to `out/`.
+pub struct Artifact {
+    pub path: PathBuf,
+    pub deployed_bytecode: Vec<u8>,
+    /// Offsets of immutable variables in the runtime code
+    pub immutables: Vec<Range<usize>>,
+}
+
+/// Finds and reads the artifact forge built for `contract_name` under `<root>/out`.
+///
+/// Forge names artifacts `<Name>.json`, or `<Name>.<solc version>.json` when several
+/// compiler versions are in use. A name defined in more than one file is an error,
+/// since there is no telling which one was deployed.
+pub fn find_artifact(root: &Path, contract_name: &str) -> Result<Artifact, TokenFetchError> {
+    let out = root.join("out");
+    let prefix = format!("{}.", contract_name);
+
+    let candidates: Vec<PathBuf> = WalkDir::new(&out)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| !entry.path().components().any(|c| c.as_os_str() == "build-info"))
+        .filter(|entry| {
+            entry.file_type().is_file()
+                && entry
+                    .file_name()
+                    .to_str()
+                    .is_some_and(|name| name.starts_with(&prefix) && name.ends_with(".json"))
+        })
+        .map(|entry| entry.into_path())
+        .collect();
+
+    let path = match candidates.as_slice() {
+        [] => return Err(format!("No artifact for {} under {:?}", contract_name, out).into()),
+        [path] => path.clone(),
+        paths => {
+            let paths: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+            return Err(format!("Several artifacts for {}:\n  {}", contract_name, paths.join("\n  ")).into());
+        }
+    };
+
+    let artifact: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+    let deployed = &artifact["deployedBytecode"];
+    let object = deployed["object"]
+        .as_str()
+        .ok_or_else(|| format!("No deployedBytecode in {:?}", path))?;
+    if object.contains("__$") {
+        return Err(format!("{} has unlinked library references", contract_name).into());
+    }
+
+    let immutables = deployed["immutableReferences"]
+        .as_object()
+        .into_iter()
+        .flat_map(|refs| refs.values())
+        .filter_map(|locations| locations.as_array())
+        .flatten()
+        .filter_map(|location| {
+            let start = location["start"].as_u64()? as usize;
+            let length = location["length"].as_u64()? as usize;
+            Some(start..start + length)
+        })
+        .collect();
+
+    Ok(Artifact {
+        path,
+        deployed_bytecode: decode_hex(object)?,
+        immutables,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_minimal_proxy_targets() {
+        let code = decode_hex("0x363d3d373d3d3d363d73bebebebebebebebebebebebebebebebebebebebe5af43d82803e903d91602b57fd5bf3").unwrap();
+        assert_eq!(minimal_proxy_target(&code).unwrap().to_string(), "0xBEbeBeBEbeBebeBeBEBEbebEBeBeBebeBeBebebe");
+        assert_eq!(minimal_proxy_target(&code[..44]), None);
+        assert_eq!(minimal_proxy_target(&decode_hex("0x6080604052").unwrap()), None);
+    }
+
+    #[test]
+    fn strips_cbor_metadata() {
+        // a2 64 'ipfs' ... followed by the 2-byte length of the map
+        let mut code = vec![0x60, 0x80, 0x60, 0x40];
+        let cbor = [0xa2, 0x64, b'i', b'p', b'f', b's', 0x42, 0x12, 0x34];
+        code.extend_from_slice(&cbor);
+        code.extend_from_slice(&(cbor.len() as u16).to_be_bytes());
+
+        assert_eq!(strip_metadata(&code), &[0x60, 0x80, 0x60, 0x40]);
+    }
+
+    #[test]
+    fn keeps_code_without_metadata() {
+        let code = [0x60, 0x80, 0x60, 0x40, 0x00, 0x02];
+        assert_eq!(strip_metadata(&code), &code);
+    }
+
+    #[test]
+    fn reports_differing_ranges() {
+        let local = [1, 2, 3, 4, 5, 6];
+        let onchain = [1, 9, 9, 4, 5, 6, 7, 8];
+
+        assert_eq!(diff_ranges(&local, &onchain), vec![1..3, 6..8]);
+        assert!(diff_ranges(&local, &local).is_empty());
+    }
+}
@@ -0,0 +1,257 @@
+//! Runs the binary against a mock explorer and checks the files it writes. The explorer
+//! is a `--custom-chain` pointed at the mock, and clones `--merge` into a project with a
+//! `foundry.toml` already in place, so forge isn't needed. The binary is run with
+//! `std::process::Command` on `CARGO_BIN_EXE_TokenFetch`, which is all `assert_cmd`
+//! would wrap here.
+
+use std::path::Path;
+use std::process::{Command, Output};
+use wiremock::matchers::{method, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const ADDRESS: &str = "0x1111111111111111111111111111111111111111";
+
+fn response(source_code: &str) -> serde_json::Value {
+    serde_json::json!({
+        "status": "1",
+        "message": "OK",
+        "result": [{
+            "SourceCode": source_code,
+            "ContractName": "Token",
+            "CompilerVersion": "v0.8.19+commit.7dd6d404",
+            "LicenseType": "MIT"
+        }]
+    })
+}
+
+/// An explorer answering getsourcecode with `responses` in turn, the last one from then on.
+async fn explorer(responses: &[serde_json::Value]) -> MockServer {
+    let server = MockServer::start().await;
+    let (last, first) = responses.split_last().expect("at least one response");
+    for json in first {
+        Mock::given(method("GET"))
+            .and(query_param("action", "getsourcecode"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+    }
+    Mock::given(method("GET"))
+        .and(query_param("action", "getsourcecode"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(last))
+        .mount(&server)
+        .await;
+    server
+}
+
+/// The binary, with the explorer at `server` as the `mock` chain and its cache under `dir`.
+fn tokenfetch(server: &MockServer, dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_TokenFetch"));
+    command
+        .args(["--no-config", "--no-cache", "--retry-delay", "1ms", "--custom-chain"])
+        .arg(format!("name=mock,url={}/api,id=9999", server.uri()))
+        .env("MOCK_API_KEY", "key")
+        .env("XDG_CACHE_HOME", dir.join("cache"))
+        .env_remove("TOKENFETCH_CHAIN");
+    command
+}
+
+/// Clones into a fresh project under `dir` from the explorer at `server`.
+fn clone(server: &MockServer, dir: &Path, extra: &[&str]) -> Output {
+    let project = dir.join("project");
+    std::fs::create_dir_all(&project).unwrap();
+    std::fs::write(project.join("foundry.toml"), "[profile.default]\n").unwrap();
+
+    tokenfetch(server, dir)
+        .args(["mock", ADDRESS])
+        .arg(&project)
+        .args(["--merge", "--no-token-info"])
+        .args(extra)
+        .output()
+        .unwrap()
+}
+
+fn read(dir: &Path, file: &str) -> String {
+    std::fs::read_to_string(dir.join("project").join(file)).unwrap_or_else(|e| panic!("{}: {}", file, e))
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn clones_a_single_file_contract() {
+    let server = explorer(&[response("pragma solidity ^0.8.0;\ncontract Token {}")]).await;
+    let dir = tempfile::tempdir().unwrap();
+
+    let output = clone(&server, dir.path(), &[]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(read(dir.path(), "src/Token.sol"), "// SPDX-License-Identifier: MIT\npragma solidity ^0.8.0;\ncontract Token {}");
+    assert_eq!(read(dir.path(), "foundry.toml"), "[profile.default]\n");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn clones_standard_json_and_double_braced_sources() {
+    let input = serde_json::json!({
+        "language": "Solidity",
+        "sources": {
+            "contracts/Token.sol": { "content": "import \"@oz/ERC20.sol\";\ncontract Token { function f() public { if (true) {}}}" },
+            "@oz/ERC20.sol": { "content": "contract ERC20 {}" }
+        },
+        "settings": { "optimizer": { "enabled": true, "runs": 200, "details": {} }, "libraries": {} }
+    });
+    // Compact, so the input has `}}` of its own besides any wrapping braces
+    let input = input.to_string();
+
+    // Etherscan wraps standard-JSON input in an extra pair of braces
+    for source in [input.clone(), format!("{{{}}}", input)] {
+        let server = explorer(&[response(&source)]).await;
+        let dir = tempfile::tempdir().unwrap();
+
+        let output = clone(&server, dir.path(), &[]);
+        assert!(output.status.success(), "{}", stderr(&output));
+        assert_eq!(read(dir.path(), "src/contracts/Token.sol"), "import \"@oz/ERC20.sol\";\ncontract Token { function f() public { if (true) {}}}");
+        assert_eq!(read(dir.path(), "src/@oz/ERC20.sol"), "contract ERC20 {}");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn explorer_errors_fail_with_their_exit_code() {
+    let cases = [
+        (serde_json::json!({ "status": "0", "message": "NOTOK", "result": "Invalid API Key" }), 5, "API error: NOTOK - Invalid API Key"),
+        (response(""), 4, "Contract source code is empty"),
+        (response(r#"{"language": "Solidity"}"#), 5, "malformed `SourceCode`: no sources object"),
+        (serde_json::json!({ "status": "1", "result": [{ "SourceCode": "contract Token {}", "ContractName": 5 }] }), 5, "result[0]: invalid type: integer `5`"),
+    ];
+    for (json, code, message) in cases {
+        let server = explorer(&[json]).await;
+        let dir = tempfile::tempdir().unwrap();
+
+        let output = clone(&server, dir.path(), &[]);
+        assert_eq!(output.status.code(), Some(code), "{}", stderr(&output));
+        assert!(stderr(&output).contains(message), "{}", stderr(&output));
+        assert!(!dir.path().join("project/src").exists());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn waits_out_rate_limits() {
+    let throttled = serde_json::json!({ "status": "0", "message": "NOTOK", "result": "Max calls per sec rate limit reached (5/sec)" });
+    let server = explorer(&[throttled, response("contract Token {}")]).await;
+    let dir = tempfile::tempdir().unwrap();
+
+    let output = clone(&server, dir.path(), &["--rate-limit-retries", "1"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).contains("Explorer is throttling requests"), "{}", stderr(&output));
+    assert!(read(dir.path(), "src/Token.sol").ends_with("contract Token {}"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn refresh_compares_with_the_sources_as_written() {
+    let server = explorer(&[response("contract Token {}\r\n")]).await;
+    let dir = tempfile::tempdir().unwrap();
+    let output = clone(&server, dir.path(), &["--normalize-eol"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(read(dir.path(), "src/Token.sol").ends_with("contract Token {}\n"));
+
+    // Edited here but unchanged upstream, which isn't an upstream change
+    let token = dir.path().join("project/src/Token.sol");
+    std::fs::write(&token, read(dir.path(), "src/Token.sol") + "// mine\n").unwrap();
+    let output = tokenfetch(&server, dir.path()).arg("refresh").arg(dir.path().join("project")).output().unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+    assert!(stderr(&output).contains("0 files changed upstream, 0 added, 0 removed upstream, 1 modified locally"), "{}", stderr(&output));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn diff_exits_with_its_own_code_when_contracts_differ() {
+    let other = "0x2222222222222222222222222222222222222222";
+    let server = MockServer::start().await;
+    for (address, source) in [(ADDRESS, "contract Token {}"), (other, "contract Token { uint256 x; }")] {
+        Mock::given(method("GET"))
+            .and(query_param("action", "getsourcecode"))
+            .and(query_param("address", address))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response(source)))
+            .mount(&server)
+            .await;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let diff = |right: &str| tokenfetch(&server, dir.path()).args(["diff", "mock", ADDRESS, "mock", right, "--names-only"]).output().unwrap();
+
+    let output = diff(ADDRESS);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = diff(other);
+    assert_eq!(output.status.code(), Some(10), "{}", stderr(&output));
+    assert!(stderr(&output).contains("The contracts differ"), "{}", stderr(&output));
+}
+
+/// Archives a fresh clone into `dir`, with a stand-in for `forge init` on the `PATH`.
+#[cfg(unix)]
+fn archive(server: &MockServer, dir: &Path, name: &str) -> Vec<u8> {
+    use std::os::unix::fs::PermissionsExt;
+    let bin = dir.join("bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    let forge = bin.join("forge");
+    std::fs::write(&forge, "#!/bin/sh\n[ \"$1\" = init ] && mkdir -p \"$2/src\" && printf '[profile.default]\\n' > \"$2/foundry.toml\"\nexit 0\n").unwrap();
+    std::fs::set_permissions(&forge, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let archive = dir.join(name);
+    let path = format!("{}:{}", bin.display(), std::env::var("PATH").unwrap_or_default());
+    let output = tokenfetch(server, dir)
+        .args(["mock", ADDRESS, "--no-token-info", "--archive"])
+        .arg(&archive)
+        .env("PATH", path)
+        .env_remove("SOURCE_DATE_EPOCH")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    std::fs::read(archive).unwrap()
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn archives_are_the_same_on_every_run() {
+    let server = explorer(&[response("contract Token {}")]).await;
+    let dir = tempfile::tempdir().unwrap();
+    let first = archive(&server, dir.path(), "first.tar.gz");
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    assert_eq!(archive(&server, dir.path(), "second.tar.gz"), first);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn follow_proxy_clones_the_implementation_too() {
+    let implementation = "0x2222222222222222222222222222222222222222";
+    let server = MockServer::start().await;
+    let mut proxy = response("contract Proxy {}");
+    proxy["result"][0]["ContractName"] = "Proxy".into();
+    proxy["result"][0]["Proxy"] = "1".into();
+    proxy["result"][0]["Implementation"] = implementation.into();
+    // The implementation answers slowly, the proxy's files are written meanwhile
+    for (address, json, delay) in [(ADDRESS, proxy, 0), (implementation, response("contract Token {}"), 300)] {
+        Mock::given(method("GET"))
+            .and(query_param("action", "getsourcecode"))
+            .and(query_param("address", address))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json).set_delay(std::time::Duration::from_millis(delay)))
+            .mount(&server)
+            .await;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let output = clone(&server, dir.path(), &["--follow-proxy"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    assert!(read(dir.path(), "src/Proxy.sol").ends_with("contract Proxy {}"));
+    assert!(read(dir.path(), "src/Token_0x222222/Token.sol").ends_with("contract Token {}"));
+    let manifest: serde_json::Value = serde_json::from_str(&read(dir.path(), ".tokenfetch.json")).unwrap();
+    let cloned: Vec<&str> = manifest["contracts"].as_array().unwrap().iter().map(|c| c["address"].as_str().unwrap()).collect();
+    assert_eq!(cloned, [ADDRESS, implementation]);
+}
+
+#[test]
+fn usage_errors_are_reported_as_json() {
+    for args in [&["eth", "--json"][..], &["eth", "0x1111111111111111111111111111111111111111", "--json", "--bogus"]] {
+        let output = Command::new(env!("CARGO_BIN_EXE_TokenFetch")).arg("--no-config").args(args).env_remove("TOKENFETCH_CHAIN").output().unwrap();
+        assert_eq!(output.status.code(), Some(2), "{}", stderr(&output));
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!((report["ok"].as_bool(), report["error"]["class"].as_str()), (Some(false), Some("invalid_input")), "{}", report);
+    }
+}